@@ -1,14 +1,15 @@
-use barnett_smart_card_protocol::discrete_log_cards;
+use barnett_smart_card_protocol::discrete_log_cards::{
+    self, ShuffleChain, ShuffleLink, TaggedProof,
+};
+use barnett_smart_card_protocol::games::{CardSemantics, FrenchCard, FrenchDeck};
 use barnett_smart_card_protocol::BarnettSmartProtocol;
 
-use anyhow;
-use ark_ff::{to_bytes, UniformRand};
 use ark_std::{rand::Rng, One};
+use blake2::Blake2s;
 use proof_essentials::utils::permutation::Permutation;
 use proof_essentials::utils::rand::sample_vector;
 use proof_essentials::zkp::proofs::{chaum_pedersen_dl_equality, schnorr_identification};
 use rand::thread_rng;
-use std::collections::HashMap;
 use std::iter::Iterator;
 use thiserror::Error;
 
@@ -22,109 +23,17 @@ type CardParameters = discrete_log_cards::Parameters<Curve>;
 type PublicKey = discrete_log_cards::PublicKey<Curve>;
 type SecretKey = discrete_log_cards::PlayerSecretKey<Curve>;
 
-type Card = discrete_log_cards::Card<Curve>;
 type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
 type RevealToken = discrete_log_cards::RevealToken<Curve>;
 
-type ProofKeyOwnership = schnorr_identification::proof::Proof<Curve>;
-type RemaskingProof = chaum_pedersen_dl_equality::proof::Proof<Curve>;
-type RevealProof = chaum_pedersen_dl_equality::proof::Proof<Curve>;
+type ProofKeyOwnership = TaggedProof<schnorr_identification::proof::Proof<Curve>>;
+type RemaskingProof = TaggedProof<chaum_pedersen_dl_equality::proof::Proof<Curve>>;
+type RevealProof = TaggedProof<chaum_pedersen_dl_equality::proof::Proof<Curve>>;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum GameErrors {
     #[error("No such card in hand")]
     CardNotFound,
-
-    #[error("Invalid card")]
-    InvalidCard,
-}
-
-#[derive(PartialEq, Clone, Copy, Eq)]
-pub enum Suite {
-    Club,
-    Diamond,
-    Heart,
-    Spade,
-}
-
-impl Suite {
-    const VALUES: [Self; 4] = [Self::Club, Self::Diamond, Self::Heart, Self::Spade];
-}
-
-#[derive(PartialEq, PartialOrd, Clone, Copy, Eq)]
-pub enum Value {
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    Ten,
-    Jack,
-    Queen,
-    King,
-    Ace,
-}
-
-impl Value {
-    const VALUES: [Self; 13] = [
-        Self::Two,
-        Self::Three,
-        Self::Four,
-        Self::Five,
-        Self::Six,
-        Self::Seven,
-        Self::Eight,
-        Self::Nine,
-        Self::Ten,
-        Self::Jack,
-        Self::Queen,
-        Self::King,
-        Self::Ace,
-    ];
-}
-
-#[derive(PartialEq, Clone, Eq, Copy)]
-pub struct ClassicPlayingCard {
-    value: Value,
-    suite: Suite,
-}
-
-impl ClassicPlayingCard {
-    pub fn new(value: Value, suite: Suite) -> Self {
-        Self { value, suite }
-    }
-}
-
-impl std::fmt::Debug for ClassicPlayingCard {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let suite = match self.suite {
-            Suite::Club => "♣",
-            Suite::Diamond => "♦",
-            Suite::Heart => "♥",
-            Suite::Spade => "♠",
-        };
-
-        let val = match self.value {
-            Value::Two => "2",
-            Value::Three => "3",
-            Value::Four => "4",
-            Value::Five => "5",
-            Value::Six => "6",
-            Value::Seven => "7",
-            Value::Eight => "8",
-            Value::Nine => "9",
-            Value::Ten => "10",
-            Value::Jack => "J",
-            Value::Queen => "Q",
-            Value::King => "K",
-            Value::Ace => "A",
-        };
-
-        write!(f, "{}{}", val, suite)
-    }
 }
 
 #[derive(Clone)]
@@ -134,7 +43,7 @@ struct Player {
     pk: PublicKey,
     proof_key: ProofKeyOwnership,
     cards: Vec<MaskedCard>,
-    opened_cards: Vec<Option<ClassicPlayingCard>>,
+    opened_cards: Vec<Option<FrenchCard>>,
 }
 
 impl Player {
@@ -160,7 +69,7 @@ impl Player {
         &mut self,
         parameters: &CardParameters,
         reveal_tokens: &mut Vec<(RevealToken, RevealProof, PublicKey)>,
-        card_mappings: &HashMap<Card, ClassicPlayingCard>,
+        card_deck: &FrenchDeck<Curve>,
         card: &MaskedCard,
     ) -> Result<(), anyhow::Error> {
         let i = self.cards.iter().position(|&x| x == *card);
@@ -172,11 +81,9 @@ impl Player {
         let own_reveal_token = self.compute_reveal_token(rng, parameters, card)?;
         reveal_tokens.push(own_reveal_token);
 
-        let unmasked_card = CardProtocol::unmask(&parameters, reveal_tokens, card, false)?;
-        let opened_card = card_mappings.get(&unmasked_card);
-        let opened_card = opened_card.ok_or(GameErrors::InvalidCard)?;
+        let opened_card = card_deck.unmask::<Blake2s>(parameters, reveal_tokens, card)?;
 
-        self.opened_cards[i] = Some(*opened_card);
+        self.opened_cards[i] = Some(opened_card);
         Ok(())
     }
 
@@ -187,7 +94,7 @@ impl Player {
         card: &MaskedCard,
     ) -> anyhow::Result<(RevealToken, RevealProof, PublicKey)> {
         let (reveal_token, reveal_proof) =
-            CardProtocol::compute_reveal_token(rng, &pp, &self.sk, &self.pk, card)?;
+            CardProtocol::compute_reveal_token(rng, pp, &self.sk, &self.pk, card)?;
 
         Ok((reveal_token, reveal_proof, self.pk))
     }
@@ -197,49 +104,28 @@ impl Player {
 pub fn open_card(
     parameters: &CardParameters,
     reveal_tokens: &Vec<(RevealToken, RevealProof, PublicKey)>,
-    card_mappings: &HashMap<Card, ClassicPlayingCard>,
+    card_deck: &FrenchDeck<Curve>,
     card: &MaskedCard,
-) -> Result<ClassicPlayingCard, anyhow::Error> {
-    let unmasked_card = CardProtocol::unmask(&parameters, reveal_tokens, card, false)?;
-    let opened_card = card_mappings.get(&unmasked_card);
-    let opened_card = opened_card.ok_or(GameErrors::InvalidCard)?;
-
-    Ok(*opened_card)
-}
-
-fn encode_cards<R: Rng>(rng: &mut R, num_of_cards: usize) -> HashMap<Card, ClassicPlayingCard> {
-    let mut map: HashMap<Card, ClassicPlayingCard> = HashMap::new();
-    let plaintexts = (0..num_of_cards)
-        .map(|_| Card::rand(rng))
-        .collect::<Vec<_>>();
-
-    let mut i = 0;
-    for value in Value::VALUES.iter().copied() {
-        for suite in Suite::VALUES.iter().copied() {
-            let current_card = ClassicPlayingCard::new(value, suite);
-            map.insert(plaintexts[i], current_card);
-            i += 1;
-        }
-    }
+) -> Result<FrenchCard, anyhow::Error> {
+    let opened_card = card_deck.unmask::<Blake2s>(parameters, reveal_tokens, card)?;
 
-    map
+    Ok(opened_card)
 }
 
 fn main() -> anyhow::Result<()> {
     let m = 2;
     let n = 26;
-    let num_of_cards = m * n;
     let rng = &mut thread_rng();
 
     let parameters = CardProtocol::setup(rng, m, n)?;
-    let card_mapping = encode_cards(rng, num_of_cards);
+    let card_deck = FrenchDeck::<Curve>::rand(rng);
 
     let mut andrija = Player::new(rng, &parameters, b"Andrija")?;
     let mut kobi = Player::new(rng, &parameters, b"Kobi")?;
     let mut nico = Player::new(rng, &parameters, b"Nico")?;
     let mut tom = Player::new(rng, &parameters, b"Tom")?;
 
-    let players = vec![andrija.clone(), kobi.clone(), nico.clone(), tom.clone()];
+    let players = [andrija.clone(), kobi.clone(), nico.clone(), tom.clone()];
 
     let mut key_infos = Vec::new();
     let mut proofs = Vec::new();
@@ -251,9 +137,10 @@ fn main() -> anyhow::Result<()> {
     let joint_pk = CardProtocol::compute_aggregate_key(&parameters, &key_infos, Some(&proofs))?;
 
     // Each player should run this computation and verify that all players agree on the initial deck
-    let deck_and_proofs: Vec<(MaskedCard, RemaskingProof)> = card_mapping
-        .keys()
-        .map(|card| CardProtocol::mask(rng, &parameters, &joint_pk, &card, &Scalar::one()))
+    let deck_and_proofs: Vec<(MaskedCard, RemaskingProof)> = card_deck
+        .points()
+        .iter()
+        .map(|card| CardProtocol::mask(rng, &parameters, &joint_pk, card, &Scalar::one()))
         .collect::<Result<Vec<_>, _>>()?;
 
     let deck = deck_and_proofs
@@ -262,93 +149,35 @@ fn main() -> anyhow::Result<()> {
         .collect::<Vec<MaskedCard>>();
 
     // SHUFFLE TIME --------------
-    // 1.a Andrija shuffles first
-    let permutation = Permutation::new(rng, m * n);
-    let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
-
-    let (a_shuffled_deck, a_shuffle_proof) = CardProtocol::shuffle_and_remask(
-        rng,
-        &parameters,
-        &joint_pk,
-        &deck,
-        &masking_factors,
-        &permutation,
-    )?;
-
-    // 1.b everyone checks!
-    CardProtocol::verify_shuffle(
-        &parameters,
-        &joint_pk,
-        &deck,
-        &a_shuffled_deck,
-        &a_shuffle_proof,
-    )?;
-
-    //2.a Kobi shuffles second
-    let permutation = Permutation::new(rng, m * n);
-    let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
-
-    let (k_shuffled_deck, k_shuffle_proof) = CardProtocol::shuffle_and_remask(
-        rng,
-        &parameters,
-        &joint_pk,
-        &a_shuffled_deck,
-        &masking_factors,
-        &permutation,
-    )?;
-
-    //2.b Everyone checks
-    CardProtocol::verify_shuffle(
-        &parameters,
-        &joint_pk,
-        &a_shuffled_deck,
-        &k_shuffled_deck,
-        &k_shuffle_proof,
-    )?;
-
-    //3.a Nico shuffles third
-    let permutation = Permutation::new(rng, m * n);
-    let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
-
-    let (n_shuffled_deck, n_shuffle_proof) = CardProtocol::shuffle_and_remask(
-        rng,
-        &parameters,
-        &joint_pk,
-        &k_shuffled_deck,
-        &masking_factors,
-        &permutation,
-    )?;
-
-    //3.b Everyone checks
-    CardProtocol::verify_shuffle(
-        &parameters,
-        &joint_pk,
-        &k_shuffled_deck,
-        &n_shuffled_deck,
-        &n_shuffle_proof,
-    )?;
-
-    //4.a Tom shuffles last
-    let permutation = Permutation::new(rng, m * n);
-    let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
-
-    let (final_shuffled_deck, final_shuffle_proof) = CardProtocol::shuffle_and_remask(
-        rng,
-        &parameters,
-        &joint_pk,
-        &n_shuffled_deck,
-        &masking_factors,
-        &permutation,
-    )?;
-
-    //4.b Everyone checks before accepting last deck for game
-    CardProtocol::verify_shuffle(
-        &parameters,
-        &joint_pk,
-        &n_shuffled_deck,
-        &final_shuffled_deck,
-        &final_shuffle_proof,
-    )?;
+    // Andrija, Kobi, Nico and Tom shuffle in turn. Everyone checks each shuffle against the
+    // previous deck before accepting it into the chain.
+    let mut chain = ShuffleChain::<Curve>::new(deck, vec![0, 1, 2, 3])?;
+    while let Some(player) = chain.next_shuffler() {
+        let permutation = Permutation::new(rng, m * n);
+        let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
+
+        let (shuffled_deck, proof) = CardProtocol::shuffle_and_remask(
+            rng,
+            &parameters,
+            &joint_pk,
+            &chain.current_deck().to_vec(),
+            &masking_factors,
+            &permutation,
+        )?;
+
+        chain.push(
+            &parameters,
+            &joint_pk,
+            ShuffleLink {
+                player,
+                shuffled_deck,
+                proof,
+            },
+        )?;
+    }
+
+    // Everyone checks that all players shuffled before accepting the final deck for the game
+    let deck = chain.certify(&parameters, &joint_pk)?.cards().to_vec();
 
     // CARDS ARE SHUFFLED. ROUND OF THE GAME CAN BEGIN
 
@@ -379,10 +208,10 @@ fn main() -> anyhow::Result<()> {
     let mut rts_tom = vec![andrija_rt_3, kobi_rt_3, nico_rt_3];
 
     //At this moment players privately open their cards and only they know that values
-    andrija.peek_at_card(&parameters, &mut rts_andrija, &card_mapping, &deck[0])?;
-    kobi.peek_at_card(&parameters, &mut rts_kobi, &card_mapping, &deck[1])?;
-    nico.peek_at_card(&parameters, &mut rts_nico, &card_mapping, &deck[2])?;
-    tom.peek_at_card(&parameters, &mut rts_tom, &card_mapping, &deck[3])?;
+    andrija.peek_at_card(&parameters, &mut rts_andrija, &card_deck, &deck[0])?;
+    kobi.peek_at_card(&parameters, &mut rts_kobi, &card_deck, &deck[1])?;
+    nico.peek_at_card(&parameters, &mut rts_nico, &card_deck, &deck[2])?;
+    tom.peek_at_card(&parameters, &mut rts_tom, &card_deck, &deck[3])?;
 
     /* Here we can add custom logic of a game:
         1. swap card
@@ -422,15 +251,15 @@ fn main() -> anyhow::Result<()> {
     let rt_3 = vec![andrija_rt_3, kobi_rt_3, nico_rt_3, tom_rt_3];
 
     //Everyone computes for each card (except for their own card):
-    let andrija_card = open_card(&parameters, &rt_0, &card_mapping, &deck[0])?;
-    let kobi_card = open_card(&parameters, &rt_1, &card_mapping, &deck[1])?;
-    let nico_card = open_card(&parameters, &rt_2, &card_mapping, &deck[2])?;
-    let tom_card = open_card(&parameters, &rt_3, &card_mapping, &deck[3])?;
-
-    println!("Andrija: {:?}", andrija_card);
-    println!("Kobi: {:?}", kobi_card);
-    println!("Nico: {:?}", nico_card);
-    println!("Tom: {:?}", tom_card);
+    let andrija_card = open_card(&parameters, &rt_0, &card_deck, &deck[0])?;
+    let kobi_card = open_card(&parameters, &rt_1, &card_deck, &deck[1])?;
+    let nico_card = open_card(&parameters, &rt_2, &card_deck, &deck[2])?;
+    let tom_card = open_card(&parameters, &rt_3, &card_deck, &deck[3])?;
+
+    println!("Andrija: {}", andrija_card);
+    println!("Kobi: {}", kobi_card);
+    println!("Nico: {}", nico_card);
+    println!("Tom: {}", tom_card);
 
     Ok(())
 }
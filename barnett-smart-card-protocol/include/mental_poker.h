@@ -0,0 +1,195 @@
+#ifndef MENTAL_POKER_H
+#define MENTAL_POKER_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Outcome of a call.
+ */
+typedef enum MpStatus {
+  MP_STATUS_OK = 0,
+  /**
+   * A required pointer is null.
+   */
+  MP_STATUS_NULL_POINTER = 1,
+  /**
+   * An input is not a valid encoding.
+   */
+  MP_STATUS_INVALID_ENCODING = 2,
+  /**
+   * A proof does not verify.
+   */
+  MP_STATUS_INVALID_PROOF = 3,
+  /**
+   * Any other failure, e.g. a wrong password or an index out of range.
+   */
+  MP_STATUS_PROTOCOL_ERROR = 4,
+  /**
+   * The library panicked.
+   */
+  MP_STATUS_PANIC = 5,
+} MpStatus;
+
+/**
+ * The keypair of the local player.
+ */
+typedef struct MpKeypair MpKeypair;
+
+/**
+ * Protocol parameters, with the window table of the generator.
+ */
+typedef struct MpParameters MpParameters;
+
+/**
+ * Bytes allocated by the library. Release them with `mp_buffer_free`.
+ */
+typedef struct MpBuffer {
+  uint8_t *data;
+  size_t len;
+} MpBuffer;
+
+/**
+ * Bytes borrowed from the caller. `data` may be null if `len` is 0.
+ */
+typedef struct MpSlice {
+  const uint8_t *data;
+  size_t len;
+} MpSlice;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+void mp_buffer_free(struct MpBuffer *buffer);
+
+/**
+ * Generate parameters for a deck of `m * n` cards.
+ */
+enum MpStatus mp_parameters_setup(size_t m, size_t n, struct MpParameters **out);
+
+enum MpStatus mp_parameters_from_bytes(struct MpSlice bytes, struct MpParameters **out);
+
+enum MpStatus mp_parameters_to_bytes(const struct MpParameters *params, struct MpBuffer *out);
+
+enum MpStatus mp_parameters_num_cards(const struct MpParameters *params, size_t *out);
+
+void mp_parameters_free(struct MpParameters *params);
+
+enum MpStatus mp_keygen(const struct MpParameters *params, struct MpKeypair **out);
+
+enum MpStatus mp_keypair_public_key(const struct MpKeypair *keypair, struct MpBuffer *out);
+
+/**
+ * Encode the keypair as the public key followed by the secret key encrypted under `password`.
+ */
+enum MpStatus mp_keypair_export(const struct MpKeypair *keypair,
+                                struct MpSlice password,
+                                struct MpBuffer *out);
+
+enum MpStatus mp_keypair_import(struct MpSlice bytes,
+                                struct MpSlice password,
+                                struct MpKeypair **out);
+
+void mp_keypair_free(struct MpKeypair *keypair);
+
+/**
+ * Prove ownership of the public key, binding the proof to the player's public info.
+ */
+enum MpStatus mp_prove_key_ownership(const struct MpParameters *params,
+                                     const struct MpKeypair *keypair,
+                                     struct MpSlice player_info,
+                                     struct MpBuffer *out_proof);
+
+/**
+ * Verify the key ownership proofs of every player and compute the aggregate key. The i-th public
+ * key, proof and player info belong to the same player.
+ */
+enum MpStatus mp_aggregate_key(const struct MpParameters *params,
+                               const struct MpSlice *public_keys,
+                               const struct MpSlice *proofs,
+                               const struct MpSlice *player_infos,
+                               size_t num_players,
+                               struct MpBuffer *out);
+
+/**
+ * The open card at `index` of the deck.
+ */
+enum MpStatus mp_card(const struct MpParameters *params, size_t index, struct MpBuffer *out_card);
+
+/**
+ * Position of an open card in the deck.
+ */
+enum MpStatus mp_card_index(const struct MpParameters *params,
+                            struct MpSlice card,
+                            size_t *out_index);
+
+/**
+ * Mask an open card under the aggregate key, as in the initial deck.
+ */
+enum MpStatus mp_mask(const struct MpParameters *params,
+                      struct MpSlice shared_key,
+                      struct MpSlice card,
+                      struct MpBuffer *out_masked_card,
+                      struct MpBuffer *out_proof);
+
+enum MpStatus mp_verify_mask(const struct MpParameters *params,
+                             struct MpSlice shared_key,
+                             struct MpSlice card,
+                             struct MpSlice masked_card,
+                             struct MpSlice proof);
+
+/**
+ * Mask every open card under the aggregate key, yielding the deck for the first shuffle.
+ */
+enum MpStatus mp_mask_deck(const struct MpParameters *params,
+                           struct MpSlice shared_key,
+                           struct MpBuffer *out_deck);
+
+/**
+ * The masked card at `index` of a deck.
+ */
+enum MpStatus mp_deck_card(struct MpSlice deck, size_t index, struct MpBuffer *out_masked_card);
+
+/**
+ * Shuffle and remask a deck with a fresh permutation and masking factors.
+ */
+enum MpStatus mp_shuffle_and_remask(const struct MpParameters *params,
+                                    struct MpSlice shared_key,
+                                    struct MpSlice deck,
+                                    struct MpBuffer *out_deck,
+                                    struct MpBuffer *out_proof);
+
+/**
+ * Check that `shuffled_deck` is a correct shuffle of `deck`.
+ */
+enum MpStatus mp_verify_shuffle(const struct MpParameters *params,
+                                struct MpSlice shared_key,
+                                struct MpSlice deck,
+                                struct MpSlice shuffled_deck,
+                                struct MpSlice proof);
+
+/**
+ * Compute the player's reveal token for a masked card.
+ */
+enum MpStatus mp_compute_reveal_token(const struct MpParameters *params,
+                                      const struct MpKeypair *keypair,
+                                      struct MpSlice masked_card,
+                                      struct MpBuffer *out_token);
+
+/**
+ * Unmask a card with the reveal tokens of every player, checking their proofs.
+ */
+enum MpStatus mp_unmask(const struct MpParameters *params,
+                        const struct MpSlice *tokens,
+                        size_t num_tokens,
+                        struct MpSlice masked_card,
+                        struct MpBuffer *out_card);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* MENTAL_POKER_H */
@@ -0,0 +1,387 @@
+// Command-line access to the protocol steps, for debugging a stuck table or reproducing a bug
+// report outside the app. The curve is fixed to Edwards on BN254, as in the wasm bindings.
+//
+// Every value is read and written in its canonical encoding. A path of `-` stands for stdin or
+// stdout. Secret keys are stored in the keystore format, encrypted under the password in
+// `MENTAL_POKER_PASSWORD`.
+
+use barnett_smart_card_protocol::discrete_log_cards::{
+    get_card_elems, BnCard, BnCardProtocol, BnMaskedCard, BnParameters, BnPlayerSecretKey,
+    BnPublicKey, BnRevealToken, BnScalar, BnZKProofKeyOwnership, BnZKProofReveal, BnZKProofShuffle,
+    EncryptedSecretKey, PreparedKey, PreparedParameters,
+};
+use barnett_smart_card_protocol::BarnettSmartProtocol;
+
+use ark_ff::One;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use clap::{Parser, Subcommand};
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use rand::thread_rng;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+
+const PASSWORD_VAR: &str = "MENTAL_POKER_PASSWORD";
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[clap(
+    name = "mental-poker",
+    about = "Run steps of the card protocol on files"
+)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate parameters for a deck of m * n cards
+    Setup {
+        #[clap(short)]
+        m: usize,
+        #[clap(short)]
+        n: usize,
+        #[clap(long, default_value = "-")]
+        out: String,
+    },
+    /// Generate a keypair. The public key goes to `out`, the encrypted secret key to `key`
+    Keygen {
+        #[clap(long)]
+        params: String,
+        #[clap(long)]
+        key: String,
+        #[clap(long, default_value = "-")]
+        out: String,
+    },
+    /// Prove ownership of a public key, binding the proof to the player's public info
+    ProveKey {
+        #[clap(long)]
+        params: String,
+        #[clap(long)]
+        pk: String,
+        #[clap(long)]
+        key: String,
+        #[clap(long)]
+        info: String,
+        #[clap(long, default_value = "-")]
+        out: String,
+    },
+    /// Verify the key ownership proofs of every player and compute the aggregate key. The n-th
+    /// `pk`, `proof` and `info` belong to the same player
+    Aggregate {
+        #[clap(long)]
+        params: String,
+        #[clap(long = "pk", required = true)]
+        pks: Vec<String>,
+        #[clap(long = "proof", required = true)]
+        proofs: Vec<String>,
+        #[clap(long = "info", required = true)]
+        infos: Vec<String>,
+        #[clap(long, default_value = "-")]
+        out: String,
+    },
+    /// Mask the open cards under the aggregate key, yielding the initial deck
+    MaskDeck {
+        #[clap(long)]
+        params: String,
+        #[clap(long)]
+        shared_key: String,
+        #[clap(long, default_value = "-")]
+        out: String,
+    },
+    /// Shuffle and remask a deck. The shuffled deck goes to `out`, the proof to `proof`
+    Shuffle {
+        #[clap(long)]
+        params: String,
+        #[clap(long)]
+        shared_key: String,
+        #[clap(long)]
+        deck: String,
+        #[clap(long)]
+        proof: String,
+        #[clap(long, default_value = "-")]
+        out: String,
+    },
+    /// Verify that `shuffled` is a correct shuffle of `deck`
+    VerifyShuffle {
+        #[clap(long)]
+        params: String,
+        #[clap(long)]
+        shared_key: String,
+        #[clap(long)]
+        deck: String,
+        #[clap(long)]
+        shuffled: String,
+        #[clap(long)]
+        proof: String,
+    },
+    /// Compute a reveal token for the card at `position` of a deck. Writes the token, its proof
+    /// and the player's public key
+    RevealToken {
+        #[clap(long)]
+        params: String,
+        #[clap(long)]
+        pk: String,
+        #[clap(long)]
+        key: String,
+        #[clap(long)]
+        deck: String,
+        #[clap(long)]
+        position: usize,
+        #[clap(long, default_value = "-")]
+        out: String,
+    },
+    /// Unmask the card at `position` of a deck with the reveal tokens of every player
+    Unmask {
+        #[clap(long)]
+        params: String,
+        #[clap(long)]
+        deck: String,
+        #[clap(long)]
+        position: usize,
+        #[clap(long = "token", required = true)]
+        tokens: Vec<String>,
+        #[clap(long, default_value = "-")]
+        out: String,
+    },
+    /// Print the index of an open card in the deck
+    Decode {
+        #[clap(long)]
+        params: String,
+        #[clap(long)]
+        card: String,
+    },
+}
+
+fn read_bytes(path: &str) -> CliResult<Vec<u8>> {
+    if path == "-" {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    } else {
+        Ok(fs::read(path)?)
+    }
+}
+
+fn write_bytes(path: &str, bytes: &[u8]) -> CliResult<()> {
+    if path == "-" {
+        io::stdout().write_all(bytes)?;
+    } else {
+        fs::write(path, bytes)?;
+    }
+
+    Ok(())
+}
+
+fn read<T: CanonicalDeserialize>(path: &str) -> CliResult<T> {
+    let bytes = read_bytes(path)?;
+    Ok(
+        T::deserialize(bytes.as_slice())
+            .map_err(|e| format!("failed to decode {}: {}", path, e))?,
+    )
+}
+
+fn write<T: CanonicalSerialize>(path: &str, value: &T) -> CliResult<()> {
+    let mut bytes = Vec::new();
+    value.serialize(&mut bytes)?;
+    write_bytes(path, &bytes)
+}
+
+fn password() -> CliResult<String> {
+    std::env::var(PASSWORD_VAR).map_err(|_| format!("{} is not set", PASSWORD_VAR).into())
+}
+
+fn read_secret_key(path: &str) -> CliResult<BnPlayerSecretKey> {
+    let keystore = EncryptedSecretKey::from_bytes(&read_bytes(path)?)?;
+    Ok(keystore.decrypt(password()?.as_bytes())?)
+}
+
+fn card_at(deck: &[BnMaskedCard], position: usize) -> CliResult<BnMaskedCard> {
+    deck.get(position).copied().ok_or_else(|| {
+        format!(
+            "position {} is out of range for a deck of {} cards",
+            position,
+            deck.len()
+        )
+        .into()
+    })
+}
+
+fn run(command: Command) -> CliResult<()> {
+    let rng = &mut thread_rng();
+
+    match command {
+        Command::Setup { m, n, out } => {
+            let parameters = BnCardProtocol::setup(rng, m, n)?;
+            write(&out, &parameters)
+        }
+        Command::Keygen { params, key, out } => {
+            let parameters: BnParameters = read(&params)?;
+            let (pk, sk) = BnCardProtocol::player_keygen(rng, &parameters)?;
+            let keystore = sk.encrypt(rng, password()?.as_bytes())?;
+            write_bytes(&key, &keystore.to_bytes())?;
+            write(&out, &pk)
+        }
+        Command::ProveKey {
+            params,
+            pk,
+            key,
+            info,
+            out,
+        } => {
+            let parameters: BnParameters = read(&params)?;
+            let pk: BnPublicKey = read(&pk)?;
+            let sk = read_secret_key(&key)?;
+            let proof =
+                BnCardProtocol::prove_key_ownership(rng, &parameters, &pk, &sk, info.as_bytes())?;
+            write(&out, &proof)
+        }
+        Command::Aggregate {
+            params,
+            pks,
+            proofs,
+            infos,
+            out,
+        } => {
+            if pks.len() != proofs.len() || pks.len() != infos.len() {
+                return Err("every player needs a pk, a proof and an info".into());
+            }
+
+            let parameters: BnParameters = read(&params)?;
+            let mut key_infos = Vec::with_capacity(pks.len());
+            let mut key_proofs = Vec::with_capacity(pks.len());
+            for ((pk, proof), info) in pks.iter().zip(proofs.iter()).zip(infos.iter()) {
+                let pk: BnPublicKey = read(pk)?;
+                let proof: BnZKProofKeyOwnership = read(proof)?;
+                key_infos.push((pk, info.as_bytes().to_vec()));
+                key_proofs.push(proof);
+            }
+
+            let shared_key =
+                BnCardProtocol::compute_aggregate_key(&parameters, &key_infos, Some(&key_proofs))?;
+            write(&out, &shared_key)
+        }
+        Command::MaskDeck {
+            params,
+            shared_key,
+            out,
+        } => {
+            let parameters: BnParameters = read(&params)?;
+            let shared_key: BnPublicKey = read(&shared_key)?;
+            let prepared_parameters = PreparedParameters::new(&parameters);
+            let prepared_key = PreparedKey::new(&shared_key);
+            let deck = get_card_elems(parameters.num_cards())
+                .iter()
+                .map(|card| {
+                    BnCardProtocol::mask_prepared(
+                        rng,
+                        &prepared_parameters,
+                        &prepared_key,
+                        card,
+                        &BnScalar::one(),
+                    )
+                    .map(|(masked_card, _)| masked_card)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            write(&out, &deck)
+        }
+        Command::Shuffle {
+            params,
+            shared_key,
+            deck,
+            proof,
+            out,
+        } => {
+            let parameters: BnParameters = read(&params)?;
+            let shared_key: BnPublicKey = read(&shared_key)?;
+            let deck: Vec<BnMaskedCard> = read(&deck)?;
+
+            let permutation = Permutation::new(rng, deck.len());
+            let masking_factors: Vec<BnScalar> = sample_vector(rng, deck.len());
+            let (shuffled_deck, shuffle_proof) = BnCardProtocol::shuffle_and_remask(
+                rng,
+                &parameters,
+                &shared_key,
+                &deck,
+                &masking_factors,
+                &permutation,
+            )?;
+            write(&proof, &shuffle_proof)?;
+            write(&out, &shuffled_deck)
+        }
+        Command::VerifyShuffle {
+            params,
+            shared_key,
+            deck,
+            shuffled,
+            proof,
+        } => {
+            let parameters: BnParameters = read(&params)?;
+            let shared_key: BnPublicKey = read(&shared_key)?;
+            let deck: Vec<BnMaskedCard> = read(&deck)?;
+            let shuffled: Vec<BnMaskedCard> = read(&shuffled)?;
+            let proof: BnZKProofShuffle = read(&proof)?;
+
+            BnCardProtocol::verify_shuffle(&parameters, &shared_key, &deck, &shuffled, &proof)?;
+            println!("ok");
+            Ok(())
+        }
+        Command::RevealToken {
+            params,
+            pk,
+            key,
+            deck,
+            position,
+            out,
+        } => {
+            let parameters: BnParameters = read(&params)?;
+            let pk: BnPublicKey = read(&pk)?;
+            let sk = read_secret_key(&key)?;
+            let deck: Vec<BnMaskedCard> = read(&deck)?;
+            let masked_card = card_at(&deck, position)?;
+
+            let (token, proof) =
+                BnCardProtocol::compute_reveal_token(rng, &parameters, &sk, &pk, &masked_card)?;
+            write(&out, &(token, proof, pk))
+        }
+        Command::Unmask {
+            params,
+            deck,
+            position,
+            tokens,
+            out,
+        } => {
+            let parameters: BnParameters = read(&params)?;
+            let deck: Vec<BnMaskedCard> = read(&deck)?;
+            let masked_card = card_at(&deck, position)?;
+            let decryption_key = tokens
+                .iter()
+                .map(|path| read::<(BnRevealToken, BnZKProofReveal, BnPublicKey)>(path))
+                .collect::<CliResult<Vec<_>>>()?;
+
+            let card = BnCardProtocol::unmask(&parameters, &decryption_key, &masked_card, false)?;
+            write(&out, &card)
+        }
+        Command::Decode { params, card } => {
+            let parameters: BnParameters = read(&params)?;
+            let card: BnCard = read(&card)?;
+            let index = get_card_elems(parameters.num_cards())
+                .iter()
+                .position(|open| *open == card)
+                .ok_or("not a card of this deck")?;
+            println!("{}", index);
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli.command) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,351 @@
+// Batch verification of masking and remasking proofs.
+//
+// Both are Chaum-Pedersen proofs `(a, b, r)` that `x = s * g` and `y = s * h` for the same `s`,
+// where `g` is the ElGamal generator and `h` the aggregate key. A single proof checks
+// `r * g = a + c * x` and `r * h = b + c * y`. For a batch we draw small exponents `d_i, e_i`
+// and check the random linear combination
+//
+//     (sum d_i r_i) * g + (sum e_i r_i) * h - sum (d_i a_i + e_i b_i + d_i c_i x_i + e_i c_i y_i) = 0
+//
+// with one multi-scalar multiplication. A cheating prover passes with probability about
+// `2^-128`. The exponents are derived from the whole batch, so that the check is deterministic
+// and needs no randomness from the verifier. When the combined check fails we fall back to
+// verifying the proofs one by one to find the culprit.
+
+use super::{Card, DLCards, MaskedCard, Parameters, PublicKey, TranscriptHash};
+use super::{TaggedProof, MASKING_RNG_SEED, REMASKING_RNG_SEED};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::msm::VariableBaseMSM;
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{to_bytes, One, PrimeField, UniformRand, Zero};
+use ark_marlin::rng::FiatShamirRng;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::Rng;
+use ark_std::string::{String, ToString};
+use ark_std::vec::Vec;
+use blake2::Blake2s;
+use proof_essentials::error::CryptoError;
+use proof_essentials::zkp::proofs::chaum_pedersen_dl_equality::proof::Proof;
+
+const BATCH_RNG_SEED: &[u8] = b"Batch Verification";
+
+/// Commitments and response of a Chaum-Pedersen proof, in the order of its canonical encoding.
+pub(super) type ProofElements<C> = (
+    <C as ProjectiveCurve>::Affine,
+    <C as ProjectiveCurve>::Affine,
+    <C as ProjectiveCurve>::ScalarField,
+);
+
+/// Read the commitments and response of a Chaum-Pedersen proof, which the proof type keeps private.
+pub(super) fn proof_elements<C: ProjectiveCurve>(
+    proof: &Proof<C>,
+) -> Result<ProofElements<C>, CardProtocolError> {
+    let mut bytes = Vec::new();
+    proof
+        .serialize(&mut bytes)
+        .map_err(|e| CardProtocolError::IoError(e.to_string()))?;
+    CanonicalDeserialize::deserialize(&bytes[..])
+        .map_err(|e| CardProtocolError::IoError(e.to_string()))
+}
+
+/// Assemble a Chaum-Pedersen proof from its commitments and response.
+pub(super) fn proof_from_elements<C: ProjectiveCurve>(
+    elements: &ProofElements<C>,
+) -> Result<Proof<C>, CardProtocolError> {
+    let mut bytes = Vec::new();
+    elements
+        .serialize(&mut bytes)
+        .map_err(|e| CardProtocolError::IoError(e.to_string()))?;
+    CanonicalDeserialize::deserialize(&bytes[..])
+        .map_err(|e| CardProtocolError::IoError(e.to_string()))
+}
+
+/// Chaum-Pedersen proof that `x = s * g` and `y = s * h`, with the Fiat-Shamir `seed` and hash
+/// `D`.
+pub(super) fn prove_chaum_pedersen<C: ProjectiveCurve, D: TranscriptHash, R: Rng>(
+    rng: &mut R,
+    seed: &[u8],
+    (g, h): (&C::Affine, &C::Affine),
+    (x, y): (&C::Affine, &C::Affine),
+    s: &C::ScalarField,
+) -> Result<TaggedProof<Proof<C>>, CardProtocolError> {
+    let w = C::ScalarField::rand(rng);
+    let a = g.mul(w.into_repr()).into_affine();
+    let b = h.mul(w.into_repr()).into_affine();
+    let c = D::chaum_pedersen_challenge::<C>(seed, (g, h), (x, y), (&a, &b))?;
+
+    Ok(TaggedProof::new(
+        D::ID,
+        proof_from_elements::<C>(&(a, b, w + c * s))?,
+    ))
+}
+
+/// Verify a Chaum-Pedersen proof that `x = s * g` and `y = s * h` for some `s`, computed with the
+/// Fiat-Shamir `seed` and hash `D`.
+pub(super) fn verify_chaum_pedersen<C: ProjectiveCurve, D: TranscriptHash>(
+    seed: &[u8],
+    (g, h): (&C::Affine, &C::Affine),
+    (x, y): (&C::Affine, &C::Affine),
+    proof: &TaggedProof<Proof<C>>,
+) -> Result<(), CryptoError> {
+    let error = || CryptoError::ProofVerificationError(String::from("Chaum-Pedersen"));
+    let (a, b, r) = proof_elements(proof.expect_hash::<D>()?).map_err(|_| error())?;
+    let c = D::chaum_pedersen_challenge::<C>(seed, (g, h), (x, y), (&a, &b))?;
+
+    if g.mul(r.into_repr()) != a.into_projective() + x.mul(c.into_repr())
+        || h.mul(r.into_repr()) != b.into_projective() + y.mul(c.into_repr())
+    {
+        return Err(error());
+    }
+
+    Ok(())
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> DLCards<C, D> {
+    /// Verify `proofs[i]` for the masking of `cards[i]` into `masked_cards[i]`, for every `i`.
+    /// On failure the error holds the index of the first invalid proof.
+    pub fn verify_mask_batch(
+        pp: &Parameters<C>,
+        shared_key: &PublicKey<C>,
+        cards: &[Card<C>],
+        masked_cards: &[MaskedCard<C>],
+        proofs: &[<Self as BarnettSmartProtocol>::ZKProofMasking],
+    ) -> Result<(), CardProtocolError> {
+        if cards.len() != proofs.len() || masked_cards.len() != proofs.len() {
+            return Err(CardProtocolError::BatchSizeMismatch);
+        }
+
+        let minus_one = -C::ScalarField::one();
+        let statements = cards
+            .iter()
+            .zip(masked_cards.iter())
+            .map(|(card, masked_card)| {
+                let negative_original = card.0.mul(minus_one).into_affine();
+                (masked_card.0, masked_card.1 + negative_original)
+            })
+            .collect::<Vec<_>>();
+
+        if Self::batch_check(MASKING_RNG_SEED, pp, shared_key, &statements, proofs)? {
+            return Ok(());
+        }
+
+        for (i, ((card, masked_card), proof)) in
+            cards.iter().zip(masked_cards).zip(proofs).enumerate()
+        {
+            if Self::verify_mask(pp, shared_key, card, masked_card, proof).is_err() {
+                return Err(CardProtocolError::InvalidProofInBatch(i));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verify `proofs[i]` for the remasking of `original_masked[i]` into `remasked[i]`, for
+    /// every `i`. On failure the error holds the index of the first invalid proof.
+    pub fn verify_remask_batch(
+        pp: &Parameters<C>,
+        shared_key: &PublicKey<C>,
+        original_masked: &[MaskedCard<C>],
+        remasked: &[MaskedCard<C>],
+        proofs: &[<Self as BarnettSmartProtocol>::ZKProofRemasking],
+    ) -> Result<(), CardProtocolError> {
+        if original_masked.len() != proofs.len() || remasked.len() != proofs.len() {
+            return Err(CardProtocolError::BatchSizeMismatch);
+        }
+
+        let minus_one = -C::ScalarField::one();
+        let statements = original_masked
+            .iter()
+            .zip(remasked.iter())
+            .map(|(original, remasked)| {
+                let statement_cipher = *remasked + *original * minus_one;
+                (statement_cipher.0, statement_cipher.1)
+            })
+            .collect::<Vec<_>>();
+
+        if Self::batch_check(REMASKING_RNG_SEED, pp, shared_key, &statements, proofs)? {
+            return Ok(());
+        }
+
+        for (i, ((original, remasked), proof)) in
+            original_masked.iter().zip(remasked).zip(proofs).enumerate()
+        {
+            if Self::verify_remask(pp, shared_key, original, remasked, proof).is_err() {
+                return Err(CardProtocolError::InvalidProofInBatch(i));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Combined check of Chaum-Pedersen proofs for `statements` under the generator and
+    /// `shared_key`. `seed` must be the one the proofs were computed with.
+    fn batch_check(
+        seed: &[u8],
+        pp: &Parameters<C>,
+        shared_key: &PublicKey<C>,
+        statements: &[(C::Affine, C::Affine)],
+        proofs: &[TaggedProof<Proof<C>>],
+    ) -> Result<bool, CardProtocolError> {
+        let g = pp.enc_parameters.generator;
+        let h = *shared_key;
+
+        let mut batch_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![BATCH_RNG_SEED]?);
+        let mut elements = Vec::with_capacity(proofs.len());
+        for ((x, y), proof) in statements.iter().zip(proofs) {
+            // A proof for another transcript hash, or one that does not decode, fails the batch,
+            // so that the proof-by-proof check locates it
+            let (a, b, r) = match proof.expect_hash::<D>().map(|proof| proof_elements(proof)) {
+                Ok(Ok(elements)) => elements,
+                _ => return Ok(false),
+            };
+            let c = D::chaum_pedersen_challenge::<C>(seed, (&g, &h), (x, y), (&a, &b))?;
+
+            batch_rng.absorb(&to_bytes![x, y, a, b, r]?);
+            elements.push((a, b, r, c));
+        }
+
+        let mut bases = Vec::with_capacity(4 * proofs.len() + 2);
+        let mut scalars = Vec::with_capacity(4 * proofs.len() + 2);
+        let mut g_scalar = C::ScalarField::zero();
+        let mut h_scalar = C::ScalarField::zero();
+        for ((x, y), (a, b, r, c)) in statements.iter().zip(elements) {
+            let d = C::ScalarField::from(batch_rng.gen::<u128>());
+            let e = C::ScalarField::from(batch_rng.gen::<u128>());
+
+            g_scalar += d * r;
+            h_scalar += e * r;
+            bases.extend_from_slice(&[a, b, *x, *y]);
+            scalars.extend_from_slice(&[-d, -e, -(d * c), -(e * c)]);
+        }
+        bases.extend_from_slice(&[g, h]);
+        scalars.extend_from_slice(&[g_scalar, h_scalar]);
+
+        let scalars = scalars
+            .iter()
+            .map(|scalar| scalar.into_repr())
+            .collect::<Vec<_>>();
+
+        Ok(VariableBaseMSM::multi_scalar_mul(&bases, &scalars).is_zero())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::discrete_log_cards::test_utils::setup_players;
+    use crate::discrete_log_cards::{self, FiatShamirHash};
+    use crate::error::CardProtocolError;
+    use crate::BarnettSmartProtocol;
+
+    use ark_ff::UniformRand;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+
+    type Card = discrete_log_cards::Card<Curve>;
+
+    #[test]
+    fn test_batch_verification() {
+        let rng = &mut thread_rng();
+        let m = 2;
+        let n = 4;
+
+        let parameters = CardProtocol::setup(rng, m, n).unwrap();
+        let (_, aggregate_key) = setup_players(rng, &parameters, 3);
+
+        let cards = (0..m * n).map(|_| Card::rand(rng)).collect::<Vec<_>>();
+        let (masked, mask_proofs): (Vec<_>, Vec<_>) = cards
+            .iter()
+            .map(|card| {
+                let alpha = Scalar::rand(rng);
+                CardProtocol::mask(rng, &parameters, &aggregate_key, card, &alpha).unwrap()
+            })
+            .unzip();
+        assert_eq!(
+            CardProtocol::verify_mask_batch(
+                &parameters,
+                &aggregate_key,
+                &cards,
+                &masked,
+                &mask_proofs
+            ),
+            Ok(())
+        );
+
+        let (remasked, mut remask_proofs): (Vec<_>, Vec<_>) = masked
+            .iter()
+            .map(|masked_card| {
+                let alpha = Scalar::rand(rng);
+                CardProtocol::remask(rng, &parameters, &aggregate_key, masked_card, &alpha).unwrap()
+            })
+            .unzip();
+        assert_eq!(
+            CardProtocol::verify_remask_batch(
+                &parameters,
+                &aggregate_key,
+                &masked,
+                &remasked,
+                &remask_proofs
+            ),
+            Ok(())
+        );
+
+        // A proof for another card
+        let mut wrong_cards = cards.clone();
+        wrong_cards[5] = Card::rand(rng);
+        assert_eq!(
+            CardProtocol::verify_mask_batch(
+                &parameters,
+                &aggregate_key,
+                &wrong_cards,
+                &masked,
+                &mask_proofs
+            ),
+            Err(CardProtocolError::InvalidProofInBatch(5))
+        );
+
+        remask_proofs.swap(2, 6);
+        assert_eq!(
+            CardProtocol::verify_remask_batch(
+                &parameters,
+                &aggregate_key,
+                &masked,
+                &remasked,
+                &remask_proofs
+            ),
+            Err(CardProtocolError::InvalidProofInBatch(2))
+        );
+
+        // A proof computed with another transcript hash
+        let mut mistagged = mask_proofs.clone();
+        mistagged[3].hash = FiatShamirHash::Sha256;
+        assert_eq!(
+            CardProtocol::verify_mask_batch(
+                &parameters,
+                &aggregate_key,
+                &cards,
+                &masked,
+                &mistagged
+            ),
+            Err(CardProtocolError::InvalidProofInBatch(3))
+        );
+
+        assert_eq!(
+            CardProtocol::verify_remask_batch(
+                &parameters,
+                &aggregate_key,
+                &masked[1..],
+                &remasked,
+                &remask_proofs
+            ),
+            Err(CardProtocolError::BatchSizeMismatch)
+        );
+    }
+}
@@ -0,0 +1,910 @@
+// R1CS gadgets for the discrete log card protocol, so that a circuit can prove that cards were
+// correctly masked, remasked and revealed.
+//
+// The gadgets are generic over a `CurveVar` for the protocol curve. For Ed-on-BN254 this is
+// `ark_ed_on_bn254::constraints::EdwardsVar`, whose constraint field is the scalar field of BN254.
+// Scalars of the protocol curve live in another field, so they are allocated as their
+// little-endian bits.
+//
+// A Chaum-Pedersen proof `(a, b, r)` for `x = s * g` and `y = s * h` is checked against its
+// challenge `c` as `r * g = a + c * x` and `r * h = b + c * y`. These equations hold for any `c`
+// when `a = r * g - c * x` and `b = r * h - c * y`, so they only prove something if `c` is the
+// Fiat-Shamir challenge of the statement and of `(a, b)`. With the `Poseidon` transcript hash the
+// challenge is a Poseidon hash of the coordinates of the points, and `DLCardsGadget` recomputes it
+// in the circuit with the `*_challenge` gadgets. Other hashes derive it with a `FiatShamirRng`,
+// whose ChaCha stream cannot be recomputed in a circuit, so the challenge is a public input
+// instead. `ChaumPedersenProofVar` always allocates `a` and `b` as public inputs, and whoever
+// verifies the SNARK must then recompute each challenge with the native `*_challenge` functions
+// from those commitments and the public statement, and reject the SNARK if it was given another.
+
+use super::batch::proof_elements;
+use super::hash::{poseidon_full_round, poseidon_mds, poseidon_round_constants};
+use super::{Card, DLCards, MaskedCard, Parameters, PublicKey, RevealToken};
+use super::{Poseidon, PoseidonField, TranscriptHash};
+use super::{MASKING_RNG_SEED, REMASKING_RNG_SEED, REVEAL_RNG_SEED};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{BigInteger, Field, One, PrimeField};
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_r1cs_std::ToConstraintFieldGadget;
+use ark_relations::r1cs::{Namespace, SynthesisError};
+use ark_std::borrow::Borrow;
+use ark_std::marker::PhantomData;
+use ark_std::{vec, vec::Vec};
+use proof_essentials::zkp::proofs::chaum_pedersen_dl_equality::proof::Proof;
+
+/// Field over which the constraints for curve `C` are expressed.
+pub type ConstraintF<C> = <<C as ProjectiveCurve>::BaseField as Field>::BasePrimeField;
+
+/// A scalar of the protocol curve, as its little-endian bits.
+#[derive(Clone)]
+pub struct ScalarVar<C: ProjectiveCurve> {
+    bits: Vec<Boolean<ConstraintF<C>>>,
+}
+
+impl<C: ProjectiveCurve> ScalarVar<C> {
+    pub fn bits(&self) -> &[Boolean<ConstraintF<C>>] {
+        &self.bits
+    }
+}
+
+impl<C: ProjectiveCurve> AllocVar<C::ScalarField, ConstraintF<C>> for ScalarVar<C> {
+    fn new_variable<T: Borrow<C::ScalarField>>(
+        cs: impl Into<Namespace<ConstraintF<C>>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let bits = f().map(|scalar| scalar.borrow().into_repr().to_bits_le());
+
+        let bits = (0..C::ScalarField::size_in_bits())
+            .map(|i| {
+                Boolean::new_variable(
+                    cs.clone(),
+                    || bits.as_ref().map(|bits| bits[i]).map_err(|e| *e),
+                    mode,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { bits })
+    }
+}
+
+/// An el-Gamal plaintext: an open card or a reveal token.
+#[derive(Clone)]
+pub struct PlaintextVar<C: ProjectiveCurve, GC: CurveVar<C, ConstraintF<C>>> {
+    pub point: GC,
+    _curve: PhantomData<C>,
+}
+
+pub type CardVar<C, GC> = PlaintextVar<C, GC>;
+pub type RevealTokenVar<C, GC> = PlaintextVar<C, GC>;
+
+impl<C: ProjectiveCurve, GC: CurveVar<C, ConstraintF<C>>> PlaintextVar<C, GC> {
+    pub fn new(point: GC) -> Self {
+        Self {
+            point,
+            _curve: PhantomData,
+        }
+    }
+}
+
+impl<C: ProjectiveCurve, GC: CurveVar<C, ConstraintF<C>>> AllocVar<Card<C>, ConstraintF<C>>
+    for PlaintextVar<C, GC>
+{
+    fn new_variable<T: Borrow<Card<C>>>(
+        cs: impl Into<Namespace<ConstraintF<C>>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let point = GC::new_variable(cs, || f().map(|plaintext| plaintext.borrow().0), mode)?;
+        Ok(Self::new(point))
+    }
+}
+
+/// An el-Gamal ciphertext `(c1, c2)`, i.e. a masked card.
+#[derive(Clone)]
+pub struct MaskedCardVar<C: ProjectiveCurve, GC: CurveVar<C, ConstraintF<C>>> {
+    pub c1: GC,
+    pub c2: GC,
+    _curve: PhantomData<C>,
+}
+
+impl<C: ProjectiveCurve, GC: CurveVar<C, ConstraintF<C>>> MaskedCardVar<C, GC>
+where
+    for<'a> &'a GC: GroupOpsBounds<'a, C, GC>,
+{
+    pub fn new(c1: GC, c2: GC) -> Self {
+        Self {
+            c1,
+            c2,
+            _curve: PhantomData,
+        }
+    }
+
+    /// Mask `card` with randomness `r`: `(r * g, card + r * pk)`.
+    pub fn mask(
+        generator: &GC,
+        shared_key: &GC,
+        card: &CardVar<C, GC>,
+        r: &ScalarVar<C>,
+    ) -> Result<Self, SynthesisError> {
+        let c1 = generator.scalar_mul_le(r.bits().iter())?;
+        let c2 = &card.point + &shared_key.scalar_mul_le(r.bits().iter())?;
+        Ok(Self::new(c1, c2))
+    }
+
+    /// Add an encryption of zero with randomness `r`.
+    pub fn remask(
+        &self,
+        generator: &GC,
+        shared_key: &GC,
+        r: &ScalarVar<C>,
+    ) -> Result<Self, SynthesisError> {
+        let c1 = &self.c1 + &generator.scalar_mul_le(r.bits().iter())?;
+        let c2 = &self.c2 + &shared_key.scalar_mul_le(r.bits().iter())?;
+        Ok(Self::new(c1, c2))
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        Self::new(&self.c1 + &other.c1, &self.c2 + &other.c2)
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        Self::new(&self.c1 - &other.c1, &self.c2 - &other.c2)
+    }
+
+    pub fn enforce_equal(&self, other: &Self) -> Result<(), SynthesisError> {
+        self.c1.enforce_equal(&other.c1)?;
+        self.c2.enforce_equal(&other.c2)
+    }
+}
+
+impl<C: ProjectiveCurve, GC: CurveVar<C, ConstraintF<C>>> AllocVar<MaskedCard<C>, ConstraintF<C>>
+    for MaskedCardVar<C, GC>
+where
+    for<'a> &'a GC: GroupOpsBounds<'a, C, GC>,
+{
+    fn new_variable<T: Borrow<MaskedCard<C>>>(
+        cs: impl Into<Namespace<ConstraintF<C>>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let masked_card = f().map(|masked_card| *masked_card.borrow());
+
+        let c1 = GC::new_variable(
+            cs.clone(),
+            || masked_card.map(|masked_card| masked_card.0),
+            mode,
+        )?;
+        let c2 = GC::new_variable(cs, || masked_card.map(|masked_card| masked_card.1), mode)?;
+        Ok(Self::new(c1, c2))
+    }
+}
+
+/// A Chaum-Pedersen proof `(a, b, r)`, as used for masking, remasking and reveal proofs. Its
+/// commitments `a` and `b` are public inputs of the circuit.
+#[derive(Clone)]
+pub struct ChaumPedersenProofVar<C: ProjectiveCurve, GC: CurveVar<C, ConstraintF<C>>> {
+    a: GC,
+    b: GC,
+    r: ScalarVar<C>,
+}
+
+impl<C: ProjectiveCurve, GC: CurveVar<C, ConstraintF<C>>> ChaumPedersenProofVar<C, GC>
+where
+    for<'a> &'a GC: GroupOpsBounds<'a, C, GC>,
+{
+    /// Allocate `proof` with its commitments as public inputs and its response as a witness. The
+    /// commitments cannot be witnesses: the verifier of the SNARK needs them to recompute the
+    /// challenge, without which a prover could pick commitments that fit any challenge.
+    pub fn new_input<T: Borrow<Proof<C>>>(
+        cs: impl Into<Namespace<ConstraintF<C>>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let elements = f().and_then(|proof| {
+            proof_elements(proof.borrow()).map_err(|_| SynthesisError::AssignmentMissing)
+        });
+
+        let a = GC::new_input(cs.clone(), || elements.map(|(a, _, _)| a))?;
+        let b = GC::new_input(cs.clone(), || elements.map(|(_, b, _)| b))?;
+        let r = ScalarVar::new_witness(cs, || elements.map(|(_, _, r)| r))?;
+        Ok(Self { a, b, r })
+    }
+
+    /// Enforce that the proof holds for `x = s * g` and `y = s * h` with the given challenge.
+    pub fn enforce_valid(
+        &self,
+        (g, h): (&GC, &GC),
+        (x, y): (&GC, &GC),
+        challenge: &ScalarVar<C>,
+    ) -> Result<(), SynthesisError> {
+        let rg = g.scalar_mul_le(self.r.bits().iter())?;
+        rg.enforce_equal(&(&self.a + &x.scalar_mul_le(challenge.bits().iter())?))?;
+
+        let rh = h.scalar_mul_le(self.r.bits().iter())?;
+        rh.enforce_equal(&(&self.b + &y.scalar_mul_le(challenge.bits().iter())?))
+    }
+}
+
+/// A player's reveal token for a masked card, its proof, the player's public key and the
+/// challenge of the proof.
+pub type DecryptionShareVar<C, GC> = (
+    RevealTokenVar<C, GC>,
+    ChaumPedersenProofVar<C, GC>,
+    GC,
+    ScalarVar<C>,
+);
+
+/// Gadgets mirroring the verification functions of `DLCards`.
+pub struct DLCardsGadget<C: ProjectiveCurve, GC: CurveVar<C, ConstraintF<C>>> {
+    _curve: PhantomData<(C, GC)>,
+}
+
+impl<C: ProjectiveCurve, GC: CurveVar<C, ConstraintF<C>>> DLCardsGadget<C, GC>
+where
+    for<'a> &'a GC: GroupOpsBounds<'a, C, GC>,
+{
+    /// Enforce that `masked_card` is a masking of `card` under `shared_key`.
+    pub fn verify_mask(
+        generator: &GC,
+        shared_key: &GC,
+        card: &CardVar<C, GC>,
+        masked_card: &MaskedCardVar<C, GC>,
+        proof: &ChaumPedersenProofVar<C, GC>,
+        challenge: &ScalarVar<C>,
+    ) -> Result<(), SynthesisError> {
+        let statement = &masked_card.c2 - &card.point;
+        proof.enforce_valid(
+            (generator, shared_key),
+            (&masked_card.c1, &statement),
+            challenge,
+        )
+    }
+
+    /// Enforce that `remasked` is a remasking of `original_masked` under `shared_key`.
+    pub fn verify_remask(
+        generator: &GC,
+        shared_key: &GC,
+        original_masked: &MaskedCardVar<C, GC>,
+        remasked: &MaskedCardVar<C, GC>,
+        proof: &ChaumPedersenProofVar<C, GC>,
+        challenge: &ScalarVar<C>,
+    ) -> Result<(), SynthesisError> {
+        let statement = remasked.sub(original_masked);
+        proof.enforce_valid(
+            (generator, shared_key),
+            (&statement.c1, &statement.c2),
+            challenge,
+        )
+    }
+
+    /// Enforce that `reveal_token` was computed for `masked_card` with the secret key of `pk`.
+    pub fn verify_reveal(
+        generator: &GC,
+        pk: &GC,
+        reveal_token: &RevealTokenVar<C, GC>,
+        masked_card: &MaskedCardVar<C, GC>,
+        proof: &ChaumPedersenProofVar<C, GC>,
+        challenge: &ScalarVar<C>,
+    ) -> Result<(), SynthesisError> {
+        proof.enforce_valid(
+            (&masked_card.c1, generator),
+            (&reveal_token.point, pk),
+            challenge,
+        )
+    }
+
+    /// Verify every reveal token and remove them from `masked_card`. The result is the open card
+    /// only if the tokens of all players are given.
+    pub fn unmask(
+        generator: &GC,
+        decryption_key: &[DecryptionShareVar<C, GC>],
+        masked_card: &MaskedCardVar<C, GC>,
+    ) -> Result<CardVar<C, GC>, SynthesisError> {
+        let mut aggregate_token = GC::zero();
+        for (token, proof, pk, challenge) in decryption_key {
+            Self::verify_reveal(generator, pk, token, masked_card, proof, challenge)?;
+            aggregate_token += &token.point;
+        }
+
+        Ok(CardVar::new(&masked_card.c2 - &aggregate_token))
+    }
+}
+
+impl<C, GC> DLCardsGadget<C, GC>
+where
+    C: ProjectiveCurve<BaseField = PoseidonField>,
+    GC: CurveVar<C, ConstraintF<C>> + ToConstraintFieldGadget<PoseidonField>,
+    for<'a> &'a GC: GroupOpsBounds<'a, C, GC>,
+{
+    /// Challenge of a masking proof computed with `Poseidon`, for use with `verify_mask`.
+    pub fn mask_challenge(
+        generator: &GC,
+        shared_key: &GC,
+        card: &CardVar<C, GC>,
+        masked_card: &MaskedCardVar<C, GC>,
+        proof: &ChaumPedersenProofVar<C, GC>,
+    ) -> Result<ScalarVar<C>, SynthesisError> {
+        let statement = &masked_card.c2 - &card.point;
+        PoseidonGadget::chaum_pedersen_challenge(
+            MASKING_RNG_SEED,
+            (generator, shared_key),
+            (&masked_card.c1, &statement),
+            (&proof.a, &proof.b),
+        )
+    }
+
+    /// Challenge of a remasking proof computed with `Poseidon`, for use with `verify_remask`.
+    pub fn remask_challenge(
+        generator: &GC,
+        shared_key: &GC,
+        original_masked: &MaskedCardVar<C, GC>,
+        remasked: &MaskedCardVar<C, GC>,
+        proof: &ChaumPedersenProofVar<C, GC>,
+    ) -> Result<ScalarVar<C>, SynthesisError> {
+        let statement = remasked.sub(original_masked);
+        PoseidonGadget::chaum_pedersen_challenge(
+            REMASKING_RNG_SEED,
+            (generator, shared_key),
+            (&statement.c1, &statement.c2),
+            (&proof.a, &proof.b),
+        )
+    }
+
+    /// Challenge of a reveal proof computed with `Poseidon`, for use with `verify_reveal`.
+    pub fn reveal_challenge(
+        generator: &GC,
+        pk: &GC,
+        reveal_token: &RevealTokenVar<C, GC>,
+        masked_card: &MaskedCardVar<C, GC>,
+        proof: &ChaumPedersenProofVar<C, GC>,
+    ) -> Result<ScalarVar<C>, SynthesisError> {
+        PoseidonGadget::chaum_pedersen_challenge(
+            REVEAL_RNG_SEED,
+            (&masked_card.c1, generator),
+            (&reveal_token.point, pk),
+            (&proof.a, &proof.b),
+        )
+    }
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> DLCards<C, D> {
+    /// Challenge of a masking proof, for use with `DLCardsGadget::verify_mask`.
+    pub fn mask_challenge(
+        pp: &Parameters<C>,
+        shared_key: &PublicKey<C>,
+        card: &Card<C>,
+        masked_card: &MaskedCard<C>,
+        proof: &<Self as BarnettSmartProtocol>::ZKProofMasking,
+    ) -> Result<C::ScalarField, CardProtocolError> {
+        let negative_original = card.0.mul(-C::ScalarField::one()).into_affine();
+        let statement = masked_card.1 + negative_original;
+        let (a, b, _) = proof_elements(proof.expect_hash::<D>()?)?;
+
+        D::chaum_pedersen_challenge::<C>(
+            MASKING_RNG_SEED,
+            (&pp.enc_parameters.generator, shared_key),
+            (&masked_card.0, &statement),
+            (&a, &b),
+        )
+        .map_err(CardProtocolError::from)
+    }
+
+    /// Challenge of a remasking proof, for use with `DLCardsGadget::verify_remask`.
+    pub fn remask_challenge(
+        pp: &Parameters<C>,
+        shared_key: &PublicKey<C>,
+        original_masked: &MaskedCard<C>,
+        remasked: &MaskedCard<C>,
+        proof: &<Self as BarnettSmartProtocol>::ZKProofRemasking,
+    ) -> Result<C::ScalarField, CardProtocolError> {
+        let statement = *remasked + *original_masked * -C::ScalarField::one();
+        let (a, b, _) = proof_elements(proof.expect_hash::<D>()?)?;
+
+        D::chaum_pedersen_challenge::<C>(
+            REMASKING_RNG_SEED,
+            (&pp.enc_parameters.generator, shared_key),
+            (&statement.0, &statement.1),
+            (&a, &b),
+        )
+        .map_err(CardProtocolError::from)
+    }
+
+    /// Challenge of a reveal proof, for use with `DLCardsGadget::verify_reveal`.
+    pub fn reveal_challenge(
+        pp: &Parameters<C>,
+        pk: &PublicKey<C>,
+        reveal_token: &RevealToken<C>,
+        masked_card: &MaskedCard<C>,
+        proof: &<Self as BarnettSmartProtocol>::ZKProofReveal,
+    ) -> Result<C::ScalarField, CardProtocolError> {
+        let (a, b, _) = proof_elements(proof.expect_hash::<D>()?)?;
+
+        D::chaum_pedersen_challenge::<C>(
+            REVEAL_RNG_SEED,
+            (&masked_card.0, &pp.enc_parameters.generator),
+            (&reveal_token.0, pk),
+            (&a, &b),
+        )
+        .map_err(CardProtocolError::from)
+    }
+}
+
+/// Gadget computing `poseidon_hash` in a circuit over the scalar field of BN254, e.g. the
+/// constraint field of Ed-on-BN254.
+pub struct PoseidonGadget;
+
+impl PoseidonGadget {
+    /// Poseidon of `a` and `b`, equal to `poseidon_hash` of their values.
+    pub fn hash(
+        a: &FpVar<PoseidonField>,
+        b: &FpVar<PoseidonField>,
+    ) -> Result<FpVar<PoseidonField>, SynthesisError> {
+        let mds = poseidon_mds();
+        let mut state = vec![FpVar::zero(), a.clone(), b.clone()];
+        for (round, constants) in poseidon_round_constants().iter().enumerate() {
+            for (x, c) in state.iter_mut().zip(constants) {
+                *x += *c;
+            }
+            if poseidon_full_round(round) {
+                for x in state.iter_mut() {
+                    *x = Self::sbox(x)?;
+                }
+            } else {
+                state[0] = Self::sbox(&state[0])?;
+            }
+
+            state = mds
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .zip(state.iter())
+                        .fold(FpVar::zero(), |acc, (m, x)| acc + x * *m)
+                })
+                .collect();
+        }
+
+        Ok(state.swap_remove(0))
+    }
+
+    /// Challenge of a Chaum-Pedersen proof, equal to `Poseidon::chaum_pedersen_challenge` of the
+    /// values of the points.
+    pub fn chaum_pedersen_challenge<C, GC>(
+        seed: &[u8],
+        (g, h): (&GC, &GC),
+        (x, y): (&GC, &GC),
+        (a, b): (&GC, &GC),
+    ) -> Result<ScalarVar<C>, SynthesisError>
+    where
+        C: ProjectiveCurve<BaseField = PoseidonField>,
+        GC: CurveVar<C, ConstraintF<C>> + ToConstraintFieldGadget<PoseidonField>,
+    {
+        let mut state = FpVar::constant(Poseidon::hash_to_field(seed));
+        for point in [g, h, x, y, a, b] {
+            for coordinate in point.to_constraint_field()?.iter().take(2) {
+                state = Self::hash(&state, coordinate)?;
+            }
+        }
+
+        let mut bits = state.to_bits_le()?;
+        bits.truncate(C::ScalarField::size_in_bits() - 1);
+        Ok(ScalarVar { bits })
+    }
+
+    fn sbox(x: &FpVar<PoseidonField>) -> Result<FpVar<PoseidonField>, SynthesisError> {
+        Ok(x.square()?.square()? * x)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        CardVar, ChaumPedersenProofVar, DLCardsGadget, MaskedCardVar, PoseidonGadget,
+        RevealTokenVar, ScalarVar,
+    };
+    use crate::discrete_log_cards::{
+        self, poseidon_hash, FiatShamirHash, Poseidon, PoseidonField, TaggedProof,
+    };
+    use crate::BarnettSmartProtocol;
+
+    use ark_ec::{AffineCurve, ProjectiveCurve};
+    use ark_ed_on_bn254::constraints::EdwardsVar;
+    use ark_ed_on_bn254::{EdwardsProjective, Fq};
+    use ark_ff::{BigInteger, PrimeField, UniformRand};
+    use ark_r1cs_std::fields::fp::FpVar;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::Zero;
+    use blake2::Blake2s;
+    use proof_essentials::zkp::proofs::chaum_pedersen_dl_equality::proof::Proof;
+    use rand::thread_rng;
+
+    // Instantiate concrete types for the card protocol and its gadgets
+    type CardProtocol<'a> = discrete_log_cards::DLCards<EdwardsProjective>;
+    type Gadget = DLCardsGadget<EdwardsProjective, EdwardsVar>;
+    type Scalar = ark_ed_on_bn254::Fr;
+    type Card = discrete_log_cards::Card<EdwardsProjective>;
+    type PublicKey = discrete_log_cards::PublicKey<EdwardsProjective>;
+
+    #[test]
+    fn test_mask_and_remask_gadgets() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 2, 2).unwrap();
+        let (shared_key, _) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+
+        let card = Card::rand(rng);
+        let alpha = Scalar::rand(rng);
+        let (masked, mask_proof) =
+            CardProtocol::mask(rng, &parameters, &shared_key, &card, &alpha).unwrap();
+        let beta = Scalar::rand(rng);
+        let (remasked, remask_proof) =
+            CardProtocol::remask(rng, &parameters, &shared_key, &masked, &beta).unwrap();
+
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        let generator =
+            EdwardsVar::new_constant(cs.clone(), parameters.enc_parameters.generator).unwrap();
+        let shared_key_var = EdwardsVar::new_input(cs.clone(), || Ok(shared_key)).unwrap();
+        let card_var = CardVar::new_input(cs.clone(), || Ok(card)).unwrap();
+        let masked_var = MaskedCardVar::new_input(cs.clone(), || Ok(masked)).unwrap();
+        let remasked_var = MaskedCardVar::new_input(cs.clone(), || Ok(remasked)).unwrap();
+
+        // Ciphertext arithmetic matches the native masking
+        let alpha_var = ScalarVar::new_witness(cs.clone(), || Ok(alpha)).unwrap();
+        let beta_var = ScalarVar::new_witness(cs.clone(), || Ok(beta)).unwrap();
+        let computed =
+            MaskedCardVar::mask(&generator, &shared_key_var, &card_var, &alpha_var).unwrap();
+        computed.enforce_equal(&masked_var).unwrap();
+        let computed = computed
+            .remask(&generator, &shared_key_var, &beta_var)
+            .unwrap();
+        assert_eq!(computed.c1.value().unwrap().into_affine(), remasked.0);
+        assert_eq!(computed.c2.value().unwrap().into_affine(), remasked.1);
+
+        let challenge =
+            CardProtocol::mask_challenge(&parameters, &shared_key, &card, &masked, &mask_proof)
+                .unwrap();
+        let challenge = ScalarVar::new_input(cs.clone(), || Ok(challenge)).unwrap();
+        let proof = ChaumPedersenProofVar::new_input(cs.clone(), || {
+            Ok(*mask_proof.expect_hash::<Blake2s>().unwrap())
+        })
+        .unwrap();
+        Gadget::verify_mask(
+            &generator,
+            &shared_key_var,
+            &card_var,
+            &masked_var,
+            &proof,
+            &challenge,
+        )
+        .unwrap();
+
+        let challenge = CardProtocol::remask_challenge(
+            &parameters,
+            &shared_key,
+            &masked,
+            &remasked,
+            &remask_proof,
+        )
+        .unwrap();
+        let challenge = ScalarVar::new_input(cs.clone(), || Ok(challenge)).unwrap();
+        let proof = ChaumPedersenProofVar::new_input(cs.clone(), || {
+            Ok(*remask_proof.expect_hash::<Blake2s>().unwrap())
+        })
+        .unwrap();
+        Gadget::verify_remask(
+            &generator,
+            &shared_key_var,
+            &masked_var,
+            &remasked_var,
+            &proof,
+            &challenge,
+        )
+        .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // The remasking proof does not hold for the masking
+        Gadget::verify_mask(
+            &generator,
+            &shared_key_var,
+            &card_var,
+            &masked_var,
+            &proof,
+            &challenge,
+        )
+        .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_unmask_gadget() {
+        let rng = &mut thread_rng();
+        let num_of_players = 3;
+        let parameters = CardProtocol::setup(rng, 2, 2).unwrap();
+
+        let mut players = Vec::with_capacity(num_of_players);
+        let mut shared_key = PublicKey::zero();
+        for _ in 0..num_of_players {
+            let (pk, sk) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+            shared_key = (shared_key.into_projective() + pk.into_projective()).into_affine();
+            players.push((pk, sk));
+        }
+
+        let card = Card::rand(rng);
+        let alpha = Scalar::rand(rng);
+        let (masked, _) = CardProtocol::mask(rng, &parameters, &shared_key, &card, &alpha).unwrap();
+
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        let generator =
+            EdwardsVar::new_constant(cs.clone(), parameters.enc_parameters.generator).unwrap();
+        let masked_var = MaskedCardVar::new_input(cs.clone(), || Ok(masked)).unwrap();
+
+        let mut decryption_key = Vec::with_capacity(num_of_players);
+        let mut native_key = Vec::with_capacity(num_of_players);
+        for (pk, sk) in players.iter() {
+            let (token, proof) =
+                CardProtocol::compute_reveal_token(rng, &parameters, sk, pk, &masked).unwrap();
+            let challenge =
+                CardProtocol::reveal_challenge(&parameters, pk, &token, &masked, &proof).unwrap();
+
+            decryption_key.push((
+                RevealTokenVar::new_input(cs.clone(), || Ok(token)).unwrap(),
+                ChaumPedersenProofVar::new_input(cs.clone(), || {
+                    Ok(*proof.expect_hash::<Blake2s>().unwrap())
+                })
+                .unwrap(),
+                EdwardsVar::new_input(cs.clone(), || Ok(*pk)).unwrap(),
+                ScalarVar::new_input(cs.clone(), || Ok(challenge)).unwrap(),
+            ));
+            native_key.push((token, proof, *pk));
+        }
+
+        let expected = CardProtocol::unmask(&parameters, &native_key, &masked, false).unwrap();
+        assert_eq!(expected, card);
+
+        let unmasked = Gadget::unmask(&generator, &decryption_key, &masked_var).unwrap();
+        assert_eq!(unmasked.point.value().unwrap().into_affine(), card.0);
+        assert!(cs.is_satisfied().unwrap());
+
+        // A token computed with another secret key
+        let (forged, _) = CardProtocol::compute_reveal_token(
+            rng,
+            &parameters,
+            &players[1].1,
+            &players[1].0,
+            &masked,
+        )
+        .unwrap();
+        decryption_key[0].0 = RevealTokenVar::new_input(cs.clone(), || Ok(forged)).unwrap();
+        Gadget::unmask(&generator, &decryption_key, &masked_var).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_forged_proof() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 2, 2).unwrap();
+        let (shared_key, _) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+        let generator = parameters.enc_parameters.generator;
+
+        // A masking of `card`, passed off as a masking of `other`
+        let card = Card::rand(rng);
+        let other = Card::rand(rng);
+        let alpha = Scalar::rand(rng);
+        let (masked, _) = CardProtocol::mask(rng, &parameters, &shared_key, &card, &alpha).unwrap();
+
+        // Pick the challenge and response first, then commitments that fit them
+        let challenge = Scalar::rand(rng);
+        let r = Scalar::rand(rng);
+        let statement = masked.1.into_projective() - other.0.into_projective();
+        let a = generator.mul(r) - masked.0.mul(challenge);
+        let b = shared_key.mul(r) - statement.into_affine().mul(challenge);
+        let mut bytes = Vec::new();
+        (a.into_affine(), b.into_affine(), r)
+            .serialize(&mut bytes)
+            .unwrap();
+        let forged = Proof::<EdwardsProjective>::deserialize(&bytes[..]).unwrap();
+        let forged = TaggedProof::new(FiatShamirHash::Blake2s, forged);
+
+        let prove = |challenge: Scalar| {
+            let cs = ConstraintSystem::<Fq>::new_ref();
+            let generator = EdwardsVar::new_constant(cs.clone(), generator).unwrap();
+            let shared_key_var = EdwardsVar::new_input(cs.clone(), || Ok(shared_key)).unwrap();
+            let other_var = CardVar::new_input(cs.clone(), || Ok(other)).unwrap();
+            let masked_var = MaskedCardVar::new_input(cs.clone(), || Ok(masked)).unwrap();
+            let proof = ChaumPedersenProofVar::new_input(cs.clone(), || Ok(forged.proof)).unwrap();
+            let challenge = ScalarVar::new_input(cs.clone(), || Ok(challenge)).unwrap();
+            Gadget::verify_mask(
+                &generator,
+                &shared_key_var,
+                &other_var,
+                &masked_var,
+                &proof,
+                &challenge,
+            )
+            .unwrap();
+            cs.is_satisfied().unwrap()
+        };
+
+        // The verification equations hold for the challenge the commitments were made for...
+        assert!(prove(challenge));
+
+        // ...but not for the one the verifier of the SNARK recomputes from the public commitments
+        let recomputed =
+            CardProtocol::mask_challenge(&parameters, &shared_key, &other, &masked, &forged)
+                .unwrap();
+        assert_ne!(recomputed, challenge);
+        assert!(!prove(recomputed));
+        assert!(
+            CardProtocol::verify_mask(&parameters, &shared_key, &other, &masked, &forged).is_err()
+        );
+    }
+
+    fn scalar_value(scalar: &ScalarVar<EdwardsProjective>) -> Scalar {
+        let bits = scalar
+            .bits()
+            .iter()
+            .map(|bit| bit.value())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        Scalar::from_repr(<Scalar as PrimeField>::BigInt::from_bits_le(&bits)).unwrap()
+    }
+
+    #[test]
+    fn test_poseidon_challenges_in_circuit() {
+        type PoseidonCards = discrete_log_cards::DLCards<EdwardsProjective, Poseidon>;
+
+        let rng = &mut thread_rng();
+        let parameters = PoseidonCards::setup(rng, 2, 2).unwrap();
+        let (pk, sk) = PoseidonCards::player_keygen(rng, &parameters).unwrap();
+
+        let card = Card::rand(rng);
+        let alpha = Scalar::rand(rng);
+        let (masked, mask_proof) =
+            PoseidonCards::mask(rng, &parameters, &pk, &card, &alpha).unwrap();
+        let beta = Scalar::rand(rng);
+        let (remasked, remask_proof) =
+            PoseidonCards::remask(rng, &parameters, &pk, &masked, &beta).unwrap();
+        let (token, reveal_proof) =
+            PoseidonCards::compute_reveal_token(rng, &parameters, &sk, &pk, &remasked).unwrap();
+
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        let generator =
+            EdwardsVar::new_constant(cs.clone(), parameters.enc_parameters.generator).unwrap();
+        let pk_var = EdwardsVar::new_input(cs.clone(), || Ok(pk)).unwrap();
+        let card_var = CardVar::new_input(cs.clone(), || Ok(card)).unwrap();
+        let masked_var = MaskedCardVar::new_input(cs.clone(), || Ok(masked)).unwrap();
+        let remasked_var = MaskedCardVar::new_input(cs.clone(), || Ok(remasked)).unwrap();
+        let token_var = RevealTokenVar::new_input(cs.clone(), || Ok(token)).unwrap();
+
+        // The challenges computed in the circuit are the native ones
+        let mask_proof_var = ChaumPedersenProofVar::new_input(cs.clone(), || {
+            Ok(*mask_proof.expect_hash::<Poseidon>().unwrap())
+        })
+        .unwrap();
+        let challenge =
+            Gadget::mask_challenge(&generator, &pk_var, &card_var, &masked_var, &mask_proof_var)
+                .unwrap();
+        assert_eq!(
+            scalar_value(&challenge),
+            PoseidonCards::mask_challenge(&parameters, &pk, &card, &masked, &mask_proof).unwrap()
+        );
+        Gadget::verify_mask(
+            &generator,
+            &pk_var,
+            &card_var,
+            &masked_var,
+            &mask_proof_var,
+            &challenge,
+        )
+        .unwrap();
+
+        let remask_proof_var = ChaumPedersenProofVar::new_input(cs.clone(), || {
+            Ok(*remask_proof.expect_hash::<Poseidon>().unwrap())
+        })
+        .unwrap();
+        let challenge = Gadget::remask_challenge(
+            &generator,
+            &pk_var,
+            &masked_var,
+            &remasked_var,
+            &remask_proof_var,
+        )
+        .unwrap();
+        assert_eq!(
+            scalar_value(&challenge),
+            PoseidonCards::remask_challenge(&parameters, &pk, &masked, &remasked, &remask_proof)
+                .unwrap()
+        );
+        Gadget::verify_remask(
+            &generator,
+            &pk_var,
+            &masked_var,
+            &remasked_var,
+            &remask_proof_var,
+            &challenge,
+        )
+        .unwrap();
+
+        let reveal_proof_var = ChaumPedersenProofVar::new_input(cs.clone(), || {
+            Ok(*reveal_proof.expect_hash::<Poseidon>().unwrap())
+        })
+        .unwrap();
+        let challenge = Gadget::reveal_challenge(
+            &generator,
+            &pk_var,
+            &token_var,
+            &remasked_var,
+            &reveal_proof_var,
+        )
+        .unwrap();
+        assert_eq!(
+            scalar_value(&challenge),
+            PoseidonCards::reveal_challenge(&parameters, &pk, &token, &remasked, &reveal_proof)
+                .unwrap()
+        );
+        Gadget::verify_reveal(
+            &generator,
+            &pk_var,
+            &token_var,
+            &remasked_var,
+            &reveal_proof_var,
+            &challenge,
+        )
+        .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // The remasking proof does not hold for the masking, with the challenge it would have
+        let challenge = Gadget::mask_challenge(
+            &generator,
+            &pk_var,
+            &card_var,
+            &masked_var,
+            &remask_proof_var,
+        )
+        .unwrap();
+        Gadget::verify_mask(
+            &generator,
+            &pk_var,
+            &card_var,
+            &masked_var,
+            &remask_proof_var,
+            &challenge,
+        )
+        .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_poseidon_gadget() {
+        let rng = &mut thread_rng();
+        let a = PoseidonField::rand(rng);
+        let b = PoseidonField::rand(rng);
+
+        let cs = ConstraintSystem::<PoseidonField>::new_ref();
+        let a_var = FpVar::new_witness(cs.clone(), || Ok(a)).unwrap();
+        let b_var = FpVar::new_witness(cs.clone(), || Ok(b)).unwrap();
+        let digest = FpVar::new_input(cs.clone(), || Ok(poseidon_hash(&a, &b))).unwrap();
+
+        let computed = PoseidonGadget::hash(&a_var, &b_var).unwrap();
+        computed.enforce_equal(&digest).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // The inputs are not interchangeable
+        PoseidonGadget::hash(&b_var, &a_var)
+            .unwrap()
+            .enforce_equal(&digest)
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
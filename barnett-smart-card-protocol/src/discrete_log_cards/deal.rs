@@ -0,0 +1,672 @@
+// Bookkeeping for dealing a shuffled deck, and the operations that move dealt cards face-down.
+//
+// Positions always refer to indices in the deck the log was created from. A player discarding or
+// playing a card face-down remasks it, so the card can no longer be linked to the reveal tokens
+// issued for it while it was in the player's hand, and proves with a remasking proof that it is the
+// card dealt at a given position. Face-down cards can be opened later, either when the game calls
+// for it (plays) or during an end-of-hand audit (discards).
+//
+// The log keeps the aggregate key the deck was certified under and replaces it when the cards are
+// re-keyed, so that proofs are always checked against the key the cards are masked under.
+
+use super::TranscriptHash;
+use super::{Card, CertifiedDeck, DLCards, MaskedCard, PublicKey, Rekeying, RevealToken};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::ProjectiveCurve;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::io::{Read, Write};
+use ark_std::marker::PhantomData;
+use ark_std::rand::Rng;
+use ark_std::{vec, vec::Vec};
+use blake2::Blake2s;
+
+/// Where a dealt position currently is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardState {
+    /// Not dealt yet.
+    InDeck,
+    /// In the hand of the given player.
+    Held(usize),
+    /// Discarded by the given player, with the index of the discard in the pile.
+    Discarded { player: usize, pile_index: usize },
+    /// Played face-down by the given player, with the index of the play.
+    Played { player: usize, play_index: usize },
+}
+
+/// A card moved face-down out of a player's hand: the remasked card and a proof that it is the
+/// card dealt at `position`.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct FaceDownCard<C: ProjectiveCurve> {
+    pub player: usize,
+    pub position: usize,
+    pub masked_card: MaskedCard<C>,
+    pub proof: <DLCards<C> as BarnettSmartProtocol>::ZKProofRemasking,
+}
+
+/// Record of which deck position went to which player, of the discard pile and of the cards played
+/// face-down. Remasking proofs are checked with transcript hash `D`, under the aggregate key of the
+/// deck.
+#[derive(Clone)]
+pub struct DealLog<C: ProjectiveCurve, D: TranscriptHash = Blake2s> {
+    shared_key: PublicKey<C>,
+    deck: Vec<MaskedCard<C>>,
+    states: Vec<CardState>,
+    next: usize,
+    discards: Vec<FaceDownCard<C>>,
+    plays: Vec<FaceDownCard<C>>,
+    pending_replacements: Vec<usize>,
+    _hash: PhantomData<D>,
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> DealLog<C, D> {
+    /// Start dealing from the final deck of a complete shuffle chain.
+    pub fn new(deck: CertifiedDeck<C>) -> Self {
+        let num_of_players = deck.num_of_players();
+        let shared_key = *deck.aggregate_key();
+        let deck = deck.into_cards();
+        let states = vec![CardState::InDeck; deck.len()];
+        Self {
+            shared_key,
+            deck,
+            states,
+            next: 0,
+            discards: Vec::new(),
+            plays: Vec::new(),
+            pending_replacements: vec![0; num_of_players],
+            _hash: PhantomData,
+        }
+    }
+
+    pub fn num_of_players(&self) -> usize {
+        self.pending_replacements.len()
+    }
+
+    /// The aggregate key the cards are currently masked under.
+    pub fn shared_key(&self) -> &PublicKey<C> {
+        &self.shared_key
+    }
+
+    /// Masked card dealt (or to be dealt) at `position`.
+    pub fn card(&self, position: usize) -> Option<&MaskedCard<C>> {
+        self.deck.get(position)
+    }
+
+    pub fn state(&self, position: usize) -> Option<CardState> {
+        self.states.get(position).copied()
+    }
+
+    /// Number of cards that have not been dealt yet.
+    pub fn remaining(&self) -> usize {
+        self.deck.len() - self.next
+    }
+
+    /// Positions currently held by `player`, in the order they were dealt.
+    pub fn hand(&self, player: usize) -> Vec<usize> {
+        self.states
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| **state == CardState::Held(player))
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    pub fn discards(&self) -> &[FaceDownCard<C>] {
+        &self.discards
+    }
+
+    pub fn plays(&self) -> &[FaceDownCard<C>] {
+        &self.plays
+    }
+
+    /// Deal the top card of the deck to `player` and return its position.
+    pub fn deal(&mut self, player: usize) -> Result<usize, CardProtocolError> {
+        if player >= self.num_of_players() {
+            return Err(CardProtocolError::UnknownPlayer(player));
+        }
+        if self.next == self.deck.len() {
+            return Err(CardProtocolError::DeckExhausted);
+        }
+
+        let position = self.next;
+        self.states[position] = CardState::Held(player);
+        self.next += 1;
+
+        Ok(position)
+    }
+
+    /// Check that `position` is in the hand of `player`.
+    pub fn check_held(&self, player: usize, position: usize) -> Result<(), CardProtocolError> {
+        match self.states.get(position) {
+            Some(CardState::Held(holder)) if *holder == player => Ok(()),
+            _ => Err(CardProtocolError::NotInHand(position, player)),
+        }
+    }
+
+    /// Check the remasking proof of the discard at `pile_index` against the card dealt at its
+    /// position.
+    pub fn verify_discard(
+        &self,
+        pp: &<DLCards<C, D> as BarnettSmartProtocol>::Parameters,
+        pile_index: usize,
+    ) -> Result<(), CardProtocolError> {
+        let discard = self
+            .discards
+            .get(pile_index)
+            .ok_or(CardProtocolError::UnknownDiscard(pile_index))?;
+
+        self.verify_face_down(pp, discard, CardProtocolError::UnknownDiscard(pile_index))
+    }
+
+    /// Check the remasking proof of the face-down play at `play_index` against the card dealt at
+    /// its position.
+    pub fn verify_play(
+        &self,
+        pp: &<DLCards<C, D> as BarnettSmartProtocol>::Parameters,
+        play_index: usize,
+    ) -> Result<(), CardProtocolError> {
+        let play = self
+            .plays
+            .get(play_index)
+            .ok_or(CardProtocolError::UnknownPlay(play_index))?;
+
+        self.verify_face_down(pp, play, CardProtocolError::UnknownPlay(play_index))
+    }
+
+    /// Check the remasking proof of a face-down card against the card dealt at its position,
+    /// failing with `unknown` if there is no such position.
+    fn verify_face_down(
+        &self,
+        pp: &<DLCards<C, D> as BarnettSmartProtocol>::Parameters,
+        card: &FaceDownCard<C>,
+        unknown: CardProtocolError,
+    ) -> Result<(), CardProtocolError> {
+        let dealt = self.deck.get(card.position).ok_or(unknown)?;
+        DLCards::<C, D>::verify_remask(
+            pp,
+            &self.shared_key,
+            dealt,
+            &card.masked_card,
+            &card.proof,
+        )?;
+
+        Ok(())
+    }
+
+    /// Verify a discard against the dealt card at its position and move it to the discard pile.
+    /// Every player runs this on the discards they receive.
+    pub fn record_discard(
+        &mut self,
+        pp: &<DLCards<C, D> as BarnettSmartProtocol>::Parameters,
+        discard: FaceDownCard<C>,
+    ) -> Result<(), CardProtocolError> {
+        self.check_held(discard.player, discard.position)?;
+        let not_held = CardProtocolError::NotInHand(discard.position, discard.player);
+        self.verify_face_down(pp, &discard, not_held)?;
+
+        self.states[discard.position] = CardState::Discarded {
+            player: discard.player,
+            pile_index: self.discards.len(),
+        };
+        self.pending_replacements[discard.player] += 1;
+        self.discards.push(discard);
+
+        Ok(())
+    }
+
+    /// Verify a face-down play against the dealt card at its position and record it. Every player
+    /// runs this on the plays they receive.
+    pub fn record_play(
+        &mut self,
+        pp: &<DLCards<C, D> as BarnettSmartProtocol>::Parameters,
+        play: FaceDownCard<C>,
+    ) -> Result<(), CardProtocolError> {
+        self.check_held(play.player, play.position)?;
+        let not_held = CardProtocolError::NotInHand(play.position, play.player);
+        self.verify_face_down(pp, &play, not_held)?;
+
+        self.states[play.position] = CardState::Played {
+            player: play.player,
+            play_index: self.plays.len(),
+        };
+        self.plays.push(play);
+
+        Ok(())
+    }
+
+    /// Deal a replacement for one of the discards of `player` that has not been replaced yet.
+    pub fn draw_replacement(&mut self, player: usize) -> Result<usize, CardProtocolError> {
+        match self.pending_replacements.get(player) {
+            Some(0) => return Err(CardProtocolError::NoPendingReplacement(player)),
+            None => return Err(CardProtocolError::UnknownPlayer(player)),
+            Some(_) => {}
+        }
+
+        let position = self.deal(player)?;
+        self.pending_replacements[player] -= 1;
+
+        Ok(position)
+    }
+
+    /// Every masked card tracked by the log: the dealt deck (including the hands) in position
+    /// order, then the discard pile, then the face-down plays. This is the batch to re-key.
+    pub fn masked_cards(&self) -> Vec<MaskedCard<C>> {
+        self.deck
+            .iter()
+            .chain(self.discards.iter().map(|discard| &discard.masked_card))
+            .chain(self.plays.iter().map(|play| &play.masked_card))
+            .copied()
+            .collect()
+    }
+
+    /// Index in `masked_cards` of the face-down card dealt at `position`.
+    fn face_down_index(&self, position: usize) -> Option<usize> {
+        match self.states.get(position)? {
+            CardState::Discarded { pile_index, .. } => Some(self.deck.len() + pile_index),
+            CardState::Played { play_index, .. } => {
+                Some(self.deck.len() + self.discards.len() + play_index)
+            }
+            _ => None,
+        }
+    }
+
+    /// Verify a re-keying of `masked_cards` and move the deck, the hands, the discard pile and the
+    /// face-down plays to the new aggregate key.
+    ///
+    /// Re-keying does not change the masking factor relating a face-down card to the card dealt
+    /// at its position, but the remasking proofs only hold under the key they were computed
+    /// with. `face_down_proofs` holds new remasking proofs under the new key, from
+    /// `DLCards::reprove_face_down`, for the discards and then the plays, so that face-down cards
+    /// can still be checked against their positions when they are opened.
+    pub fn apply_rekeying(
+        &mut self,
+        pp: &<DLCards<C, D> as BarnettSmartProtocol>::Parameters,
+        rekeying: &Rekeying<C>,
+        face_down_proofs: &[<DLCards<C, D> as BarnettSmartProtocol>::ZKProofRemasking],
+    ) -> Result<(), CardProtocolError> {
+        DLCards::<C, D>::verify_rekeying(pp, &self.masked_cards(), rekeying)?;
+        if face_down_proofs.len() != self.discards.len() + self.plays.len() {
+            return Err(CardProtocolError::BatchSizeMismatch);
+        }
+
+        let new_key = DLCards::<C, D>::rekey_aggregate_key(&self.shared_key, rekeying);
+        let (dealt, face_down) = rekeying.masked_cards.split_at(self.deck.len());
+        for ((card, new_card), proof) in self
+            .discards
+            .iter()
+            .chain(self.plays.iter())
+            .zip(face_down)
+            .zip(face_down_proofs)
+        {
+            DLCards::<C, D>::verify_remask(pp, &new_key, &dealt[card.position], new_card, proof)?;
+        }
+
+        self.deck.copy_from_slice(dealt);
+        for ((card, new_card), proof) in self
+            .discards
+            .iter_mut()
+            .chain(self.plays.iter_mut())
+            .zip(face_down)
+            .zip(face_down_proofs)
+        {
+            card.masked_card = *new_card;
+            card.proof = *proof;
+        }
+        self.shared_key = new_key;
+
+        Ok(())
+    }
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> DLCards<C, D> {
+    fn face_down<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        log: &DealLog<C, D>,
+        player: usize,
+        position: usize,
+        alpha: &C::ScalarField,
+    ) -> Result<FaceDownCard<C>, CardProtocolError> {
+        log.check_held(player, position)?;
+        let (masked_card, proof) =
+            Self::remask(rng, pp, &log.shared_key, &log.deck[position], alpha)?;
+
+        Ok(FaceDownCard {
+            player,
+            position,
+            masked_card,
+            proof,
+        })
+    }
+
+    /// Prove again, under the aggregate key after `rekeying`, that the face-down card at
+    /// `position` is the card dealt there. Only the player who moved the card face-down knows its
+    /// masking factor `alpha`, so every such player sends these proofs to the others before
+    /// they apply the re-keying; a leaving player does so before leaving.
+    pub fn reprove_face_down<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        log: &DealLog<C, D>,
+        rekeying: &Rekeying<C>,
+        position: usize,
+        alpha: &C::ScalarField,
+    ) -> Result<<Self as BarnettSmartProtocol>::ZKProofRemasking, CardProtocolError> {
+        let index = log
+            .face_down_index(position)
+            .ok_or(CardProtocolError::NotFaceDown(position))?;
+        if rekeying.masked_cards.len() != log.masked_cards().len() {
+            return Err(CardProtocolError::BatchSizeMismatch);
+        }
+
+        let new_key = Self::rekey_aggregate_key(&log.shared_key, rekeying);
+        Self::prove_remasking(
+            rng,
+            pp,
+            &new_key,
+            &rekeying.masked_cards[position],
+            &rekeying.masked_cards[index],
+            alpha,
+        )
+    }
+
+    fn open_face_down(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        card: &FaceDownCard<C>,
+        decryption_key: &[(
+            RevealToken<C>,
+            <Self as BarnettSmartProtocol>::ZKProofReveal,
+            PublicKey<C>,
+        )],
+    ) -> Result<Card<C>, CardProtocolError> {
+        Self::unmask(pp, &decryption_key.to_vec(), &card.masked_card, false)
+    }
+
+    /// Return the card dealt at `position` to `player` face-down. The card is remasked with the
+    /// masking factor `alpha` and the remasking proof binds it to the dealt position.
+    pub fn discard<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        log: &DealLog<C, D>,
+        player: usize,
+        position: usize,
+        alpha: &C::ScalarField,
+    ) -> Result<FaceDownCard<C>, CardProtocolError> {
+        Self::face_down(rng, pp, log, player, position, alpha)
+    }
+
+    /// Open a card of the discard pile at the end of a hand. `decryption_key` holds the reveal
+    /// tokens of every player for the discarded (remasked) card. The remasking proof is checked
+    /// again, so the opened card is the one dealt at the discard's position.
+    pub fn open_discard(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        log: &DealLog<C, D>,
+        pile_index: usize,
+        decryption_key: &[(
+            RevealToken<C>,
+            <Self as BarnettSmartProtocol>::ZKProofReveal,
+            PublicKey<C>,
+        )],
+    ) -> Result<Card<C>, CardProtocolError> {
+        log.verify_discard(pp, pile_index)?;
+
+        Self::open_face_down(pp, &log.discards[pile_index], decryption_key)
+    }
+
+    /// Play the card dealt at `position` to `player` face-down, to be revealed later with
+    /// `open_play`. The card is remasked with the masking factor `alpha` and the remasking proof
+    /// binds it to the dealt position.
+    pub fn commit_play<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        log: &DealLog<C, D>,
+        player: usize,
+        position: usize,
+        alpha: &C::ScalarField,
+    ) -> Result<FaceDownCard<C>, CardProtocolError> {
+        Self::face_down(rng, pp, log, player, position, alpha)
+    }
+
+    /// Reveal a card played face-down. `decryption_key` holds the reveal tokens of every player
+    /// for the played (remasked) card. Since the remasking proof ties the play to its dealt
+    /// position, the revealed card is exactly the card dealt to the player who played it.
+    pub fn open_play(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        log: &DealLog<C, D>,
+        play_index: usize,
+        decryption_key: &[(
+            RevealToken<C>,
+            <Self as BarnettSmartProtocol>::ZKProofReveal,
+            PublicKey<C>,
+        )],
+    ) -> Result<Card<C>, CardProtocolError> {
+        log.verify_play(pp, play_index)?;
+
+        Self::open_face_down(pp, &log.plays[play_index], decryption_key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::discrete_log_cards::test_utils::setup_players;
+    use crate::discrete_log_cards::{
+        self, CardState, CertifiedDeck, DealLog, ShuffleChain, ShuffleLink,
+    };
+    use crate::error::CardProtocolError;
+    use crate::BarnettSmartProtocol;
+
+    use ark_ff::UniformRand;
+    use ark_std::rand::Rng;
+    use proof_essentials::error::CryptoError;
+    use proof_essentials::utils::permutation::Permutation;
+    use proof_essentials::utils::rand::sample_vector;
+    use proof_essentials::zkp::proofs::chaum_pedersen_dl_equality;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+    type CardParameters = discrete_log_cards::Parameters<Curve>;
+    type PublicKey = discrete_log_cards::PublicKey<Curve>;
+    type SecretKey = discrete_log_cards::PlayerSecretKey<Curve>;
+
+    type Card = discrete_log_cards::Card<Curve>;
+    type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
+    type RevealToken = discrete_log_cards::RevealToken<Curve>;
+
+    type RevealProof =
+        discrete_log_cards::TaggedProof<chaum_pedersen_dl_equality::proof::Proof<Curve>>;
+
+    fn certified_deck<R: Rng>(
+        rng: &mut R,
+        parameters: &CardParameters,
+        players: &[(PublicKey, SecretKey)],
+        aggregate_key: &PublicKey,
+    ) -> (Vec<Card>, CertifiedDeck<Curve>) {
+        let deck = (0..parameters.num_cards())
+            .map(|_| {
+                let card = Card::rand(rng);
+                let alpha = Scalar::rand(rng);
+                CardProtocol::mask(rng, parameters, aggregate_key, &card, &alpha)
+                    .unwrap()
+                    .0
+            })
+            .collect::<Vec<_>>();
+
+        let mut chain = ShuffleChain::<Curve>::new(deck, (0..players.len()).collect()).unwrap();
+        for player in 0..players.len() {
+            let permutation = Permutation::new(rng, parameters.num_cards());
+            let masking_factors: Vec<Scalar> = sample_vector(rng, parameters.num_cards());
+            let (shuffled_deck, proof) = CardProtocol::shuffle_and_remask(
+                rng,
+                parameters,
+                aggregate_key,
+                &chain.current_deck().to_vec(),
+                &masking_factors,
+                &permutation,
+            )
+            .unwrap();
+            let link = ShuffleLink {
+                player,
+                shuffled_deck,
+                proof,
+            };
+            chain.push(parameters, aggregate_key, link).unwrap();
+        }
+        let deck = chain.certify(parameters, aggregate_key).unwrap();
+
+        // Open every position, so the tests know which card was dealt where
+        let cards = deck
+            .cards()
+            .iter()
+            .map(|masked_card| {
+                let decryption_key = reveal_tokens(rng, parameters, players, masked_card);
+                CardProtocol::unmask(parameters, &decryption_key, masked_card, false).unwrap()
+            })
+            .collect();
+
+        (cards, deck)
+    }
+
+    fn reveal_tokens<R: Rng>(
+        rng: &mut R,
+        parameters: &CardParameters,
+        players: &[(PublicKey, SecretKey)],
+        masked_card: &MaskedCard,
+    ) -> Vec<(RevealToken, RevealProof, PublicKey)> {
+        players
+            .iter()
+            .map(|(pk, sk)| {
+                let (token, proof) =
+                    CardProtocol::compute_reveal_token(rng, parameters, sk, pk, masked_card)
+                        .unwrap();
+                (token, proof, *pk)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_discard_and_draw_replacement() {
+        let rng = &mut thread_rng();
+        let m = 2;
+        let n = 4;
+
+        let parameters = CardProtocol::setup(rng, m, n).unwrap();
+        let (players, aggregate_key) = setup_players(rng, &parameters, 2);
+
+        let (cards, deck) = certified_deck(rng, &parameters, &players, &aggregate_key);
+
+        let mut log = DealLog::<Curve>::new(deck);
+        for _ in 0..2 {
+            log.deal(0).unwrap();
+            log.deal(1).unwrap();
+        }
+        assert_eq!(log.hand(0), vec![0, 2]);
+
+        // Player 0 cannot discard a card held by player 1
+        let alpha = Scalar::rand(rng);
+        assert_eq!(
+            CardProtocol::discard(rng, &parameters, &log, 0, 1, &alpha).err(),
+            Some(CardProtocolError::NotInHand(1, 0))
+        );
+
+        let discard = CardProtocol::discard(rng, &parameters, &log, 0, 2, &alpha).unwrap();
+        assert_ne!(discard.masked_card, *log.card(2).unwrap());
+
+        // A discard bound to another position is rejected
+        let mut forged = discard.clone();
+        forged.position = 0;
+        assert_eq!(
+            log.record_discard(&parameters, forged),
+            Err(CardProtocolError::ProofVerificationError(
+                CryptoError::ProofVerificationError(String::from("Chaum-Pedersen"))
+            ))
+        );
+
+        assert_eq!(
+            log.draw_replacement(0),
+            Err(CardProtocolError::NoPendingReplacement(0))
+        );
+        log.record_discard(&parameters, discard).unwrap();
+        assert_eq!(
+            log.state(2),
+            Some(CardState::Discarded {
+                player: 0,
+                pile_index: 0
+            })
+        );
+
+        let replacement = log.draw_replacement(0).unwrap();
+        assert_eq!(replacement, 4);
+        assert_eq!(log.hand(0), vec![0, 4]);
+
+        // End of hand audit
+        let discarded = log.discards()[0].masked_card;
+        let decryption_key = reveal_tokens(rng, &parameters, &players, &discarded);
+        let opened = CardProtocol::open_discard(&parameters, &log, 0, &decryption_key).unwrap();
+        assert_eq!(opened, cards[2]);
+    }
+
+    #[test]
+    fn test_commit_and_open_play() {
+        let rng = &mut thread_rng();
+        let m = 2;
+        let n = 4;
+
+        let parameters = CardProtocol::setup(rng, m, n).unwrap();
+        let (players, aggregate_key) = setup_players(rng, &parameters, 2);
+        let (cards, deck) = certified_deck(rng, &parameters, &players, &aggregate_key);
+
+        let mut log = DealLog::<Curve>::new(deck);
+        for _ in 0..2 {
+            log.deal(0).unwrap();
+            log.deal(1).unwrap();
+        }
+
+        let alpha = Scalar::rand(rng);
+        let play = CardProtocol::commit_play(rng, &parameters, &log, 1, 3, &alpha).unwrap();
+
+        // Claiming the play comes from a card dealt to the other player is rejected
+        let mut substituted = play.clone();
+        substituted.player = 0;
+        substituted.position = 2;
+        assert_eq!(
+            log.record_play(&parameters, substituted),
+            Err(CardProtocolError::ProofVerificationError(
+                CryptoError::ProofVerificationError(String::from("Chaum-Pedersen"))
+            ))
+        );
+
+        log.record_play(&parameters, play).unwrap();
+        assert_eq!(
+            log.state(3),
+            Some(CardState::Played {
+                player: 1,
+                play_index: 0
+            })
+        );
+        assert_eq!(log.hand(1), vec![1]);
+
+        let played = log.plays()[0].masked_card;
+        let decryption_key = reveal_tokens(rng, &parameters, &players, &played);
+        let opened = CardProtocol::open_play(&parameters, &log, 0, &decryption_key).unwrap();
+        assert_eq!(opened, cards[3]);
+
+        assert_eq!(
+            CardProtocol::open_play(&parameters, &log, 1, &decryption_key),
+            Err(CardProtocolError::UnknownPlay(1))
+        );
+        assert_eq!(log.verify_play(&parameters, 0), Ok(()));
+        assert_eq!(
+            log.verify_play(&parameters, 1),
+            Err(CardProtocolError::UnknownPlay(1))
+        );
+        assert_eq!(
+            log.verify_discard(&parameters, 0),
+            Err(CardProtocolError::UnknownDiscard(0))
+        );
+    }
+}
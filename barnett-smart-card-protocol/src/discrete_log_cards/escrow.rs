@@ -0,0 +1,701 @@
+// Optional escrow of player secret keys, so that a quorum can stand in for a player who drops out.
+//
+// At registration a player Feldman-shares their secret key among the other players: they publish
+// commitments to the coefficients of a random polynomial f of degree `threshold - 1` with
+// f(0) = sk, and send f(j + 1) to player j encrypted with hashed el-Gamal under the public key of
+// player j. Each recipient checks their share against the commitments and can publish a complaint,
+// with a Chaum-Pedersen proof of their decryption, if it does not match.
+//
+// Once a timeout or abort of the player is recorded, `threshold` share holders compute partial
+// reveal tokens f(j + 1) * c0 for a masked card, each with a Chaum-Pedersen proof against the share
+// public key derived from the commitments. Interpolating them at 0 gives the reveal token sk * c0
+// of the missing player. The secret key itself is never reconstructed.
+
+use super::batch::{prove_chaum_pedersen, verify_chaum_pedersen};
+#[cfg(feature = "std")]
+use super::Parameters;
+use super::{Card, DLCards, MaskedCard, PlayerSecretKey, PublicKey, RevealToken};
+use super::{TaggedProof, TranscriptHash};
+use crate::error::CardProtocolError;
+#[cfg(feature = "std")]
+use crate::session::Timeout;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{to_bytes, Field, PrimeField, UniformRand, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::io::{Read, Write};
+use ark_std::rand::Rng;
+use ark_std::{string::String, vec, vec::Vec};
+use blake2::{Blake2s, Digest};
+use proof_essentials::error::CryptoError;
+use proof_essentials::homomorphic_encryption::el_gamal;
+use proof_essentials::zkp::proofs::chaum_pedersen_dl_equality;
+
+const ESCROW_KEY_DERIVATION_TAG: &[u8] = b"Escrowed Share Key";
+const COMPLAINT_RNG_SEED: &[u8] = b"Escrow Complaint Proof";
+const PARTIAL_REVEAL_RNG_SEED: &[u8] = b"Partial Reveal Proof";
+
+/// A share of an escrowed secret key, encrypted for `recipient` with hashed el-Gamal.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct EncryptedShare<C: ProjectiveCurve> {
+    pub recipient: usize,
+    pub ephemeral_key: C::Affine,
+    pub ciphertext: C::ScalarField,
+}
+
+/// Escrow of the secret key of `player`, published at key registration. `commitments[k]` commits
+/// to the k-th coefficient of the sharing polynomial, so `commitments[0]` is the player's public
+/// key and `commitments.len()` is the number of shares needed to stand in for the player.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KeyEscrow<C: ProjectiveCurve> {
+    pub player: usize,
+    pub commitments: Vec<C::Affine>,
+    pub shares: Vec<EncryptedShare<C>>,
+}
+
+impl<C: ProjectiveCurve> KeyEscrow<C> {
+    pub fn threshold(&self) -> usize {
+        self.commitments.len()
+    }
+
+    /// Public key of the share held by `holder`, i.e. f(holder + 1) * g, computed from the
+    /// commitments.
+    pub fn share_public_key(&self, holder: usize) -> PublicKey<C> {
+        let x = share_index::<C>(holder);
+        self.commitments
+            .iter()
+            .rev()
+            .fold(C::zero(), |acc, commitment| {
+                acc.mul(x.into_repr()) + commitment.into_projective()
+            })
+            .into_affine()
+    }
+
+    fn share_for(&self, recipient: usize) -> Result<&EncryptedShare<C>, CardProtocolError> {
+        self.shares
+            .iter()
+            .find(|share| share.recipient == recipient)
+            .ok_or(CardProtocolError::InvalidEscrow(self.player))
+    }
+}
+
+/// Complaint of `recipient` that the share they received in the escrow of `player` does not match
+/// the commitments. `shared_secret` is the recipient's el-Gamal decryption of the share key,
+/// proven correct so that anyone can decrypt the share and check the complaint.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ShareComplaint<C: ProjectiveCurve> {
+    pub player: usize,
+    pub recipient: usize,
+    pub shared_secret: C::Affine,
+    pub proof: TaggedProof<chaum_pedersen_dl_equality::proof::Proof<C>>,
+}
+
+/// Share `holder`'s part of the reveal token of a player who dropped out.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PartialRevealToken<C: ProjectiveCurve> {
+    pub holder: usize,
+    pub token: RevealToken<C>,
+    pub proof: TaggedProof<chaum_pedersen_dl_equality::proof::Proof<C>>,
+}
+
+/// Why a player was declared gone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dropout {
+    Timeout,
+    Abort,
+}
+
+/// Registered escrows and recorded dropouts of a session. Partial reveal tokens for a player are
+/// only produced and accepted once a dropout of that player is recorded here.
+#[derive(Clone)]
+pub struct EscrowRegistry<C: ProjectiveCurve> {
+    player_keys: Vec<PublicKey<C>>,
+    escrows: Vec<Option<KeyEscrow<C>>>,
+    dropouts: Vec<Option<Dropout>>,
+}
+
+impl<C: ProjectiveCurve> EscrowRegistry<C> {
+    pub fn new(player_keys: Vec<PublicKey<C>>) -> Self {
+        let num_of_players = player_keys.len();
+        Self {
+            player_keys,
+            escrows: vec![None; num_of_players],
+            dropouts: vec![None; num_of_players],
+        }
+    }
+
+    pub fn num_of_players(&self) -> usize {
+        self.player_keys.len()
+    }
+
+    pub fn escrow(&self, player: usize) -> Option<&KeyEscrow<C>> {
+        self.escrows.get(player).and_then(Option::as_ref)
+    }
+
+    pub fn dropout(&self, player: usize) -> Option<Dropout> {
+        self.dropouts.get(player).copied().flatten()
+    }
+
+    /// Check the structure of an escrow and register it. This does not check the shares
+    /// themselves; every recipient does that with `decrypt_escrow_share`.
+    pub fn register(&mut self, escrow: KeyEscrow<C>) -> Result<(), CardProtocolError> {
+        let player = escrow.player;
+        let pk = self
+            .player_keys
+            .get(player)
+            .ok_or(CardProtocolError::UnknownPlayer(player))?;
+
+        let others = self.num_of_players() - 1;
+        let well_formed = escrow.commitments.first() == Some(pk)
+            && escrow.threshold() <= others
+            && escrow.shares.len() == others
+            && (0..self.num_of_players())
+                .filter(|recipient| *recipient != player)
+                .all(|recipient| escrow.share_for(recipient).is_ok());
+        if !well_formed {
+            return Err(CardProtocolError::InvalidEscrow(player));
+        }
+
+        self.escrows[player] = Some(escrow);
+
+        Ok(())
+    }
+
+    /// Record that `player` timed out or aborted, unlocking the recovery of their reveal tokens.
+    pub fn record_dropout(
+        &mut self,
+        player: usize,
+        reason: Dropout,
+    ) -> Result<(), CardProtocolError> {
+        let dropout = self
+            .dropouts
+            .get_mut(player)
+            .ok_or(CardProtocolError::UnknownPlayer(player))?;
+        *dropout = Some(reason);
+
+        Ok(())
+    }
+
+    /// Record a missed deadline of the player as a dropout, once its signed evidence checks out
+    /// against the registered keys.
+    #[cfg(feature = "std")]
+    pub fn record_timeout(
+        &mut self,
+        pp: &Parameters<C>,
+        timeout: &Timeout<C>,
+    ) -> Result<(), CardProtocolError> {
+        timeout.verify(pp, &self.player_keys)?;
+        self.record_dropout(timeout.player, Dropout::Timeout)
+    }
+
+    fn recoverable(&self, player: usize) -> Result<&KeyEscrow<C>, CardProtocolError> {
+        let escrow = self
+            .escrow(player)
+            .ok_or(CardProtocolError::NoEscrow(player))?;
+        if self.dropout(player).is_none() {
+            return Err(CardProtocolError::NoDropoutRecorded(player));
+        }
+
+        Ok(escrow)
+    }
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> DLCards<C, D> {
+    /// Share the secret key of `player` among all other players. Any `threshold` of them can later
+    /// stand in for the player.
+    pub fn escrow_key<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        player: usize,
+        sk: &<Self as BarnettSmartProtocol>::PlayerSecretKey,
+        player_keys: &[PublicKey<C>],
+        threshold: usize,
+    ) -> Result<KeyEscrow<C>, CardProtocolError> {
+        if player >= player_keys.len() {
+            return Err(CardProtocolError::UnknownPlayer(player));
+        }
+        if threshold == 0 || threshold >= player_keys.len() {
+            return Err(CardProtocolError::InvalidEscrow(player));
+        }
+
+        let generator = pp.enc_parameters.generator;
+        let coefficients = ark_std::iter::once(*sk.expose())
+            .chain((1..threshold).map(|_| C::ScalarField::rand(rng)))
+            .map(PlayerSecretKey::<C>::new)
+            .collect::<Vec<_>>();
+        let commitments = coefficients
+            .iter()
+            .map(|coefficient| {
+                generator
+                    .mul(coefficient.expose().into_repr())
+                    .into_affine()
+            })
+            .collect();
+
+        let shares = player_keys
+            .iter()
+            .enumerate()
+            .filter(|(recipient, _)| *recipient != player)
+            .map(|(recipient, recipient_key)| {
+                let x = share_index::<C>(recipient);
+                let share = PlayerSecretKey::<C>::new(
+                    coefficients
+                        .iter()
+                        .rev()
+                        .fold(C::ScalarField::zero(), |acc, coefficient| {
+                            acc * x + coefficient.expose()
+                        }),
+                );
+
+                let r = PlayerSecretKey::<C>::new(C::ScalarField::rand(rng));
+                let ephemeral_key = generator.mul(r.expose().into_repr()).into_affine();
+                let shared_secret = recipient_key.mul(r.expose().into_repr()).into_affine();
+                let pad = share_pad::<C>(&shared_secret)?;
+
+                Ok(EncryptedShare {
+                    recipient,
+                    ephemeral_key,
+                    ciphertext: *share.expose() + pad,
+                })
+            })
+            .collect::<Result<Vec<_>, CardProtocolError>>()?;
+
+        Ok(KeyEscrow {
+            player,
+            commitments,
+            shares,
+        })
+    }
+
+    /// Decrypt the share `recipient` holds in `escrow` and check it against the commitments.
+    pub fn decrypt_escrow_share(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        escrow: &KeyEscrow<C>,
+        recipient: usize,
+        sk: &<Self as BarnettSmartProtocol>::PlayerSecretKey,
+    ) -> Result<<Self as BarnettSmartProtocol>::PlayerSecretKey, CardProtocolError> {
+        let encrypted = escrow.share_for(recipient)?;
+        let shared_secret = encrypted
+            .ephemeral_key
+            .mul(sk.expose().into_repr())
+            .into_affine();
+
+        Self::open_share(pp, escrow, encrypted, &shared_secret).ok_or(
+            CardProtocolError::InvalidEscrowShare(escrow.player, recipient),
+        )
+    }
+
+    /// Publish a provable complaint about the share `recipient` received in `escrow`.
+    pub fn complain_about_share<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        escrow: &KeyEscrow<C>,
+        recipient: usize,
+        sk: &<Self as BarnettSmartProtocol>::PlayerSecretKey,
+        pk: &PublicKey<C>,
+    ) -> Result<ShareComplaint<C>, CardProtocolError> {
+        let encrypted = escrow.share_for(recipient)?;
+        let shared_secret = encrypted
+            .ephemeral_key
+            .mul(sk.expose().into_repr())
+            .into_affine();
+
+        let proof = prove_chaum_pedersen::<C, D, R>(
+            rng,
+            COMPLAINT_RNG_SEED,
+            (&pp.enc_parameters.generator, &encrypted.ephemeral_key),
+            (pk, &shared_secret),
+            sk.expose(),
+        )?;
+
+        Ok(ShareComplaint {
+            player: escrow.player,
+            recipient,
+            shared_secret,
+            proof,
+        })
+    }
+
+    /// Check a complaint. Returns `Ok(())` if the complaint is justified, in which case the escrow
+    /// of the accused player should be rejected.
+    pub fn verify_complaint(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        escrow: &KeyEscrow<C>,
+        recipient_key: &PublicKey<C>,
+        complaint: &ShareComplaint<C>,
+    ) -> Result<(), CryptoError> {
+        let encrypted = escrow
+            .share_for(complaint.recipient)
+            .map_err(|_| complaint_error())?;
+        if complaint.player != escrow.player {
+            return Err(complaint_error());
+        }
+
+        verify_chaum_pedersen::<C, D>(
+            COMPLAINT_RNG_SEED,
+            (&pp.enc_parameters.generator, &encrypted.ephemeral_key),
+            (recipient_key, &complaint.shared_secret),
+            &complaint.proof,
+        )?;
+
+        match Self::open_share(pp, escrow, encrypted, &complaint.shared_secret) {
+            Some(_) => Err(complaint_error()),
+            None => Ok(()),
+        }
+    }
+
+    /// Compute `holder`'s partial reveal token for `masked_card` on behalf of `player`, who must
+    /// have been recorded as dropped out in `registry`.
+    pub fn compute_partial_reveal_token<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        registry: &EscrowRegistry<C>,
+        player: usize,
+        holder: usize,
+        share: &<Self as BarnettSmartProtocol>::PlayerSecretKey,
+        masked_card: &MaskedCard<C>,
+    ) -> Result<PartialRevealToken<C>, CardProtocolError> {
+        let escrow = registry.recoverable(player)?;
+        let share_key = escrow.share_public_key(holder);
+
+        let token =
+            el_gamal::Plaintext(masked_card.0.mul(share.expose().into_repr()).into_affine());
+
+        let proof = prove_chaum_pedersen::<C, D, R>(
+            rng,
+            PARTIAL_REVEAL_RNG_SEED,
+            (&masked_card.0, &pp.enc_parameters.generator),
+            (&token.0, &share_key),
+            share.expose(),
+        )?;
+
+        Ok(PartialRevealToken {
+            holder,
+            token,
+            proof,
+        })
+    }
+
+    /// Verify the partial reveal tokens of a quorum and combine them into the reveal token of
+    /// `player` for `masked_card`.
+    pub fn recover_reveal_token(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        registry: &EscrowRegistry<C>,
+        player: usize,
+        masked_card: &MaskedCard<C>,
+        partial_tokens: &[PartialRevealToken<C>],
+    ) -> Result<RevealToken<C>, CardProtocolError> {
+        let escrow = registry.recoverable(player)?;
+
+        let mut holders = partial_tokens
+            .iter()
+            .map(|partial| partial.holder)
+            .collect::<Vec<_>>();
+        holders.sort_unstable();
+        holders.dedup();
+        if holders.len() != partial_tokens.len()
+            || holders
+                .iter()
+                .any(|holder| *holder == player || *holder >= registry.num_of_players())
+        {
+            return Err(CardProtocolError::InvalidEscrow(player));
+        }
+        if partial_tokens.len() < escrow.threshold() {
+            return Err(CardProtocolError::NotEnoughShares(
+                escrow.threshold(),
+                partial_tokens.len(),
+            ));
+        }
+
+        let mut token = C::zero();
+        for partial in partial_tokens {
+            let share_key = escrow.share_public_key(partial.holder);
+            verify_chaum_pedersen::<C, D>(
+                PARTIAL_REVEAL_RNG_SEED,
+                (&masked_card.0, &pp.enc_parameters.generator),
+                (&partial.token.0, &share_key),
+                &partial.proof,
+            )?;
+
+            let coefficient = lagrange_at_zero::<C>(partial.holder, &holders);
+            token += partial.token.0.mul(coefficient.into_repr());
+        }
+
+        Ok(el_gamal::Plaintext(token.into_affine()))
+    }
+
+    /// Unmask a card where the reveal tokens of dropped-out players were recovered from escrow.
+    /// The tokens in `decryption_key` are verified as in `unmask`; the recovered ones were checked
+    /// share by share in `recover_reveal_token`.
+    pub fn unmask_with_recovered(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        decryption_key: &[(
+            RevealToken<C>,
+            <Self as BarnettSmartProtocol>::ZKProofReveal,
+            PublicKey<C>,
+        )],
+        recovered: &[RevealToken<C>],
+        masked_card: &MaskedCard<C>,
+    ) -> Result<Card<C>, CardProtocolError> {
+        for (token, proof, pk) in decryption_key {
+            Self::verify_reveal(pp, pk, token, masked_card, proof)?;
+        }
+
+        let aggregate_token = decryption_key
+            .iter()
+            .map(|(token, _, _)| token)
+            .chain(recovered.iter())
+            .fold(RevealToken::<C>::zero(), |acc, token| acc + *token);
+
+        Ok(el_gamal::Plaintext(masked_card.1 + (-aggregate_token.0)))
+    }
+
+    fn open_share(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        escrow: &KeyEscrow<C>,
+        encrypted: &EncryptedShare<C>,
+        shared_secret: &C::Affine,
+    ) -> Option<<Self as BarnettSmartProtocol>::PlayerSecretKey> {
+        let pad = share_pad::<C>(shared_secret).ok()?;
+        let share = PlayerSecretKey::new(encrypted.ciphertext - pad);
+
+        let share_key = pp
+            .enc_parameters
+            .generator
+            .mul(*share.expose())
+            .into_affine();
+        if share_key == escrow.share_public_key(encrypted.recipient) {
+            Some(share)
+        } else {
+            None
+        }
+    }
+}
+
+/// Evaluation point of the share held by player `index`.
+fn share_index<C: ProjectiveCurve>(index: usize) -> C::ScalarField {
+    C::ScalarField::from((index + 1) as u64)
+}
+
+/// Scalar used to pad a share, derived from the hashed el-Gamal shared secret.
+fn share_pad<C: ProjectiveCurve>(shared_secret: &C::Affine) -> Result<C::ScalarField, CryptoError> {
+    let digest = Blake2s::digest(&to_bytes![ESCROW_KEY_DERIVATION_TAG, shared_secret]?);
+
+    Ok(C::ScalarField::from_le_bytes_mod_order(&digest))
+}
+
+/// Lagrange coefficient of `holder` for interpolating at 0 over the shares of `holders`.
+fn lagrange_at_zero<C: ProjectiveCurve>(holder: usize, holders: &[usize]) -> C::ScalarField {
+    let x = share_index::<C>(holder);
+    holders.iter().filter(|other| **other != holder).fold(
+        C::ScalarField::from(1u64),
+        |acc, other| {
+            let x_other = share_index::<C>(*other);
+            acc * x_other * (x_other - x).inverse().unwrap()
+        },
+    )
+}
+
+fn complaint_error() -> CryptoError {
+    CryptoError::ProofVerificationError(String::from("Escrow Complaint"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::discrete_log_cards::test_utils::setup_players;
+    use crate::discrete_log_cards::{self, Dropout, EscrowRegistry};
+    use crate::error::CardProtocolError;
+    use crate::BarnettSmartProtocol;
+
+    use ark_ff::UniformRand;
+    use ark_std::One;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+
+    type Card = discrete_log_cards::Card<Curve>;
+
+    #[test]
+    fn test_recover_reveal_token() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 2, 4).unwrap();
+        let (players, aggregate_key) = setup_players(rng, &parameters, 4);
+        let player_keys = players.iter().map(|(pk, _)| *pk).collect::<Vec<_>>();
+
+        // Player 3 escrows their key with a threshold of 2
+        let mut registry = EscrowRegistry::new(player_keys.clone());
+        let escrow =
+            CardProtocol::escrow_key(rng, &parameters, 3, &players[3].1, &player_keys, 2).unwrap();
+        registry.register(escrow.clone()).unwrap();
+
+        let shares = (0..3)
+            .map(|holder| {
+                CardProtocol::decrypt_escrow_share(&parameters, &escrow, holder, &players[holder].1)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        // Player 0 cannot open player 1's share
+        assert_eq!(
+            CardProtocol::decrypt_escrow_share(&parameters, &escrow, 1, &players[0].1),
+            Err(CardProtocolError::InvalidEscrowShare(3, 1))
+        );
+
+        let card = Card::rand(rng);
+        let alpha = Scalar::rand(rng);
+        let (masked_card, _) =
+            CardProtocol::mask(rng, &parameters, &aggregate_key, &card, &alpha).unwrap();
+
+        // Nothing can be recovered before a dropout is recorded
+        assert_eq!(
+            CardProtocol::compute_partial_reveal_token(
+                rng,
+                &parameters,
+                &registry,
+                3,
+                0,
+                &shares[0],
+                &masked_card
+            )
+            .err(),
+            Some(CardProtocolError::NoDropoutRecorded(3))
+        );
+
+        registry.record_dropout(3, Dropout::Timeout).unwrap();
+        let partial_tokens = [0, 2]
+            .iter()
+            .map(|holder| {
+                CardProtocol::compute_partial_reveal_token(
+                    rng,
+                    &parameters,
+                    &registry,
+                    3,
+                    *holder,
+                    &shares[*holder],
+                    &masked_card,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            CardProtocol::recover_reveal_token(
+                &parameters,
+                &registry,
+                3,
+                &masked_card,
+                &partial_tokens[..1]
+            ),
+            Err(CardProtocolError::NotEnoughShares(2, 1))
+        );
+
+        let recovered = CardProtocol::recover_reveal_token(
+            &parameters,
+            &registry,
+            3,
+            &masked_card,
+            &partial_tokens,
+        )
+        .unwrap();
+        let (expected, _) = CardProtocol::compute_reveal_token(
+            rng,
+            &parameters,
+            &players[3].1,
+            &players[3].0,
+            &masked_card,
+        )
+        .unwrap();
+        assert_eq!(recovered, expected);
+
+        let decryption_key = players[..3]
+            .iter()
+            .map(|(pk, sk)| {
+                let (token, proof) =
+                    CardProtocol::compute_reveal_token(rng, &parameters, sk, pk, &masked_card)
+                        .unwrap();
+                (token, proof, *pk)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            CardProtocol::unmask_with_recovered(
+                &parameters,
+                &decryption_key,
+                &[recovered],
+                &masked_card
+            ),
+            Ok(card)
+        );
+
+        // A partial token from the wrong share is rejected
+        let mut forged = partial_tokens.clone();
+        forged[1].holder = 1;
+        assert!(CardProtocol::recover_reveal_token(
+            &parameters,
+            &registry,
+            3,
+            &masked_card,
+            &forged
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_complaint() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 2, 4).unwrap();
+        let (players, _) = setup_players(rng, &parameters, 3);
+        let player_keys = players.iter().map(|(pk, _)| *pk).collect::<Vec<_>>();
+
+        let mut escrow =
+            CardProtocol::escrow_key(rng, &parameters, 0, &players[0].1, &player_keys, 2).unwrap();
+
+        // An honest share cannot be complained about
+        let complaint = CardProtocol::complain_about_share(
+            rng,
+            &parameters,
+            &escrow,
+            1,
+            &players[1].1,
+            &players[1].0,
+        )
+        .unwrap();
+        assert!(
+            CardProtocol::verify_complaint(&parameters, &escrow, &players[1].0, &complaint)
+                .is_err()
+        );
+
+        // Player 0 cheats on the share of player 2
+        escrow.shares[1].ciphertext += Scalar::one();
+        assert_eq!(
+            CardProtocol::decrypt_escrow_share(&parameters, &escrow, 2, &players[2].1),
+            Err(CardProtocolError::InvalidEscrowShare(0, 2))
+        );
+
+        let complaint = CardProtocol::complain_about_share(
+            rng,
+            &parameters,
+            &escrow,
+            2,
+            &players[2].1,
+            &players[2].0,
+        )
+        .unwrap();
+        assert_eq!(
+            CardProtocol::verify_complaint(&parameters, &escrow, &players[2].0, &complaint),
+            Ok(())
+        );
+
+        // A complaint with a wrong decryption is rejected
+        let mut forged = complaint;
+        forged.shared_secret = players[0].0;
+        assert!(
+            CardProtocol::verify_complaint(&parameters, &escrow, &players[2].0, &forged).is_err()
+        );
+    }
+}
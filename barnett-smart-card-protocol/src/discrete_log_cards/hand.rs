@@ -0,0 +1,407 @@
+// Statements about a player's hidden hand.
+//
+// A player learns their cards through private reveals but never learns the masking factors, so on
+// their own they cannot prove anything about them. Instead, the other players publish their
+// (verifiable) reveal tokens for the card. Subtracting those tokens re-targets the ciphertext to
+// the holder's own key: `(c0, c1 - sum(tokens)) = (c0, card + sk * c0)`. The holder then proves, with
+// a disjunctive Chaum-Pedersen argument, that the designated ciphertext opens to one of a public set
+// of cards without saying which one.
+
+use super::{Card, DLCards, MaskedCard, Parameters, PlayerSecretKey, PublicKey, RevealToken};
+use super::{TaggedProof, TranscriptHash};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{to_bytes, PrimeField, UniformRand, Zero};
+use ark_marlin::rng::FiatShamirRng;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::io::{Read, Write};
+use ark_std::rand::Rng;
+use ark_std::{string::String, vec, vec::Vec};
+use proof_essentials::error::CryptoError;
+use proof_essentials::homomorphic_encryption::el_gamal;
+
+const HAND_CLAIM_RNG_SEED: &[u8] = b"Hand Claim Proof";
+
+/// A public statement about the cards a player holds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HandClaim<C: ProjectiveCurve> {
+    /// Every card in the hand belongs to the set, e.g. "I have no card of the led suit" is
+    /// expressed as membership of every card in the complement of that suit.
+    AllIn(Vec<Card<C>>),
+    /// At least one card in the hand belongs to the set, e.g. "I hold a card of rank at least X".
+    AnyIn(Vec<Card<C>>),
+}
+
+impl<C: ProjectiveCurve> HandClaim<C> {
+    /// Claim that no card in the hand belongs to `excluded`, given the full set of possible cards.
+    pub fn none_of(universe: &[Card<C>], excluded: &[Card<C>]) -> Self {
+        Self::AllIn(
+            universe
+                .iter()
+                .filter(|card| !excluded.contains(card))
+                .copied()
+                .collect(),
+        )
+    }
+}
+
+/// A 1-out-of-k proof that a designated card opens to one of several candidate cards. Each branch
+/// carries its share of the challenge and its response; the commitments are recomputed by the
+/// verifier.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct DisjunctiveProof<C: ProjectiveCurve> {
+    pub challenges: Vec<C::ScalarField>,
+    pub responses: Vec<C::ScalarField>,
+}
+
+/// Proof of a `HandClaim`. An `AllIn` claim carries one disjunctive proof per card, an `AnyIn`
+/// claim a single disjunctive proof over every (card, candidate) pair.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ZKProofHandClaim<C: ProjectiveCurve> {
+    pub proofs: Vec<DisjunctiveProof<C>>,
+}
+
+/// One branch of a disjunction: the holder knows `sk` such that `pk = sk * g` and `v = sk * u`.
+struct Branch<C: ProjectiveCurve> {
+    u: C::Affine,
+    v: C::Affine,
+}
+
+impl<C: ProjectiveCurve> Branch<C> {
+    fn new(designated: &MaskedCard<C>, candidate: &Card<C>) -> Self {
+        Self {
+            u: designated.0,
+            v: designated.1 + -candidate.0,
+        }
+    }
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> DLCards<C, D> {
+    /// Re-target a masked card to the key of the player holding it by removing the reveal tokens
+    /// of every other player. Each token is checked against its proof, so every player computes
+    /// the same designated card from the published tokens.
+    pub fn designate(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        others_tokens: &[(
+            RevealToken<C>,
+            <Self as BarnettSmartProtocol>::ZKProofReveal,
+            PublicKey<C>,
+        )],
+        masked_card: &MaskedCard<C>,
+    ) -> Result<MaskedCard<C>, CardProtocolError> {
+        let mut aggregate_token = RevealToken::<C>::zero();
+        for (token, proof, pk) in others_tokens {
+            Self::verify_reveal(pp, pk, token, masked_card, proof)?;
+            aggregate_token = aggregate_token + *token;
+        }
+
+        Ok(el_gamal::Ciphertext(
+            masked_card.0,
+            masked_card.1 + -aggregate_token.0,
+        ))
+    }
+
+    /// Prove a claim about a hand of designated cards (see `designate`) held by the owner of `pk`.
+    pub fn prove_hand_claim<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        sk: &PlayerSecretKey<C>,
+        pk: &PublicKey<C>,
+        hand: &[MaskedCard<C>],
+        claim: &HandClaim<C>,
+    ) -> Result<TaggedProof<ZKProofHandClaim<C>>, CardProtocolError> {
+        let opened = hand
+            .iter()
+            .map(|card| card.1 + -card.0.mul(sk.expose().into_repr()).into_affine())
+            .collect::<Vec<_>>();
+
+        let proofs = match claim {
+            HandClaim::AllIn(set) => hand
+                .iter()
+                .zip(opened.iter())
+                .map(|(card, plain)| {
+                    let branches = set
+                        .iter()
+                        .map(|candidate| Branch::new(card, candidate))
+                        .collect::<Vec<_>>();
+                    let real = set
+                        .iter()
+                        .position(|candidate| candidate.0 == *plain)
+                        .ok_or(CardProtocolError::ClaimNotSatisfied)?;
+                    Ok(disjunctive_prove::<_, C, D>(
+                        rng, pp, pk, sk, &branches, real,
+                    )?)
+                })
+                .collect::<Result<Vec<_>, CardProtocolError>>()?,
+            HandClaim::AnyIn(set) => {
+                let mut branches = Vec::with_capacity(hand.len() * set.len());
+                let mut real = None;
+                for (card, plain) in hand.iter().zip(opened.iter()) {
+                    for candidate in set {
+                        if real.is_none() && candidate.0 == *plain {
+                            real = Some(branches.len());
+                        }
+                        branches.push(Branch::new(card, candidate));
+                    }
+                }
+                let real = real.ok_or(CardProtocolError::ClaimNotSatisfied)?;
+                vec![disjunctive_prove::<_, C, D>(
+                    rng, pp, pk, sk, &branches, real,
+                )?]
+            }
+        };
+
+        Ok(TaggedProof::new(D::ID, ZKProofHandClaim { proofs }))
+    }
+
+    /// Verify a claim about the designated hand of the owner of `pk`.
+    pub fn verify_hand_claim(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        pk: &PublicKey<C>,
+        hand: &[MaskedCard<C>],
+        claim: &HandClaim<C>,
+        proof: &TaggedProof<ZKProofHandClaim<C>>,
+    ) -> Result<(), CryptoError> {
+        let proof = proof.expect_hash::<D>()?;
+        match claim {
+            HandClaim::AllIn(set) => {
+                if proof.proofs.len() != hand.len() {
+                    return Err(hand_claim_error());
+                }
+                for (card, card_proof) in hand.iter().zip(proof.proofs.iter()) {
+                    let branches = set
+                        .iter()
+                        .map(|candidate| Branch::new(card, candidate))
+                        .collect::<Vec<_>>();
+                    disjunctive_verify::<C, D>(pp, pk, &branches, card_proof)?;
+                }
+                Ok(())
+            }
+            HandClaim::AnyIn(set) => {
+                if proof.proofs.len() != 1 {
+                    return Err(hand_claim_error());
+                }
+                let branches = hand
+                    .iter()
+                    .flat_map(|card| {
+                        set.iter()
+                            .map(move |candidate| Branch::new(card, candidate))
+                    })
+                    .collect::<Vec<_>>();
+                disjunctive_verify::<C, D>(pp, pk, &branches, &proof.proofs[0])
+            }
+        }
+    }
+}
+
+fn hand_claim_error() -> CryptoError {
+    CryptoError::ProofVerificationError(String::from("Hand Claim"))
+}
+
+fn challenge<C: ProjectiveCurve, D: TranscriptHash>(
+    pp: &Parameters<C>,
+    pk: &PublicKey<C>,
+    branches: &[Branch<C>],
+    commitments: &[(C::Affine, C::Affine)],
+) -> Result<C::ScalarField, CryptoError> {
+    let mut fs_rng = FiatShamirRng::<D>::from_seed(&to_bytes![
+        HAND_CLAIM_RNG_SEED,
+        pp.enc_parameters.generator,
+        pk
+    ]?);
+    for (branch, (a, b)) in branches.iter().zip(commitments.iter()) {
+        fs_rng.absorb(&to_bytes![branch.u, branch.v, a, b]?);
+    }
+
+    Ok(C::ScalarField::rand(&mut fs_rng))
+}
+
+/// Commitments of a simulated (or completed) branch: `a = z * g - e * pk`, `b = z * u - e * v`.
+fn branch_commitments<C: ProjectiveCurve>(
+    generator: &C::Affine,
+    pk: &PublicKey<C>,
+    branch: &Branch<C>,
+    challenge: &C::ScalarField,
+    response: &C::ScalarField,
+) -> (C::Affine, C::Affine) {
+    let a = generator.mul(response.into_repr()) - pk.mul(challenge.into_repr());
+    let b = branch.u.mul(response.into_repr()) - branch.v.mul(challenge.into_repr());
+
+    (a.into_affine(), b.into_affine())
+}
+
+fn disjunctive_prove<R: Rng, C: ProjectiveCurve, D: TranscriptHash>(
+    rng: &mut R,
+    pp: &Parameters<C>,
+    pk: &PublicKey<C>,
+    sk: &PlayerSecretKey<C>,
+    branches: &[Branch<C>],
+    real: usize,
+) -> Result<DisjunctiveProof<C>, CryptoError> {
+    let generator = pp.enc_parameters.generator;
+
+    let mut challenges = Vec::with_capacity(branches.len());
+    let mut responses = Vec::with_capacity(branches.len());
+    let mut commitments = Vec::with_capacity(branches.len());
+
+    // Simulate every branch but the real one, for which we commit to a fresh nonce
+    let omega = C::ScalarField::rand(rng);
+    for (i, branch) in branches.iter().enumerate() {
+        if i == real {
+            challenges.push(C::ScalarField::zero());
+            responses.push(C::ScalarField::zero());
+            commitments.push((
+                generator.mul(omega.into_repr()).into_affine(),
+                branch.u.mul(omega.into_repr()).into_affine(),
+            ));
+        } else {
+            let e = C::ScalarField::rand(rng);
+            let z = C::ScalarField::rand(rng);
+            commitments.push(branch_commitments(&generator, pk, branch, &e, &z));
+            challenges.push(e);
+            responses.push(z);
+        }
+    }
+
+    let c = challenge::<C, D>(pp, pk, branches, &commitments)?;
+    let simulated: C::ScalarField = challenges.iter().sum();
+    challenges[real] = c - simulated;
+    responses[real] = omega + challenges[real] * sk.expose();
+
+    Ok(DisjunctiveProof {
+        challenges,
+        responses,
+    })
+}
+
+fn disjunctive_verify<C: ProjectiveCurve, D: TranscriptHash>(
+    pp: &Parameters<C>,
+    pk: &PublicKey<C>,
+    branches: &[Branch<C>],
+    proof: &DisjunctiveProof<C>,
+) -> Result<(), CryptoError> {
+    if branches.is_empty()
+        || proof.challenges.len() != branches.len()
+        || proof.responses.len() != branches.len()
+    {
+        return Err(hand_claim_error());
+    }
+
+    let generator = pp.enc_parameters.generator;
+    let commitments = branches
+        .iter()
+        .zip(proof.challenges.iter().zip(proof.responses.iter()))
+        .map(|(branch, (e, z))| branch_commitments(&generator, pk, branch, e, z))
+        .collect::<Vec<_>>();
+
+    let c = challenge::<C, D>(pp, pk, branches, &commitments)?;
+    let sum: C::ScalarField = proof.challenges.iter().sum();
+    if sum != c {
+        return Err(hand_claim_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::discrete_log_cards::test_utils::setup_players;
+    use crate::discrete_log_cards::{self, HandClaim};
+    use crate::error::CardProtocolError;
+    use crate::BarnettSmartProtocol;
+
+    use ark_ff::UniformRand;
+    use ark_std::rand::Rng;
+    use proof_essentials::error::CryptoError;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+    type CardParameters = discrete_log_cards::Parameters<Curve>;
+    type PublicKey = discrete_log_cards::PublicKey<Curve>;
+    type SecretKey = discrete_log_cards::PlayerSecretKey<Curve>;
+
+    type Card = discrete_log_cards::Card<Curve>;
+    type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
+
+    /// Mask `cards` and designate them to player 0 using the tokens of every other player.
+    fn deal_to_first<R: Rng>(
+        rng: &mut R,
+        parameters: &CardParameters,
+        players: &[(PublicKey, SecretKey)],
+        aggregate_key: &PublicKey,
+        cards: &[Card],
+    ) -> Vec<MaskedCard> {
+        cards
+            .iter()
+            .map(|card| {
+                let alpha = Scalar::rand(rng);
+                let (masked, _) =
+                    CardProtocol::mask(rng, parameters, aggregate_key, card, &alpha).unwrap();
+                let tokens = players[1..]
+                    .iter()
+                    .map(|(pk, sk)| {
+                        let (token, proof) =
+                            CardProtocol::compute_reveal_token(rng, parameters, sk, pk, &masked)
+                                .unwrap();
+                        (token, proof, *pk)
+                    })
+                    .collect::<Vec<_>>();
+                CardProtocol::designate(parameters, &tokens, &masked).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hand_claims() {
+        let rng = &mut thread_rng();
+        let m = 4;
+        let n = 13;
+
+        let parameters = CardProtocol::setup(rng, m, n).unwrap();
+        let (players, aggregate_key) = setup_players(rng, &parameters, 4);
+        let (pk, sk) = &players[0];
+
+        let deck = (0..8).map(|_| Card::rand(rng)).collect::<Vec<_>>();
+        let (suit, others) = deck.split_at(4);
+
+        // The hand holds two cards outside of `suit`
+        let hand = deal_to_first(rng, &parameters, &players, &aggregate_key, &others[1..3]);
+
+        let no_suit = HandClaim::none_of(&deck, suit);
+        let proof =
+            CardProtocol::prove_hand_claim(rng, &parameters, sk, pk, &hand, &no_suit).unwrap();
+        assert_eq!(
+            Ok(()),
+            CardProtocol::verify_hand_claim(&parameters, pk, &hand, &no_suit, &proof)
+        );
+
+        let some_other = HandClaim::AnyIn(vec![others[0], others[2]]);
+        let proof =
+            CardProtocol::prove_hand_claim(rng, &parameters, sk, pk, &hand, &some_other).unwrap();
+        assert_eq!(
+            Ok(()),
+            CardProtocol::verify_hand_claim(&parameters, pk, &hand, &some_other, &proof)
+        );
+
+        // A proof does not transfer to another claim
+        assert_eq!(
+            CardProtocol::verify_hand_claim(&parameters, pk, &hand, &no_suit, &proof),
+            Err(CryptoError::ProofVerificationError(String::from(
+                "Hand Claim"
+            )))
+        );
+
+        let false_claim = HandClaim::AllIn(suit.to_vec());
+        assert_eq!(
+            CardProtocol::prove_hand_claim(rng, &parameters, sk, pk, &hand, &false_claim),
+            Err(CardProtocolError::ClaimNotSatisfied)
+        );
+    }
+}
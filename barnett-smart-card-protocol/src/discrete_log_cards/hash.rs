@@ -0,0 +1,608 @@
+// Hash functions for the Fiat-Shamir transcripts of the protocol proofs.
+//
+// `DLCards` takes the hash as a type parameter, defaulting to Blake2s. SHA-256 and Keccak-256
+// are cheap to recompute on most chains, and Poseidon over the scalar field of BN254 is cheap to
+// recompute in BN254 circuits, whose constraint field is the base field of Ed-on-BN254. Every
+// proof records the hash it was computed with, so that a verifier instantiated with another hash
+// rejects it instead of failing on an unrelated challenge.
+//
+// Chaum-Pedersen challenges are drawn from a `FiatShamirRng` over the hash, except with Poseidon:
+// there the challenge is a Poseidon hash of the affine coordinates of the points, for curves
+// defined over the scalar field of BN254, so that `constraints::PoseidonGadget` can recompute it.
+
+use super::poseidon_constants::{MDS, ROUND_CONSTANTS};
+
+use ark_ec::ProjectiveCurve;
+use ark_ff::{to_bytes, BigInteger, BigInteger256, Field, PrimeField, UniformRand, Zero};
+use ark_marlin::rng::FiatShamirRng;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalDeserializeWithFlags, CanonicalSerialize, SWFlags,
+    SerializationError,
+};
+use ark_std::boxed::Box;
+use ark_std::fmt;
+use ark_std::io::{Read, Write};
+use ark_std::string::ToString;
+use ark_std::vec::Vec;
+use blake2::digest::generic_array::{typenum::U32, GenericArray};
+use blake2::digest::{FixedOutput, Reset, Update};
+use blake2::{Blake2s, Digest};
+use once_cell::race::OnceBox;
+use proof_essentials::error::CryptoError;
+
+/// Hash functions available for Fiat-Shamir transcripts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FiatShamirHash {
+    Blake2s = 0,
+    Sha256 = 1,
+    Keccak256 = 2,
+    Poseidon = 3,
+}
+
+impl FiatShamirHash {
+    fn from_u8(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::Blake2s),
+            1 => Some(Self::Sha256),
+            2 => Some(Self::Keccak256),
+            3 => Some(Self::Poseidon),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FiatShamirHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Blake2s => "Blake2s",
+            Self::Sha256 => "SHA-256",
+            Self::Keccak256 => "Keccak-256",
+            Self::Poseidon => "Poseidon",
+        })
+    }
+}
+
+/// A hash function usable as the Fiat-Shamir hash of `DLCards`.
+pub trait TranscriptHash: Digest + Sized {
+    const ID: FiatShamirHash;
+
+    /// The challenge of a Chaum-Pedersen proof with commitments `(a, b)` for the statement
+    /// `(x, y)` under the bases `(g, h)`, with the Fiat-Shamir `seed`.
+    fn chaum_pedersen_challenge<C: ProjectiveCurve>(
+        seed: &[u8],
+        (g, h): (&C::Affine, &C::Affine),
+        (x, y): (&C::Affine, &C::Affine),
+        (a, b): (&C::Affine, &C::Affine),
+    ) -> Result<C::ScalarField, CryptoError> {
+        let mut fs_rng = FiatShamirRng::<Self>::from_seed(&to_bytes![seed]?);
+        fs_rng.absorb(&to_bytes![b"chaum_pedersen", g, h, x, y, a, b]?);
+        Ok(C::ScalarField::rand(&mut fs_rng))
+    }
+}
+
+impl TranscriptHash for Blake2s {
+    const ID: FiatShamirHash = FiatShamirHash::Blake2s;
+}
+
+impl TranscriptHash for Sha256 {
+    const ID: FiatShamirHash = FiatShamirHash::Sha256;
+}
+
+impl TranscriptHash for Keccak256 {
+    const ID: FiatShamirHash = FiatShamirHash::Keccak256;
+}
+
+impl TranscriptHash for Poseidon {
+    const ID: FiatShamirHash = FiatShamirHash::Poseidon;
+
+    /// Absorbs the affine coordinates of the points with `h = poseidon_hash(h, coordinate)`,
+    /// starting from the Poseidon digest of `seed`, and keeps the low
+    /// `C::ScalarField::size_in_bits() - 1` bits of `h`. The base field of `C` must be
+    /// `PoseidonField`, as for Ed-on-BN254.
+    fn chaum_pedersen_challenge<C: ProjectiveCurve>(
+        seed: &[u8],
+        (g, h): (&C::Affine, &C::Affine),
+        (x, y): (&C::Affine, &C::Affine),
+        (a, b): (&C::Affine, &C::Affine),
+    ) -> Result<C::ScalarField, CryptoError> {
+        let mut state = Self::hash_to_field(seed);
+        for point in [g, h, x, y, a, b] {
+            for coordinate in poseidon_coordinates::<C>(point)? {
+                state = poseidon_hash(&state, &coordinate);
+            }
+        }
+
+        let bits = state.into_repr().to_bits_le();
+        let bits = &bits[..bits.len().min(C::ScalarField::size_in_bits() - 1)];
+        Ok(
+            C::ScalarField::from_repr(<C::ScalarField as PrimeField>::BigInt::from_bits_le(bits))
+                .expect("fewer bits than the modulus"),
+        )
+    }
+}
+
+/// A proof together with the hash of the transcript it was computed with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaggedProof<P> {
+    pub hash: FiatShamirHash,
+    pub proof: P,
+}
+
+impl<P> TaggedProof<P> {
+    pub fn new(hash: FiatShamirHash, proof: P) -> Self {
+        Self { hash, proof }
+    }
+
+    /// The proof, if it was computed with the transcript hash `D`.
+    pub fn expect_hash<D: TranscriptHash>(&self) -> Result<&P, CryptoError> {
+        if self.hash == D::ID {
+            Ok(&self.proof)
+        } else {
+            Err(CryptoError::ProofVerificationError(
+                self.hash.to_string() + " transcript",
+            ))
+        }
+    }
+}
+
+impl<P: CanonicalSerialize> CanonicalSerialize for TaggedProof<P> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        (self.hash as u8).serialize(&mut writer)?;
+        self.proof.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        1 + self.proof.serialized_size()
+    }
+}
+
+impl<P: CanonicalDeserialize> CanonicalDeserialize for TaggedProof<P> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let hash = FiatShamirHash::from_u8(u8::deserialize(&mut reader)?)
+            .ok_or(SerializationError::InvalidData)?;
+        let proof = P::deserialize(&mut reader)?;
+
+        Ok(Self { hash, proof })
+    }
+}
+
+/// Adapts a hash of the `digest` 0.10 ecosystem to the `digest` 0.9 traits that `FiatShamirRng`
+/// expects.
+#[derive(Clone, Default)]
+pub struct Digest010<H>(H);
+
+impl<H: sha2::digest::Update> Update for Digest010<H> {
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        sha2::digest::Update::update(&mut self.0, data.as_ref());
+    }
+}
+
+impl<H: sha2::digest::FixedOutputReset + sha2::digest::OutputSizeUser<OutputSize = U32>> FixedOutput
+    for Digest010<H>
+{
+    type OutputSize = U32;
+
+    fn finalize_into(mut self, out: &mut GenericArray<u8, U32>) {
+        sha2::digest::FixedOutputReset::finalize_into_reset(&mut self.0, out);
+    }
+
+    fn finalize_into_reset(&mut self, out: &mut GenericArray<u8, U32>) {
+        sha2::digest::FixedOutputReset::finalize_into_reset(&mut self.0, out);
+    }
+}
+
+impl<H: sha2::digest::Reset> Reset for Digest010<H> {
+    fn reset(&mut self) {
+        sha2::digest::Reset::reset(&mut self.0);
+    }
+}
+
+pub type Sha256 = Digest010<sha2::Sha256>;
+
+pub type Keccak256 = Digest010<sha3::Keccak256>;
+
+/// The scalar field of BN254, over which `Poseidon` is defined. It is the base field of
+/// Ed-on-BN254.
+pub type PoseidonField = ark_bn254::Fr;
+
+/// Poseidon over the scalar field of BN254, as instantiated by circomlib: width 3, `x^5` S-box, 8
+/// full and 57 partial rounds, with the round constants and MDS matrix published with the
+/// reference implementation. `poseidon_hash(a, b)` is circomlib's `Poseidon([a, b])`, and
+/// `constraints::PoseidonGadget` computes it in a circuit over the same field.
+///
+/// As a `Digest`, the input is split into 31-byte little-endian chunks, each chunk `m` is absorbed
+/// with `h = poseidon_hash(h, m)` starting from `h = 0`, and the output is
+/// `poseidon_hash(h, length in bytes)` as 32 little-endian bytes.
+#[derive(Clone, Default)]
+pub struct Poseidon {
+    buffer: Vec<u8>,
+}
+
+pub(crate) const POSEIDON_WIDTH: usize = 3;
+pub(crate) const POSEIDON_FULL_ROUNDS: usize = 8;
+pub(crate) const POSEIDON_PARTIAL_ROUNDS: usize = 57;
+
+/// Number of input bytes packed into each field element.
+const POSEIDON_CHUNK_SIZE: usize = 31;
+
+fn poseidon_element(limbs: &[u64; 4]) -> PoseidonField {
+    PoseidonField::from_repr(BigInteger256::new(*limbs)).expect("constants are canonical")
+}
+
+static POSEIDON_ROUND_CONSTANTS: OnceBox<Vec<[PoseidonField; POSEIDON_WIDTH]>> = OnceBox::new();
+static POSEIDON_MDS: OnceBox<[[PoseidonField; POSEIDON_WIDTH]; POSEIDON_WIDTH]> = OnceBox::new();
+
+/// Round constants of `Poseidon`, `POSEIDON_WIDTH` per round.
+pub(crate) fn poseidon_round_constants() -> &'static [[PoseidonField; POSEIDON_WIDTH]] {
+    POSEIDON_ROUND_CONSTANTS.get_or_init(|| {
+        Box::new(
+            ROUND_CONSTANTS
+                .chunks(POSEIDON_WIDTH)
+                .map(|round| {
+                    [
+                        poseidon_element(&round[0]),
+                        poseidon_element(&round[1]),
+                        poseidon_element(&round[2]),
+                    ]
+                })
+                .collect(),
+        )
+    })
+}
+
+/// MDS matrix of `Poseidon`, by rows.
+pub(crate) fn poseidon_mds() -> &'static [[PoseidonField; POSEIDON_WIDTH]; POSEIDON_WIDTH] {
+    POSEIDON_MDS.get_or_init(|| {
+        let mut mds = [[PoseidonField::zero(); POSEIDON_WIDTH]; POSEIDON_WIDTH];
+        for (row, limbs) in mds.iter_mut().zip(MDS.iter()) {
+            for (entry, limbs) in row.iter_mut().zip(limbs.iter()) {
+                *entry = poseidon_element(limbs);
+            }
+        }
+        Box::new(mds)
+    })
+}
+
+/// The affine coordinates of `point`, on a curve defined over `PoseidonField`.
+fn poseidon_coordinates<C: ProjectiveCurve>(
+    point: &C::Affine,
+) -> Result<[PoseidonField; 2], CryptoError> {
+    if C::BaseField::extension_degree() != 1
+        || C::BaseField::characteristic() != PoseidonField::characteristic()
+    {
+        return Err(CryptoError::ProofVerificationError(
+            FiatShamirHash::Poseidon.to_string() + " transcript",
+        ));
+    }
+
+    // Both curve models encode an uncompressed point as `x || y`, short Weierstrass points with
+    // their flags in the spare bits of `y`
+    let mut bytes = Vec::new();
+    point
+        .serialize_uncompressed(&mut bytes)
+        .map_err(|e| CryptoError::IoError(e.to_string()))?;
+    let (x, y) = bytes.split_at(bytes.len() / 2);
+    let x = PoseidonField::deserialize_uncompressed(x);
+    let y = PoseidonField::deserialize_with_flags::<_, SWFlags>(y).map(|(y, _)| y);
+
+    match (x, y) {
+        (Ok(x), Ok(y)) => Ok([x, y]),
+        (Err(e), _) | (_, Err(e)) => Err(CryptoError::IoError(e.to_string())),
+    }
+}
+
+/// Whether round `round` applies the S-box to every element of the state.
+pub(crate) fn poseidon_full_round(round: usize) -> bool {
+    let half_full = POSEIDON_FULL_ROUNDS / 2;
+    round < half_full || round >= half_full + POSEIDON_PARTIAL_ROUNDS
+}
+
+/// Poseidon of two field elements: the permutation of `[0, a, b]`, of which the first element is
+/// the output.
+pub fn poseidon_hash(a: &PoseidonField, b: &PoseidonField) -> PoseidonField {
+    let mds = poseidon_mds();
+    let mut state = [PoseidonField::zero(), *a, *b];
+    for (round, constants) in poseidon_round_constants().iter().enumerate() {
+        for (x, c) in state.iter_mut().zip(constants) {
+            *x += c;
+        }
+        if poseidon_full_round(round) {
+            for x in state.iter_mut() {
+                *x = x.pow([5]);
+            }
+        } else {
+            state[0] = state[0].pow([5]);
+        }
+
+        let mut mixed = [PoseidonField::zero(); POSEIDON_WIDTH];
+        for (out, row) in mixed.iter_mut().zip(mds.iter()) {
+            *out = row.iter().zip(state.iter()).map(|(m, x)| *m * x).sum();
+        }
+        state = mixed;
+    }
+
+    state[0]
+}
+
+impl Poseidon {
+    /// The digest of `bytes` as a field element.
+    pub(crate) fn hash_to_field(bytes: &[u8]) -> PoseidonField {
+        let h = bytes
+            .chunks(POSEIDON_CHUNK_SIZE)
+            .map(PoseidonField::from_le_bytes_mod_order)
+            .fold(PoseidonField::zero(), |h, m| poseidon_hash(&h, &m));
+        poseidon_hash(&h, &PoseidonField::from(bytes.len() as u64))
+    }
+
+    fn hash(bytes: &[u8]) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        out.copy_from_slice(&Self::hash_to_field(bytes).into_repr().to_bytes_le());
+        out
+    }
+}
+
+impl Update for Poseidon {
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.buffer.extend_from_slice(data.as_ref());
+    }
+}
+
+impl FixedOutput for Poseidon {
+    type OutputSize = U32;
+
+    fn finalize_into(self, out: &mut GenericArray<u8, U32>) {
+        out.copy_from_slice(&Self::hash(&self.buffer));
+    }
+
+    fn finalize_into_reset(&mut self, out: &mut GenericArray<u8, U32>) {
+        out.copy_from_slice(&Self::hash(&self.buffer));
+        self.buffer.clear();
+    }
+}
+
+impl Reset for Poseidon {
+    fn reset(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        poseidon_hash, FiatShamirHash, Keccak256, Poseidon, PoseidonField, Sha256, TaggedProof,
+        TranscriptHash,
+    };
+    use crate::discrete_log_cards::{self, DLCards, HandClaim, PreparedKey, PreparedParameters};
+    use crate::BarnettSmartProtocol;
+
+    use ark_ec::ProjectiveCurve;
+    use ark_ff::UniformRand;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::str::FromStr;
+    use blake2::{Blake2s, Digest};
+    use proof_essentials::utils::permutation::Permutation;
+    use proof_essentials::utils::rand::sample_vector;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    type Card = discrete_log_cards::Card<Curve>;
+
+    /// Play masking, shuffling and revealing over `C` with transcript hash `D`, and check that a verifier
+    /// using Blake2s rejects the proofs unless `D` is Blake2s.
+    fn play_with_hash<C: ProjectiveCurve, D: TranscriptHash>() {
+        let rng = &mut thread_rng();
+        let parameters = DLCards::<C, D>::setup(rng, 2, 2).unwrap();
+        let (pk, sk) = DLCards::<C, D>::player_keygen(rng, &parameters).unwrap();
+
+        let key_proof =
+            DLCards::<C, D>::prove_key_ownership(rng, &parameters, &pk, &sk, b"Alice").unwrap();
+        assert_eq!(key_proof.hash, D::ID);
+        assert!(
+            DLCards::<C, D>::verify_key_ownership(&parameters, &pk, b"Alice", &key_proof).is_ok()
+        );
+
+        let cards = (0..4)
+            .map(|_| discrete_log_cards::Card::<C>::rand(rng))
+            .collect::<Vec<_>>();
+        let (deck, mask_proofs): (Vec<_>, Vec<_>) = cards
+            .iter()
+            .map(|card| {
+                let alpha = C::ScalarField::rand(rng);
+                DLCards::<C, D>::mask(rng, &parameters, &pk, card, &alpha).unwrap()
+            })
+            .unzip();
+        for ((card, masked), proof) in cards.iter().zip(&deck).zip(&mask_proofs) {
+            assert!(DLCards::<C, D>::verify_mask(&parameters, &pk, card, masked, proof).is_ok());
+        }
+        assert!(
+            DLCards::<C, D>::verify_mask_batch(&parameters, &pk, &cards, &deck, &mask_proofs)
+                .is_ok()
+        );
+
+        let permutation = Permutation::new(rng, 4);
+        let masking_factors = sample_vector(rng, 4);
+        let (shuffled, shuffle_proof) = DLCards::<C, D>::shuffle_and_remask(
+            rng,
+            &parameters,
+            &pk,
+            &deck,
+            &masking_factors,
+            &permutation,
+        )
+        .unwrap();
+        assert!(DLCards::<C, D>::verify_shuffle(
+            &parameters,
+            &pk,
+            &deck,
+            &shuffled,
+            &shuffle_proof
+        )
+        .is_ok());
+
+        let (token, reveal_proof) =
+            DLCards::<C, D>::compute_reveal_token(rng, &parameters, &sk, &pk, &shuffled[0])
+                .unwrap();
+        let opened = DLCards::<C, D>::unmask(
+            &parameters,
+            &vec![(token, reveal_proof, pk)],
+            &shuffled[0],
+            false,
+        )
+        .unwrap();
+        assert!(cards.contains(&opened));
+
+        let blake2s_accepts = DLCards::<C, Blake2s>::verify_mask(
+            &parameters,
+            &pk,
+            &cards[0],
+            &deck[0],
+            &mask_proofs[0],
+        )
+        .is_ok();
+        assert_eq!(blake2s_accepts, D::ID == FiatShamirHash::Blake2s);
+        let blake2s_accepts = DLCards::<C, Blake2s>::verify_reveal(
+            &parameters,
+            &pk,
+            &token,
+            &shuffled[0],
+            &reveal_proof,
+        )
+        .is_ok();
+        assert_eq!(blake2s_accepts, D::ID == FiatShamirHash::Blake2s);
+
+        // The proofs of the extensions are computed with `D` as well
+        let alpha = C::ScalarField::rand(rng);
+        let (_, prepared_proof) = DLCards::<C, D>::mask_prepared(
+            rng,
+            &PreparedParameters::new(&parameters),
+            &PreparedKey::new(&pk),
+            &cards[0],
+            &alpha,
+        )
+        .unwrap();
+        assert_eq!(prepared_proof.hash, D::ID);
+
+        let rekeying = DLCards::<C, D>::rekey_leave(rng, &parameters, &sk, &pk, &shuffled).unwrap();
+        assert!(DLCards::<C, D>::verify_rekeying(&parameters, &shuffled, &rekeying).is_ok());
+        let blake2s_accepts =
+            DLCards::<C, Blake2s>::verify_rekeying(&parameters, &shuffled, &rekeying).is_ok();
+        assert_eq!(blake2s_accepts, D::ID == FiatShamirHash::Blake2s);
+
+        let claim = HandClaim::AnyIn(cards.clone());
+        let hand = &shuffled[..1];
+        let claim_proof =
+            DLCards::<C, D>::prove_hand_claim(rng, &parameters, &sk, &pk, hand, &claim).unwrap();
+        assert!(
+            DLCards::<C, D>::verify_hand_claim(&parameters, &pk, hand, &claim, &claim_proof)
+                .is_ok()
+        );
+        let blake2s_accepts =
+            DLCards::<C, Blake2s>::verify_hand_claim(&parameters, &pk, hand, &claim, &claim_proof)
+                .is_ok();
+        assert_eq!(blake2s_accepts, D::ID == FiatShamirHash::Blake2s);
+    }
+
+    #[test]
+    fn test_protocol_with_each_hash() {
+        play_with_hash::<Curve, Blake2s>();
+        play_with_hash::<Curve, Sha256>();
+        play_with_hash::<Curve, Keccak256>();
+        // Poseidon challenges need a curve over its field
+        play_with_hash::<ark_ed_on_bn254::EdwardsProjective, Poseidon>();
+
+        let rng = &mut thread_rng();
+        let parameters = DLCards::<Curve, Poseidon>::setup(rng, 2, 2).unwrap();
+        let (pk, _) = DLCards::<Curve, Poseidon>::player_keygen(rng, &parameters).unwrap();
+        let card = Card::rand(rng);
+        let alpha = Scalar::rand(rng);
+        assert!(DLCards::<Curve, Poseidon>::mask(rng, &parameters, &pk, &card, &alpha).is_err());
+    }
+
+    #[test]
+    fn test_relabelled_proof_is_rejected() {
+        let rng = &mut thread_rng();
+        let parameters = DLCards::<Curve, Sha256>::setup(rng, 2, 2).unwrap();
+        let (pk, _) = DLCards::<Curve, Sha256>::player_keygen(rng, &parameters).unwrap();
+        let card = Card::rand(rng);
+        let alpha = Scalar::rand(rng);
+        let (masked, mut proof) =
+            DLCards::<Curve, Sha256>::mask(rng, &parameters, &pk, &card, &alpha).unwrap();
+
+        // Claiming another hash does not make the proof verify under it
+        proof.hash = FiatShamirHash::Keccak256;
+        assert!(
+            DLCards::<Curve, Keccak256>::verify_mask(&parameters, &pk, &card, &masked, &proof)
+                .is_err()
+        );
+        assert!(
+            DLCards::<Curve, Sha256>::verify_mask(&parameters, &pk, &card, &masked, &proof)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(
+            Sha256::digest(b"abc").as_slice(),
+            [
+                0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+                0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+                0xf2, 0x00, 0x15, 0xad
+            ]
+        );
+        assert_eq!(
+            Keccak256::digest(b"").as_slice(),
+            [
+                0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7,
+                0x03, 0xc0, 0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04,
+                0x5d, 0x85, 0xa4, 0x70
+            ]
+        );
+    }
+
+    #[test]
+    fn test_poseidon() {
+        // Known answer of circomlib's `Poseidon([1, 2])`
+        assert_eq!(
+            poseidon_hash(&PoseidonField::from(1u64), &PoseidonField::from(2u64)),
+            PoseidonField::from_str(
+                "7853200120776062878684798364095072458815029376092732009249414926327459813530"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Poseidon::digest(b"abc").as_slice(),
+            [
+                0xc9, 0x3e, 0xaf, 0x98, 0x00, 0x83, 0xe5, 0x6c, 0xde, 0x9b, 0xce, 0x6c, 0xad, 0xb4,
+                0x51, 0xd6, 0x0b, 0x67, 0xe8, 0xf8, 0x21, 0xae, 0x94, 0xf4, 0xc3, 0xee, 0x57, 0x75,
+                0x95, 0xa5, 0x33, 0x1d
+            ]
+        );
+
+        let digest = Poseidon::digest(b"Masking Proof");
+        assert_eq!(digest, Poseidon::digest(b"Masking Proof"));
+        assert_ne!(digest, Poseidon::digest(b"Remasking Proof"));
+        // Trailing zeros change the length, and so the digest
+        assert_ne!(Poseidon::digest(b""), Poseidon::digest(&[0u8]));
+
+        let mut hasher = Poseidon::new();
+        hasher.update(b"Masking ");
+        hasher.update(b"Proof");
+        assert_eq!(hasher.finalize(), digest);
+    }
+
+    #[test]
+    fn test_tagged_proof_encoding() {
+        let tagged = TaggedProof::new(FiatShamirHash::Keccak256, 7u64);
+        let mut bytes = Vec::new();
+        tagged.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), tagged.serialized_size());
+        assert_eq!(TaggedProof::<u64>::deserialize(&bytes[..]).unwrap(), tagged);
+
+        bytes[0] = 9;
+        assert!(TaggedProof::<u64>::deserialize(&bytes[..]).is_err());
+    }
+}
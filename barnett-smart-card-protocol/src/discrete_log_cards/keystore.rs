@@ -0,0 +1,229 @@
+// Password-protected export format of player secret keys.
+
+use super::PlayerSecretKey;
+use crate::error::CardProtocolError;
+
+use ark_ec::ProjectiveCurve;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::Rng;
+use borsh::{BorshDeserialize, BorshSerialize};
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::Hmac;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+const KEYSTORE_MAGIC: &[u8; 4] = b"MPSK";
+const KEYSTORE_VERSION: u8 = 1;
+
+/// Default number of PBKDF2-HMAC-SHA256 rounds used to derive the encryption key from a password.
+pub const DEFAULT_KDF_ROUNDS: u32 = 100_000;
+
+/// Fewest PBKDF2 rounds a keystore may be created or opened with.
+pub const MIN_KDF_ROUNDS: u32 = 10_000;
+
+impl<C: ProjectiveCurve> PlayerSecretKey<C> {
+    /// Encrypt the key under `password` into the keystore format.
+    pub fn encrypt<R: Rng>(
+        &self,
+        rng: &mut R,
+        password: &[u8],
+    ) -> Result<EncryptedSecretKey, CardProtocolError> {
+        self.encrypt_with_rounds(rng, password, DEFAULT_KDF_ROUNDS)
+    }
+
+    pub fn encrypt_with_rounds<R: Rng>(
+        &self,
+        rng: &mut R,
+        password: &[u8],
+        kdf_rounds: u32,
+    ) -> Result<EncryptedSecretKey, CardProtocolError> {
+        if kdf_rounds < MIN_KDF_ROUNDS {
+            return Err(CardProtocolError::KeystoreError);
+        }
+
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut nonce);
+
+        let mut encrypted = EncryptedSecretKey {
+            version: KEYSTORE_VERSION,
+            kdf_rounds,
+            salt,
+            nonce,
+            ciphertext: Vec::new(),
+        };
+
+        let mut plaintext = Vec::new();
+        self.expose()
+            .serialize(&mut plaintext)
+            .map_err(|_| CardProtocolError::KeystoreError)?;
+
+        let cipher = encrypted.cipher(password);
+        let result = cipher.encrypt(
+            Nonce::from_slice(&encrypted.nonce),
+            Payload {
+                msg: &plaintext,
+                aad: &encrypted.header(),
+            },
+        );
+        plaintext.zeroize();
+
+        encrypted.ciphertext = result.map_err(|_| CardProtocolError::KeystoreError)?;
+        Ok(encrypted)
+    }
+}
+
+/// A player secret key encrypted with ChaCha20-Poly1305 under a key derived from a password with
+/// PBKDF2-HMAC-SHA256. The header fields are authenticated along with the key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct EncryptedSecretKey {
+    pub version: u8,
+    pub kdf_rounds: u32,
+    pub salt: [u8; 16],
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedSecretKey {
+    fn header(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(37);
+        header.extend_from_slice(KEYSTORE_MAGIC);
+        header.push(self.version);
+        header.extend_from_slice(&self.kdf_rounds.to_le_bytes());
+        header.extend_from_slice(&self.salt);
+        header.extend_from_slice(&self.nonce);
+        header
+    }
+
+    fn cipher(&self, password: &[u8]) -> ChaCha20Poly1305 {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2::<Hmac<Sha256>>(password, &self.salt, self.kdf_rounds, &mut key);
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        key.zeroize();
+        cipher
+    }
+
+    /// Recover the secret key. Fails if the password is wrong or the keystore was tampered with.
+    pub fn decrypt<C: ProjectiveCurve>(
+        &self,
+        password: &[u8],
+    ) -> Result<PlayerSecretKey<C>, CardProtocolError> {
+        if self.version != KEYSTORE_VERSION || self.kdf_rounds < MIN_KDF_ROUNDS {
+            return Err(CardProtocolError::KeystoreError);
+        }
+
+        let cipher = self.cipher(password);
+        let mut plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(&self.nonce),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &self.header(),
+                },
+            )
+            .map_err(|_| CardProtocolError::KeystoreError)?;
+
+        let result = C::ScalarField::deserialize(plaintext.as_slice());
+        plaintext.zeroize();
+
+        result
+            .map(PlayerSecretKey::new)
+            .map_err(|_| CardProtocolError::KeystoreError)
+    }
+
+    /// Encode as `magic || version || kdf_rounds || salt || nonce || ciphertext`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header();
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+
+    /// Decode a keystore, rejecting one with fewer than `MIN_KDF_ROUNDS` rounds.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CardProtocolError> {
+        if bytes.len() < 37 || &bytes[0..4] != KEYSTORE_MAGIC {
+            return Err(CardProtocolError::KeystoreError);
+        }
+
+        let mut kdf_rounds = [0u8; 4];
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        kdf_rounds.copy_from_slice(&bytes[5..9]);
+        salt.copy_from_slice(&bytes[9..25]);
+        nonce.copy_from_slice(&bytes[25..37]);
+
+        let kdf_rounds = u32::from_le_bytes(kdf_rounds);
+        if kdf_rounds < MIN_KDF_ROUNDS {
+            return Err(CardProtocolError::KeystoreError);
+        }
+
+        Ok(Self {
+            version: bytes[4],
+            kdf_rounds,
+            salt,
+            nonce,
+            ciphertext: bytes[37..].to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::discrete_log_cards::{self, EncryptedSecretKey, MIN_KDF_ROUNDS};
+    use crate::error::CardProtocolError;
+    use crate::BarnettSmartProtocol;
+
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 4, 13).unwrap();
+        let (_, sk) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+
+        assert_eq!(format!("{:?}", sk), "PlayerSecretKey(<redacted>)");
+
+        assert_eq!(
+            sk.encrypt_with_rounds(rng, b"hunter2", MIN_KDF_ROUNDS - 1),
+            Err(CardProtocolError::KeystoreError)
+        );
+        let encrypted = sk
+            .encrypt_with_rounds(rng, b"hunter2", MIN_KDF_ROUNDS)
+            .unwrap();
+        let decoded = EncryptedSecretKey::from_bytes(&encrypted.to_bytes()).unwrap();
+        assert_eq!(decoded, encrypted);
+        assert_eq!(decoded.decrypt::<Curve>(b"hunter2").unwrap(), sk);
+
+        assert_eq!(
+            decoded.decrypt::<Curve>(b"hunter3"),
+            Err(CardProtocolError::KeystoreError)
+        );
+
+        // A keystore claiming too few rounds is not even parsed
+        let mut weak = encrypted.clone();
+        weak.kdf_rounds = 0;
+        assert_eq!(
+            EncryptedSecretKey::from_bytes(&weak.to_bytes()),
+            Err(CardProtocolError::KeystoreError)
+        );
+        assert_eq!(
+            weak.decrypt::<Curve>(b"hunter2"),
+            Err(CardProtocolError::KeystoreError)
+        );
+
+        let mut tampered = encrypted;
+        tampered.kdf_rounds += 1;
+        assert_eq!(
+            tampered.decrypt::<Curve>(b"hunter2"),
+            Err(CardProtocolError::KeystoreError)
+        );
+    }
+}
@@ -43,7 +43,8 @@ mod test {
     type Card = discrete_log_cards::Card<Curve>;
     type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
 
-    type MaskingProof = chaum_pedersen_dl_equality::proof::Proof<Curve>;
+    type MaskingProof =
+        discrete_log_cards::TaggedProof<chaum_pedersen_dl_equality::proof::Proof<Curve>>;
 
     fn setup_players<R: Rng>(
         rng: &mut R,
@@ -1,5 +1,6 @@
 use super::BarnettSmartProtocol;
 use super::{Mask, Remask, Reveal};
+use batch::{prove_chaum_pedersen, verify_chaum_pedersen};
 
 use crate::error::CardProtocolError;
 
@@ -8,7 +9,9 @@ use ark_ff::{to_bytes, One, PrimeField, ToBytes};
 use ark_marlin::rng::FiatShamirRng;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use ark_std::io::{Read, Write};
+use ark_std::marker::PhantomData;
 use ark_std::rand::Rng;
+use ark_std::vec::Vec;
 use ark_std::Zero;
 use blake2::Blake2s;
 use proof_essentials::error::CryptoError;
@@ -23,19 +26,59 @@ use proof_essentials::zkp::{
     proofs::{chaum_pedersen_dl_equality, schnorr_identification},
     ArgumentOfKnowledge,
 };
-use std::marker::PhantomData;
 
 // mod key_ownership;
+mod batch;
+#[cfg(feature = "r1cs")]
+pub mod constraints;
+mod deal;
+mod escrow;
+mod hand;
+mod hash;
+#[cfg(feature = "std")]
+mod keystore;
 mod masking;
+mod poseidon_constants;
+mod prepared;
+mod rekey;
+mod remask_pool;
 mod remasking;
 mod reveal;
+mod secret_key;
+mod shuffle_chain;
+mod signature;
+#[cfg(test)]
+pub(crate) mod test_utils;
 mod tests;
-
+mod transcript;
+
+pub use deal::*;
+pub use escrow::*;
+pub use hand::*;
+pub use hash::*;
+#[cfg(feature = "std")]
+pub use keystore::*;
+pub use prepared::*;
+pub use rekey::*;
+pub use remask_pool::*;
+pub use secret_key::*;
+pub use shuffle_chain::*;
+pub use signature::*;
+pub use transcript::*;
+
+#[cfg(feature = "std")]
 mod wasm;
+#[cfg(feature = "std")]
 pub use wasm::*;
-
-pub struct DLCards<C: ProjectiveCurve> {
-    _group: PhantomData<C>,
+#[cfg(feature = "js")]
+mod wasm_classes;
+#[cfg(feature = "js")]
+pub use wasm_classes::*;
+
+/// The discrete log card protocol over the curve `C`, with `D` as the hash of its Fiat-Shamir
+/// transcripts.
+pub struct DLCards<C: ProjectiveCurve, D: TranscriptHash = Blake2s> {
+    _group: PhantomData<(C, D)>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
@@ -71,8 +114,6 @@ impl<C: ProjectiveCurve> Parameters<C> {
 
 pub type PublicKey<C> = el_gamal::PublicKey<C>;
 
-pub type PlayerSecretKey<C> = el_gamal::SecretKey<C>;
-
 /// An open playing card. In this Discrete Log-based implementation of the Barnett-Smart card protocol
 /// a card is an el-Gamal plaintext. We create a type alias to implement the `Mask` trait on it.
 pub type Card<C> = el_gamal::Plaintext<C>;
@@ -87,7 +128,7 @@ pub type MaskedCard<C> = el_gamal::Ciphertext<C>;
 pub type RevealToken<C> = el_gamal::Plaintext<C>;
 
 pub type ZKProofShuffle<C: ProjectiveCurve> =
-    shuffle::proof::Proof<C::ScalarField, ElGamal<C>, PedersenCommitment<C>>;
+    TaggedProof<shuffle::proof::Proof<C::ScalarField, ElGamal<C>, PedersenCommitment<C>>>;
 
 const KEY_OWN_RNG_SEED: &'static [u8] = b"Key Ownership Proof";
 const MASKING_RNG_SEED: &'static [u8] = b"Masking Proof";
@@ -95,7 +136,91 @@ const REMASKING_RNG_SEED: &'static [u8] = b"Remasking Proof";
 const REVEAL_RNG_SEED: &'static [u8] = b"Reveal Proof";
 const SHUFFLE_RNG_SEED: &'static [u8] = b"Shuffle Proof";
 
-impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
+impl<C: ProjectiveCurve, D: TranscriptHash> DLCards<C, D> {
+    /// Prove that `masked_card` encrypts `original_card` with randomness `r`.
+    fn prove_masking<R: Rng>(
+        rng: &mut R,
+        pp: &Parameters<C>,
+        shared_key: &PublicKey<C>,
+        original_card: &Card<C>,
+        masked_card: &MaskedCard<C>,
+        r: &C::ScalarField,
+    ) -> Result<<Self as BarnettSmartProtocol>::ZKProofMasking, CardProtocolError> {
+        let gen = pp.enc_parameters.generator;
+
+        // Map to Chaum-Pedersen statement
+        let minus_one = -C::ScalarField::one();
+        let negative_original = original_card.0.mul(minus_one).into_affine();
+        let statement_cipher = masked_card.1 + negative_original;
+
+        prove_chaum_pedersen::<C, D, R>(
+            rng,
+            MASKING_RNG_SEED,
+            (&gen, shared_key),
+            (&masked_card.0, &statement_cipher),
+            r,
+        )
+    }
+
+    /// Prove that `remasked` is `original_card` remasked with `alpha`.
+    fn prove_remasking<R: Rng>(
+        rng: &mut R,
+        pp: &Parameters<C>,
+        shared_key: &PublicKey<C>,
+        original_card: &MaskedCard<C>,
+        remasked: &MaskedCard<C>,
+        alpha: &C::ScalarField,
+    ) -> Result<<Self as BarnettSmartProtocol>::ZKProofRemasking, CardProtocolError> {
+        // Map to Chaum-Pedersen statement
+        let minus_one = -C::ScalarField::one();
+        let negative_original = *original_card * minus_one;
+        let statement_cipher = *remasked + negative_original;
+
+        prove_chaum_pedersen::<C, D, R>(
+            rng,
+            REMASKING_RNG_SEED,
+            (&pp.enc_parameters.generator, shared_key),
+            (&statement_cipher.0, &statement_cipher.1),
+            alpha,
+        )
+    }
+
+    /// Prove that `masked_shuffled` is `deck` permuted by `permutation` and remasked with
+    /// `masking_factors`.
+    fn prove_shuffle<R: Rng>(
+        rng: &mut R,
+        pp: &Parameters<C>,
+        shared_key: &PublicKey<C>,
+        deck: &Vec<MaskedCard<C>>,
+        masked_shuffled: &Vec<MaskedCard<C>>,
+        masking_factors: &Vec<C::ScalarField>,
+        permutation: &Permutation,
+    ) -> Result<ZKProofShuffle<C>, CardProtocolError> {
+        let shuffle_parameters = shuffle::Parameters::new(
+            &pp.enc_parameters,
+            shared_key,
+            &pp.commit_parameters,
+            &pp.generator,
+        );
+
+        let shuffle_statement = shuffle::Statement::new(deck, masked_shuffled, pp.m, pp.n);
+
+        let witness = shuffle::Witness::new(permutation, masking_factors);
+
+        let mut fs_rng = FiatShamirRng::<D>::from_seed(&to_bytes![SHUFFLE_RNG_SEED]?);
+        let proof = shuffle::ShuffleArgument::prove(
+            rng,
+            &shuffle_parameters,
+            &shuffle_statement,
+            &witness,
+            &mut fs_rng,
+        )?;
+
+        Ok(TaggedProof::new(D::ID, proof))
+    }
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> BarnettSmartProtocol for DLCards<C, D> {
     type Scalar = C::ScalarField;
     type Enc = ElGamal<C>;
     type Comm = PedersenCommitment<C>;
@@ -108,11 +233,11 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
     type MaskedCard = MaskedCard<C>;
     type RevealToken = RevealToken<C>;
 
-    type ZKProofKeyOwnership = schnorr_identification::proof::Proof<C>;
-    type ZKProofMasking = chaum_pedersen_dl_equality::proof::Proof<C>;
-    type ZKProofRemasking = chaum_pedersen_dl_equality::proof::Proof<C>;
-    type ZKProofReveal = chaum_pedersen_dl_equality::proof::Proof<C>;
-    type ZKProofShuffle = shuffle::proof::Proof<Self::Scalar, Self::Enc, Self::Comm>;
+    type ZKProofKeyOwnership = TaggedProof<schnorr_identification::proof::Proof<C>>;
+    type ZKProofMasking = TaggedProof<chaum_pedersen_dl_equality::proof::Proof<C>>;
+    type ZKProofRemasking = TaggedProof<chaum_pedersen_dl_equality::proof::Proof<C>>;
+    type ZKProofReveal = TaggedProof<chaum_pedersen_dl_equality::proof::Proof<C>>;
+    type ZKProofShuffle = ZKProofShuffle<C>;
 
     fn setup<R: Rng>(
         rng: &mut R,
@@ -138,7 +263,7 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
     ) -> Result<(Self::PlayerPublicKey, Self::PlayerSecretKey), CardProtocolError> {
         let (pk, sk) = Self::Enc::keygen(&pp.enc_parameters, rng)?;
 
-        Ok((pk, sk))
+        Ok((pk, PlayerSecretKey::new(sk)))
     }
 
     fn prove_key_ownership<B: ToBytes, R: Rng>(
@@ -149,15 +274,17 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
         player_public_info: B,
     ) -> Result<Self::ZKProofKeyOwnership, CryptoError> {
         let mut fs_rng =
-            FiatShamirRng::<Blake2s>::from_seed(&to_bytes![KEY_OWN_RNG_SEED, player_public_info]?);
+            FiatShamirRng::<D>::from_seed(&to_bytes![KEY_OWN_RNG_SEED, player_public_info]?);
 
-        schnorr_identification::SchnorrIdentification::prove(
+        let proof = schnorr_identification::SchnorrIdentification::prove(
             rng,
             &pp.enc_parameters.generator,
             pk,
-            sk,
+            sk.expose(),
             &mut fs_rng,
-        )
+        )?;
+
+        Ok(TaggedProof::new(D::ID, proof))
     }
 
     fn verify_key_ownership<B: ToBytes>(
@@ -167,11 +294,11 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
         proof: &Self::ZKProofKeyOwnership,
     ) -> Result<(), CryptoError> {
         let mut fs_rng =
-            FiatShamirRng::<Blake2s>::from_seed(&to_bytes![KEY_OWN_RNG_SEED, player_public_info]?);
+            FiatShamirRng::<D>::from_seed(&to_bytes![KEY_OWN_RNG_SEED, player_public_info]?);
         schnorr_identification::SchnorrIdentification::verify(
             &pp.enc_parameters.generator,
             pk,
-            proof,
+            proof.expect_hash::<D>()?,
             &mut fs_rng,
         )
     }
@@ -206,26 +333,7 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
         r: &Self::Scalar,
     ) -> Result<(Self::MaskedCard, Self::ZKProofMasking), CardProtocolError> {
         let masked_card = original_card.mask(&pp.enc_parameters, shared_key, r)?;
-        let gen = pp.enc_parameters.generator;
-
-        // Map to Chaum-Pedersen parameters
-        let cp_parameters = chaum_pedersen_dl_equality::Parameters::new(&gen, shared_key);
-
-        // Map to Chaum-Pedersen statement
-        let minus_one = -Self::Scalar::one();
-        let negative_original = original_card.0.mul(minus_one).into_affine();
-        let statement_cipher = masked_card.1 + negative_original;
-        let cp_statement =
-            chaum_pedersen_dl_equality::Statement::new(&masked_card.0, &statement_cipher);
-
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![MASKING_RNG_SEED]?);
-        let proof = chaum_pedersen_dl_equality::DLEquality::prove(
-            rng,
-            &cp_parameters,
-            &cp_statement,
-            r,
-            &mut fs_rng,
-        )?;
+        let proof = Self::prove_masking(rng, pp, shared_key, original_card, &masked_card, r)?;
 
         Ok((masked_card, proof))
     }
@@ -237,23 +345,16 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
         masked_card: &Self::MaskedCard,
         proof: &Self::ZKProofMasking,
     ) -> Result<(), CryptoError> {
-        // Map to Chaum-Pedersen parameters
-        let cp_parameters =
-            chaum_pedersen_dl_equality::Parameters::new(&pp.enc_parameters.generator, shared_key);
-
         // Map to Chaum-Pedersen statement
         let minus_one = -Self::Scalar::one();
         let negative_original = card.0.mul(minus_one).into_affine();
         let statement_cipher = masked_card.1 + negative_original;
-        let cp_statement =
-            chaum_pedersen_dl_equality::Statement::new(&masked_card.0, &statement_cipher);
 
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![MASKING_RNG_SEED]?);
-        chaum_pedersen_dl_equality::DLEquality::verify(
-            &cp_parameters,
-            &cp_statement,
+        verify_chaum_pedersen::<C, D>(
+            MASKING_RNG_SEED,
+            (&pp.enc_parameters.generator, shared_key),
+            (&masked_card.0, &statement_cipher),
             proof,
-            &mut fs_rng,
         )
     }
 
@@ -265,26 +366,7 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
         alpha: &Self::Scalar,
     ) -> Result<(Self::MaskedCard, Self::ZKProofRemasking), CardProtocolError> {
         let remasked = original_card.remask(&pp.enc_parameters, shared_key, alpha)?;
-
-        // Map to Chaum-Pedersen parameters
-        let cp_parameters =
-            chaum_pedersen_dl_equality::Parameters::new(&pp.enc_parameters.generator, shared_key);
-
-        // Map to Chaum-Pedersen statement
-        let minus_one = -C::ScalarField::one();
-        let negative_original = *original_card * minus_one;
-        let statement_cipher = remasked + negative_original;
-        let cp_statement =
-            chaum_pedersen_dl_equality::Statement::new(&statement_cipher.0, &statement_cipher.1);
-
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![REMASKING_RNG_SEED]?);
-        let proof = chaum_pedersen_dl_equality::DLEquality::prove(
-            rng,
-            &cp_parameters,
-            &cp_statement,
-            alpha,
-            &mut fs_rng,
-        )?;
+        let proof = Self::prove_remasking(rng, pp, shared_key, original_card, &remasked, alpha)?;
 
         Ok((remasked, proof))
     }
@@ -296,23 +378,16 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
         remasked: &Self::MaskedCard,
         proof: &Self::ZKProofRemasking,
     ) -> Result<(), CryptoError> {
-        // Map to Chaum-Pedersen parameters
-        let cp_parameters =
-            chaum_pedersen_dl_equality::Parameters::new(&pp.enc_parameters.generator, shared_key);
-
         // Map to Chaum-Pedersen statement
         let minus_one = -C::ScalarField::one();
         let negative_original = *original_masked * minus_one;
         let statement_cipher = *remasked + negative_original;
-        let cp_statement =
-            chaum_pedersen_dl_equality::Statement::new(&statement_cipher.0, &statement_cipher.1);
 
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![REMASKING_RNG_SEED]?);
-        chaum_pedersen_dl_equality::DLEquality::verify(
-            &cp_parameters,
-            &cp_statement,
+        verify_chaum_pedersen::<C, D>(
+            REMASKING_RNG_SEED,
+            (&pp.enc_parameters.generator, shared_key),
+            (&statement_cipher.0, &statement_cipher.1),
             proof,
-            &mut fs_rng,
         )
     }
 
@@ -323,25 +398,20 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
         pk: &Self::PlayerPublicKey,
         masked_card: &Self::MaskedCard,
     ) -> Result<(Self::RevealToken, Self::ZKProofReveal), CardProtocolError> {
-        let reveal_token: RevealToken<C> =
-            el_gamal::Plaintext(masked_card.0.into().mul(sk.into_repr()).into_affine());
-
-        // Map to Chaum-Pedersen parameters
-        let cp_parameters = chaum_pedersen_dl_equality::Parameters::new(
-            &masked_card.0,
-            &pp.enc_parameters.generator,
+        let reveal_token: RevealToken<C> = el_gamal::Plaintext(
+            masked_card
+                .0
+                .into()
+                .mul(sk.expose().into_repr())
+                .into_affine(),
         );
 
-        // Map to Chaum-Pedersen parameters
-        let cp_statement = chaum_pedersen_dl_equality::Statement::new(&reveal_token.0, pk);
-
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![REVEAL_RNG_SEED]?);
-        let proof = chaum_pedersen_dl_equality::DLEquality::prove(
+        let proof = prove_chaum_pedersen::<C, D, R>(
             rng,
-            &cp_parameters,
-            &cp_statement,
-            sk,
-            &mut fs_rng,
+            REVEAL_RNG_SEED,
+            (&masked_card.0, &pp.enc_parameters.generator),
+            (&reveal_token.0, pk),
+            sk.expose(),
         )?;
 
         Ok((reveal_token, proof))
@@ -354,21 +424,11 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
         masked_card: &Self::MaskedCard,
         proof: &Self::ZKProofReveal,
     ) -> Result<(), CryptoError> {
-        // Map to Chaum-Pedersen parameters
-        let cp_parameters = chaum_pedersen_dl_equality::Parameters::new(
-            &masked_card.0,
-            &pp.enc_parameters.generator,
-        );
-
-        // Map to Chaum-Pedersen parameters
-        let cp_statement = chaum_pedersen_dl_equality::Statement::new(&reveal_token.0, pk);
-
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![REVEAL_RNG_SEED]?);
-        chaum_pedersen_dl_equality::DLEquality::verify(
-            &cp_parameters,
-            &cp_statement,
+        verify_chaum_pedersen::<C, D>(
+            REVEAL_RNG_SEED,
+            (&masked_card.0, &pp.enc_parameters.generator),
+            (&reveal_token.0, pk),
             proof,
-            &mut fs_rng,
         )
     }
 
@@ -415,25 +475,14 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
                 masked_card.remask(&pp.enc_parameters, &shared_key, masking_factor)
             })
             .collect::<Result<Vec<_>, CardProtocolError>>()?;
-
-        let shuffle_parameters = shuffle::Parameters::new(
-            &pp.enc_parameters,
-            shared_key,
-            &pp.commit_parameters,
-            &pp.generator,
-        );
-
-        let shuffle_statement = shuffle::Statement::new(deck, &masked_shuffled, pp.m, pp.n);
-
-        let witness = shuffle::Witness::new(permutation, masking_factors);
-
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![SHUFFLE_RNG_SEED]?);
-        let proof = shuffle::ShuffleArgument::prove(
+        let proof = Self::prove_shuffle(
             rng,
-            &shuffle_parameters,
-            &shuffle_statement,
-            &witness,
-            &mut fs_rng,
+            pp,
+            shared_key,
+            deck,
+            &masked_shuffled,
+            masking_factors,
+            permutation,
         )?;
 
         Ok((masked_shuffled, proof))
@@ -455,11 +504,11 @@ impl<C: ProjectiveCurve> BarnettSmartProtocol for DLCards<C> {
 
         let shuffle_statement = shuffle::Statement::new(original_deck, shuffled_deck, pp.m, pp.n);
 
-        let mut fs_rng = FiatShamirRng::<Blake2s>::from_seed(&to_bytes![SHUFFLE_RNG_SEED]?);
+        let mut fs_rng = FiatShamirRng::<D>::from_seed(&to_bytes![SHUFFLE_RNG_SEED]?);
         shuffle::ShuffleArgument::verify(
             &shuffle_parameters,
             &shuffle_statement,
-            proof,
+            proof.expect_hash::<D>()?,
             &mut fs_rng,
         )
     }
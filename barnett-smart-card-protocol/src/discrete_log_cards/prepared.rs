@@ -0,0 +1,345 @@
+// Fixed-base precomputation for the two bases every masking operation multiplies: the ElGamal
+// generator and the aggregate public key.
+//
+// A masking of `card` with `r` is `(r * g, card + r * pk)`. Masking a deck and remasking it on
+// every shuffle multiplies the same `g` and `pk` over and over, so we precompute a window table
+// for each base once and turn every multiplication into a few table lookups and additions.
+//
+// The Chaum-Pedersen proofs of masking and remasking commit to `(w * g, w * pk)` for a random `w`,
+// so they are computed from the tables as well, with the same transcript as the unprepared
+// provers. The outputs are identical to the ones of the unprepared functions and the proofs
+// verify the same way. The shuffle argument is computed by proof-essentials, which cannot use the
+// tables, so `shuffle_and_remask_prepared` speeds up the remasking of the deck but not its proof.
+
+use super::batch::proof_from_elements;
+use super::{Card, DLCards, MaskedCard, Parameters, PublicKey, TaggedProof, TranscriptHash};
+use super::{ZKProofShuffle, MASKING_RNG_SEED, REMASKING_RNG_SEED};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::msm::FixedBaseMSM;
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{PrimeField, UniformRand};
+use ark_std::rand::Rng;
+use ark_std::vec::Vec;
+use proof_essentials::homomorphic_encryption::el_gamal;
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::zkp::proofs::chaum_pedersen_dl_equality::proof::Proof;
+
+/// Window size of the tables. A table holds `2^w * (bits / w)` points; with `w = 6` it takes
+/// about as long to build as ten plain multiplications and speeds up each multiplication by
+/// roughly an order of magnitude.
+const WINDOW_SIZE: usize = 6;
+
+/// Window table of the multiples of a fixed base.
+#[derive(Clone)]
+pub struct FixedBaseTable<C: ProjectiveCurve> {
+    outerc: usize,
+    table: Vec<Vec<C::Affine>>,
+}
+
+impl<C: ProjectiveCurve> FixedBaseTable<C> {
+    pub fn new(base: C) -> Self {
+        let scalar_size = C::ScalarField::size_in_bits();
+        let table = FixedBaseMSM::get_window_table(scalar_size, WINDOW_SIZE, base);
+
+        Self {
+            outerc: table.len(),
+            table,
+        }
+    }
+
+    pub fn mul(&self, scalar: &C::ScalarField) -> C {
+        FixedBaseMSM::windowed_mul(self.outerc, WINDOW_SIZE, &self.table, scalar)
+    }
+}
+
+/// The randomness `w` of a Chaum-Pedersen proof and its commitments `(w * g, w * pk)`.
+pub(crate) type ProofCommitment<C> = (<C as ProjectiveCurve>::ScalarField, MaskedCard<C>);
+
+/// Protocol parameters with a window table for the ElGamal generator.
+#[derive(Clone)]
+pub struct PreparedParameters<C: ProjectiveCurve> {
+    parameters: Parameters<C>,
+    generator: FixedBaseTable<C>,
+}
+
+impl<C: ProjectiveCurve> PreparedParameters<C> {
+    pub fn new(parameters: &Parameters<C>) -> Self {
+        Self {
+            parameters: parameters.clone(),
+            generator: FixedBaseTable::new(parameters.enc_parameters.generator.into_projective()),
+        }
+    }
+
+    pub fn parameters(&self) -> &Parameters<C> {
+        &self.parameters
+    }
+}
+
+/// An aggregate public key with its window table. Prepare it once the key is known and reuse it
+/// for the whole game.
+#[derive(Clone)]
+pub struct PreparedKey<C: ProjectiveCurve> {
+    key: PublicKey<C>,
+    table: FixedBaseTable<C>,
+}
+
+impl<C: ProjectiveCurve> PreparedKey<C> {
+    pub fn new(key: &PublicKey<C>) -> Self {
+        Self {
+            key: *key,
+            table: FixedBaseTable::new(key.into_projective()),
+        }
+    }
+
+    pub fn key(&self) -> &PublicKey<C> {
+        &self.key
+    }
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> DLCards<C, D> {
+    /// The encryption of zero with randomness `r`, i.e. `(r * g, r * pk)`.
+    pub(crate) fn masking_point(
+        pp: &PreparedParameters<C>,
+        shared_key: &PreparedKey<C>,
+        r: &C::ScalarField,
+    ) -> MaskedCard<C> {
+        el_gamal::Ciphertext(
+            pp.generator.mul(r).into_affine(),
+            shared_key.table.mul(r).into_affine(),
+        )
+    }
+
+    /// Draw the randomness `w` of a Chaum-Pedersen proof and compute its commitments
+    /// `(w * g, w * pk)` from the tables.
+    pub(crate) fn proof_commitment<R: Rng>(
+        rng: &mut R,
+        pp: &PreparedParameters<C>,
+        shared_key: &PreparedKey<C>,
+    ) -> ProofCommitment<C> {
+        let w = C::ScalarField::rand(rng);
+        (w, Self::masking_point(pp, shared_key, &w))
+    }
+
+    /// Chaum-Pedersen proof that `x = s * g` and `y = s * pk`, opening `commitment`. A commitment
+    /// must not be used for more than one proof, or `s` can be recovered from the two responses.
+    pub(crate) fn prove_committed(
+        seed: &[u8],
+        pp: &PreparedParameters<C>,
+        shared_key: &PreparedKey<C>,
+        (x, y): (&C::Affine, &C::Affine),
+        s: &C::ScalarField,
+        (w, commitment): &ProofCommitment<C>,
+    ) -> Result<TaggedProof<Proof<C>>, CardProtocolError> {
+        let c = D::chaum_pedersen_challenge::<C>(
+            seed,
+            (&pp.parameters.enc_parameters.generator, &shared_key.key),
+            (x, y),
+            (&commitment.0, &commitment.1),
+        )?;
+
+        Ok(TaggedProof::new(
+            D::ID,
+            proof_from_elements::<C>(&(commitment.0, commitment.1, *w + c * s))?,
+        ))
+    }
+
+    /// Same as `mask`, using the precomputed tables.
+    pub fn mask_prepared<R: Rng>(
+        rng: &mut R,
+        pp: &PreparedParameters<C>,
+        shared_key: &PreparedKey<C>,
+        original_card: &Card<C>,
+        alpha: &C::ScalarField,
+    ) -> Result<
+        (
+            MaskedCard<C>,
+            <Self as BarnettSmartProtocol>::ZKProofMasking,
+        ),
+        CardProtocolError,
+    > {
+        let masking_point = Self::masking_point(pp, shared_key, alpha);
+        let masked_card = el_gamal::Ciphertext(masking_point.0, masking_point.1 + original_card.0);
+        let commitment = Self::proof_commitment(rng, pp, shared_key);
+        let proof = Self::prove_committed(
+            MASKING_RNG_SEED,
+            pp,
+            shared_key,
+            (&masking_point.0, &masking_point.1),
+            alpha,
+            &commitment,
+        )?;
+
+        Ok((masked_card, proof))
+    }
+
+    /// Same as `remask`, using the precomputed tables.
+    pub fn remask_prepared<R: Rng>(
+        rng: &mut R,
+        pp: &PreparedParameters<C>,
+        shared_key: &PreparedKey<C>,
+        original_masked: &MaskedCard<C>,
+        alpha: &C::ScalarField,
+    ) -> Result<
+        (
+            MaskedCard<C>,
+            <Self as BarnettSmartProtocol>::ZKProofRemasking,
+        ),
+        CardProtocolError,
+    > {
+        let masking_point = Self::masking_point(pp, shared_key, alpha);
+        let remasked = *original_masked + masking_point;
+        let commitment = Self::proof_commitment(rng, pp, shared_key);
+        let proof = Self::prove_committed(
+            REMASKING_RNG_SEED,
+            pp,
+            shared_key,
+            (&masking_point.0, &masking_point.1),
+            alpha,
+            &commitment,
+        )?;
+
+        Ok((remasked, proof))
+    }
+
+    /// Same as `shuffle_and_remask`, using the precomputed tables for the remasking. There must be
+    /// one masking factor per card.
+    pub fn shuffle_and_remask_prepared<R: Rng>(
+        rng: &mut R,
+        pp: &PreparedParameters<C>,
+        shared_key: &PreparedKey<C>,
+        deck: &Vec<MaskedCard<C>>,
+        masking_factors: &Vec<C::ScalarField>,
+        permutation: &Permutation,
+    ) -> Result<(Vec<MaskedCard<C>>, ZKProofShuffle<C>), CardProtocolError> {
+        if masking_factors.len() != deck.len() || permutation.size != deck.len() {
+            return Err(CardProtocolError::BatchSizeMismatch);
+        }
+
+        let masked_shuffled = permutation
+            .permute_array(deck)
+            .iter()
+            .zip(masking_factors.iter())
+            .map(|(masked_card, masking_factor)| {
+                *masked_card + Self::masking_point(pp, shared_key, masking_factor)
+            })
+            .collect::<Vec<_>>();
+        let proof = Self::prove_shuffle(
+            rng,
+            &pp.parameters,
+            &shared_key.key,
+            deck,
+            &masked_shuffled,
+            masking_factors,
+            permutation,
+        )?;
+
+        Ok((masked_shuffled, proof))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::discrete_log_cards::test_utils::setup_players;
+    use crate::discrete_log_cards::{self, PreparedKey, PreparedParameters};
+    use crate::error::CardProtocolError;
+    use crate::BarnettSmartProtocol;
+
+    use ark_ff::UniformRand;
+    use proof_essentials::utils::permutation::Permutation;
+    use proof_essentials::utils::rand::sample_vector;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+
+    type Card = discrete_log_cards::Card<Curve>;
+    type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
+
+    #[test]
+    fn test_prepared_matches_unprepared() {
+        let rng = &mut thread_rng();
+        let m = 2;
+        let n = 4;
+
+        let parameters = CardProtocol::setup(rng, m, n).unwrap();
+        let (_, aggregate_key) = setup_players(rng, &parameters, 3);
+
+        let prepared_parameters = PreparedParameters::new(&parameters);
+        let prepared_key = PreparedKey::new(&aggregate_key);
+
+        let card = Card::rand(rng);
+        let alpha = Scalar::rand(rng);
+        let (masked, proof) =
+            CardProtocol::mask_prepared(rng, &prepared_parameters, &prepared_key, &card, &alpha)
+                .unwrap();
+        let (expected, _) =
+            CardProtocol::mask(rng, &parameters, &aggregate_key, &card, &alpha).unwrap();
+        assert_eq!(masked, expected);
+        assert_eq!(
+            CardProtocol::verify_mask(&parameters, &aggregate_key, &card, &masked, &proof),
+            Ok(())
+        );
+
+        let (remasked, proof) = CardProtocol::remask_prepared(
+            rng,
+            &prepared_parameters,
+            &prepared_key,
+            &masked,
+            &alpha,
+        )
+        .unwrap();
+        let (expected, _) =
+            CardProtocol::remask(rng, &parameters, &aggregate_key, &masked, &alpha).unwrap();
+        assert_eq!(remasked, expected);
+        assert_eq!(
+            CardProtocol::verify_remask(&parameters, &aggregate_key, &masked, &remasked, &proof),
+            Ok(())
+        );
+
+        let deck: Vec<MaskedCard> = sample_vector(rng, m * n);
+        let permutation = Permutation::new(rng, m * n);
+        let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
+        let (shuffled, proof) = CardProtocol::shuffle_and_remask_prepared(
+            rng,
+            &prepared_parameters,
+            &prepared_key,
+            &deck,
+            &masking_factors,
+            &permutation,
+        )
+        .unwrap();
+        let (expected, _) = CardProtocol::shuffle_and_remask(
+            rng,
+            &parameters,
+            &aggregate_key,
+            &deck,
+            &masking_factors,
+            &permutation,
+        )
+        .unwrap();
+        assert_eq!(shuffled, expected);
+        assert_eq!(
+            CardProtocol::verify_shuffle(&parameters, &aggregate_key, &deck, &shuffled, &proof),
+            Ok(())
+        );
+        let short_factors = masking_factors[1..].to_vec();
+        assert_eq!(
+            CardProtocol::shuffle_and_remask_prepared(
+                rng,
+                &prepared_parameters,
+                &prepared_key,
+                &deck,
+                &short_factors,
+                &permutation,
+            )
+            .map(|_| ()),
+            Err(CardProtocolError::BatchSizeMismatch)
+        );
+    }
+}
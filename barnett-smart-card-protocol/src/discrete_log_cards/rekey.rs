@@ -0,0 +1,420 @@
+// Moving outstanding ciphertexts to a new aggregate key when the set of players changes.
+//
+// A masked card is an el-Gamal ciphertext (c0, c1) under the sum of the player keys. A leaving
+// player with key share sk strips it from every ciphertext by subtracting its reveal token sk * c0
+// from c1, proving with a Chaum-Pedersen proof that the subtracted point is their partial
+// decryption. A joining player adds sk * c0 to c1 and proves the same relation for the added point.
+// In both cases c0 is untouched and no card is revealed. A joining player also proves ownership
+// of their key, since a key chosen as a function of the other keys could otherwise cancel them
+// out of the aggregate key.
+
+use super::batch::{prove_chaum_pedersen, verify_chaum_pedersen};
+use super::{DLCards, MaskedCard, PublicKey, TaggedProof, TranscriptHash};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::io::{Read, Write};
+use ark_std::rand::Rng;
+use ark_std::{string::String, vec::Vec};
+use proof_essentials::error::CryptoError;
+use proof_essentials::homomorphic_encryption::el_gamal;
+use proof_essentials::zkp::proofs::chaum_pedersen_dl_equality;
+
+const KEY_REMOVAL_RNG_SEED: &[u8] = b"Key Share Removal Proof";
+const KEY_ADDITION_RNG_SEED: &[u8] = b"Key Share Addition Proof";
+
+/// Whether a re-keying removes the key share of a leaving player or adds the one of a joining player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyShareChange {
+    Leave,
+    Join,
+}
+
+impl CanonicalSerialize for KeyShareChange {
+    fn serialize<W: Write>(&self, writer: W) -> Result<(), SerializationError> {
+        (*self == KeyShareChange::Join).serialize(writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        true.serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for KeyShareChange {
+    fn deserialize<R: Read>(reader: R) -> Result<Self, SerializationError> {
+        if bool::deserialize(reader)? {
+            Ok(KeyShareChange::Join)
+        } else {
+            Ok(KeyShareChange::Leave)
+        }
+    }
+}
+
+/// A batch of masked cards moved to a new aggregate key by the owner of `player_key`. The i-th
+/// card and proof correspond to the i-th card of the batch being re-keyed. The proofs record the
+/// transcript hash they were computed with.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Rekeying<C: ProjectiveCurve> {
+    pub change: KeyShareChange,
+    pub player_key: PublicKey<C>,
+    /// Public information of a joining player and their proof of ownership of `player_key`.
+    /// Unset when a player leaves.
+    pub key_ownership: Option<(
+        Vec<u8>,
+        <DLCards<C> as BarnettSmartProtocol>::ZKProofKeyOwnership,
+    )>,
+    pub masked_cards: Vec<MaskedCard<C>>,
+    pub proofs: Vec<TaggedProof<chaum_pedersen_dl_equality::proof::Proof<C>>>,
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> DLCards<C, D> {
+    /// Strip the key share of a leaving player from `masked_cards`.
+    pub fn rekey_leave<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        sk: &<Self as BarnettSmartProtocol>::PlayerSecretKey,
+        pk: &PublicKey<C>,
+        masked_cards: &[MaskedCard<C>],
+    ) -> Result<Rekeying<C>, CardProtocolError> {
+        Self::rekey(rng, pp, sk, pk, masked_cards, KeyShareChange::Leave)
+    }
+
+    /// Add the key share of a joining player to `masked_cards`, together with a proof of
+    /// ownership of `pk` for the public information `player_public_info`.
+    pub fn rekey_join<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        sk: &<Self as BarnettSmartProtocol>::PlayerSecretKey,
+        pk: &PublicKey<C>,
+        player_public_info: &[u8],
+        masked_cards: &[MaskedCard<C>],
+    ) -> Result<Rekeying<C>, CardProtocolError> {
+        let key_proof = Self::prove_key_ownership(rng, pp, pk, sk, player_public_info)?;
+        let mut rekeying = Self::rekey(rng, pp, sk, pk, masked_cards, KeyShareChange::Join)?;
+        rekeying.key_ownership = Some((player_public_info.to_vec(), key_proof));
+
+        Ok(rekeying)
+    }
+
+    fn rekey<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        sk: &<Self as BarnettSmartProtocol>::PlayerSecretKey,
+        pk: &PublicKey<C>,
+        masked_cards: &[MaskedCard<C>],
+        change: KeyShareChange,
+    ) -> Result<Rekeying<C>, CardProtocolError> {
+        let mut rekeyed = Vec::with_capacity(masked_cards.len());
+        let mut proofs = Vec::with_capacity(masked_cards.len());
+
+        for masked_card in masked_cards {
+            let share = masked_card.0.mul(sk.expose().into_repr()).into_affine();
+            let c1 = match change {
+                KeyShareChange::Leave => masked_card.1 + (-share),
+                KeyShareChange::Join => masked_card.1 + share,
+            };
+
+            let proof = prove_chaum_pedersen::<C, D, R>(
+                rng,
+                rekey_seed(change),
+                (&masked_card.0, &pp.enc_parameters.generator),
+                (&share, pk),
+                sk.expose(),
+            )?;
+
+            rekeyed.push(el_gamal::Ciphertext(masked_card.0, c1));
+            proofs.push(proof);
+        }
+
+        Ok(Rekeying {
+            change,
+            player_key: *pk,
+            key_ownership: None,
+            masked_cards: rekeyed,
+            proofs,
+        })
+    }
+
+    /// Check that `rekeying` moves each of `original` to the new aggregate key without changing
+    /// the underlying card, and that a joining player owns the key they join with.
+    pub fn verify_rekeying(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        original: &[MaskedCard<C>],
+        rekeying: &Rekeying<C>,
+    ) -> Result<(), CryptoError> {
+        match (rekeying.change, &rekeying.key_ownership) {
+            (KeyShareChange::Join, Some((info, key_proof))) => {
+                Self::verify_key_ownership(pp, &rekeying.player_key, info, key_proof)?
+            }
+            (KeyShareChange::Leave, None) => {}
+            _ => {
+                return Err(CryptoError::ProofVerificationError(String::from(
+                    "Rekeying",
+                )))
+            }
+        }
+
+        if original.len() != rekeying.masked_cards.len() || original.len() != rekeying.proofs.len()
+        {
+            return Err(CryptoError::ProofVerificationError(String::from(
+                "Rekeying",
+            )));
+        }
+
+        for ((masked_card, rekeyed), proof) in original
+            .iter()
+            .zip(rekeying.masked_cards.iter())
+            .zip(rekeying.proofs.iter())
+        {
+            if masked_card.0 != rekeyed.0 {
+                return Err(CryptoError::ProofVerificationError(String::from(
+                    "Rekeying",
+                )));
+            }
+
+            let share = match rekeying.change {
+                KeyShareChange::Leave => masked_card.1 + (-rekeyed.1),
+                KeyShareChange::Join => rekeyed.1 + (-masked_card.1),
+            };
+
+            verify_chaum_pedersen::<C, D>(
+                rekey_seed(rekeying.change),
+                (&masked_card.0, &pp.enc_parameters.generator),
+                (&share, &rekeying.player_key),
+                proof,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Aggregate key the cards are masked under once `rekeying` has been applied.
+    pub fn rekey_aggregate_key(
+        shared_key: &<Self as BarnettSmartProtocol>::AggregatePublicKey,
+        rekeying: &Rekeying<C>,
+    ) -> <Self as BarnettSmartProtocol>::AggregatePublicKey {
+        match rekeying.change {
+            KeyShareChange::Leave => *shared_key + (-rekeying.player_key),
+            KeyShareChange::Join => *shared_key + rekeying.player_key,
+        }
+    }
+}
+
+/// Fiat-Shamir seed of the proofs of a rekeying.
+fn rekey_seed(change: KeyShareChange) -> &'static [u8] {
+    match change {
+        KeyShareChange::Leave => KEY_REMOVAL_RNG_SEED,
+        KeyShareChange::Join => KEY_ADDITION_RNG_SEED,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::discrete_log_cards::test_utils::setup_players;
+    use crate::discrete_log_cards::{self, DealLog, ShuffleChain, ShuffleLink};
+    use crate::error::CardProtocolError;
+    use crate::BarnettSmartProtocol;
+
+    use ark_ff::UniformRand;
+    use ark_std::rand::Rng;
+    use proof_essentials::error::CryptoError;
+    use proof_essentials::utils::permutation::Permutation;
+    use proof_essentials::utils::rand::sample_vector;
+    use proof_essentials::zkp::proofs::chaum_pedersen_dl_equality;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+    type CardParameters = discrete_log_cards::Parameters<Curve>;
+    type PublicKey = discrete_log_cards::PublicKey<Curve>;
+    type SecretKey = discrete_log_cards::PlayerSecretKey<Curve>;
+
+    type Card = discrete_log_cards::Card<Curve>;
+    type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
+    type RevealToken = discrete_log_cards::RevealToken<Curve>;
+
+    type RevealProof =
+        discrete_log_cards::TaggedProof<chaum_pedersen_dl_equality::proof::Proof<Curve>>;
+
+    fn open(
+        rng: &mut impl Rng,
+        parameters: &CardParameters,
+        players: &[(PublicKey, SecretKey)],
+        masked_card: &MaskedCard,
+    ) -> Result<Card, CardProtocolError> {
+        let decryption_key: Vec<(RevealToken, RevealProof, PublicKey)> = players
+            .iter()
+            .map(|(pk, sk)| {
+                let (token, proof) =
+                    CardProtocol::compute_reveal_token(rng, parameters, sk, pk, masked_card)
+                        .unwrap();
+                (token, proof, *pk)
+            })
+            .collect();
+
+        CardProtocol::unmask(parameters, &decryption_key, masked_card, false)
+    }
+
+    #[test]
+    fn test_leave_and_join() {
+        let rng = &mut thread_rng();
+        let m = 2;
+        let n = 4;
+
+        let parameters = CardProtocol::setup(rng, m, n).unwrap();
+        let (mut players, aggregate_key) = setup_players(rng, &parameters, 3);
+
+        let deck = (0..parameters.num_cards())
+            .map(|_| {
+                let card = Card::rand(rng);
+                let alpha = Scalar::rand(rng);
+                CardProtocol::mask(rng, &parameters, &aggregate_key, &card, &alpha)
+                    .unwrap()
+                    .0
+            })
+            .collect::<Vec<_>>();
+        let mut chain = ShuffleChain::<Curve>::new(deck, vec![0, 1, 2]).unwrap();
+        for player in 0..3 {
+            let permutation = Permutation::new(rng, parameters.num_cards());
+            let masking_factors: Vec<Scalar> = sample_vector(rng, parameters.num_cards());
+            let (shuffled_deck, proof) = CardProtocol::shuffle_and_remask(
+                rng,
+                &parameters,
+                &aggregate_key,
+                &chain.current_deck().to_vec(),
+                &masking_factors,
+                &permutation,
+            )
+            .unwrap();
+            let link = ShuffleLink {
+                player,
+                shuffled_deck,
+                proof,
+            };
+            chain.push(&parameters, &aggregate_key, link).unwrap();
+        }
+        let deck = chain.certify(&parameters, &aggregate_key).unwrap();
+        let cards = deck
+            .cards()
+            .iter()
+            .map(|masked_card| open(rng, &parameters, &players, masked_card).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut log = DealLog::<Curve>::new(deck);
+        for player in 0..3 {
+            log.deal(player).unwrap();
+        }
+        let alpha = Scalar::rand(rng);
+        let discard = CardProtocol::discard(rng, &parameters, &log, 1, 1, &alpha).unwrap();
+        log.record_discard(&parameters, discard).unwrap();
+
+        // Player 2 leaves
+        let (leaving_pk, leaving_sk) = players.pop().unwrap();
+        let leave = CardProtocol::rekey_leave(
+            rng,
+            &parameters,
+            &leaving_sk,
+            &leaving_pk,
+            &log.masked_cards(),
+        )
+        .unwrap();
+
+        // A re-keying of a different batch is rejected
+        assert_eq!(
+            CardProtocol::verify_rekeying(&parameters, &log.masked_cards()[1..], &leave),
+            Err(CryptoError::ProofVerificationError(String::from(
+                "Rekeying"
+            )))
+        );
+
+        // The discard is proven again under the new key by the player who discarded it
+        let reproof =
+            CardProtocol::reprove_face_down(rng, &parameters, &log, &leave, 1, &alpha).unwrap();
+        assert_eq!(
+            log.apply_rekeying(&parameters, &leave, &[]),
+            Err(CardProtocolError::BatchSizeMismatch)
+        );
+        let stale = log.discards()[0].proof;
+        assert!(log.apply_rekeying(&parameters, &leave, &[stale]).is_err());
+        log.apply_rekeying(&parameters, &leave, &[reproof]).unwrap();
+        let aggregate_key = CardProtocol::rekey_aggregate_key(&aggregate_key, &leave);
+        assert_eq!(aggregate_key, players[0].0 + players[1].0);
+        assert_eq!(log.shared_key(), &aggregate_key);
+
+        assert_eq!(
+            open(rng, &parameters, &players, log.card(0).unwrap()),
+            Ok(cards[0])
+        );
+
+        // Player 3 joins
+        let (joining_pk, joining_sk) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+        let join = CardProtocol::rekey_join(
+            rng,
+            &parameters,
+            &joining_sk,
+            &joining_pk,
+            b"Dave",
+            &log.masked_cards(),
+        )
+        .unwrap();
+
+        let reproof =
+            CardProtocol::reprove_face_down(rng, &parameters, &log, &join, 1, &alpha).unwrap();
+
+        // The proofs are bound to the key of the joining player
+        let mut forged = join.clone();
+        forged.player_key = leaving_pk;
+        assert!(log
+            .apply_rekeying(&parameters, &forged, &[reproof])
+            .is_err());
+
+        // Joining without a proof of key ownership, or with one for other information, is rejected
+        let mut unproven = join.clone();
+        unproven.key_ownership = None;
+        assert!(log
+            .apply_rekeying(&parameters, &unproven, &[reproof])
+            .is_err());
+        let mut relabelled = join.clone();
+        relabelled.key_ownership.as_mut().unwrap().0 = b"Eve".to_vec();
+        assert!(log
+            .apply_rekeying(&parameters, &relabelled, &[reproof])
+            .is_err());
+
+        log.apply_rekeying(&parameters, &join, &[reproof]).unwrap();
+        let aggregate_key = CardProtocol::rekey_aggregate_key(&aggregate_key, &join);
+        assert_eq!(aggregate_key, players[0].0 + players[1].0 + joining_pk);
+        assert_eq!(log.shared_key(), &aggregate_key);
+        players.push((joining_pk, joining_sk));
+
+        // Undealt cards and the discard pile can be opened by the new set of players
+        assert_eq!(
+            open(rng, &parameters, &players, log.card(5).unwrap()),
+            Ok(cards[5])
+        );
+        let decryption_key = players
+            .iter()
+            .map(|(pk, sk)| {
+                let (token, proof) = CardProtocol::compute_reveal_token(
+                    rng,
+                    &parameters,
+                    sk,
+                    pk,
+                    &log.discards()[0].masked_card,
+                )
+                .unwrap();
+                (token, proof, *pk)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            CardProtocol::open_discard(&parameters, &log, 0, &decryption_key),
+            Ok(cards[1])
+        );
+    }
+}
@@ -0,0 +1,238 @@
+// Offline/online split for remasking.
+//
+// A remask adds an encryption of zero `(alpha * g, alpha * pk)` to a card and proves it with a
+// Chaum-Pedersen proof whose commitments are `(w * g, w * pk)` for a random `w`. None of these
+// depend on the card, so a player can compute them while the table is idle and keep them in a
+// `RemaskPool`. A remask then only adds a precomputed point, hashes the transcript and computes
+// one response. The pool is bound to the parameters and the aggregate key it was filled for, so
+// its entries cannot be spent under other ones.
+//
+// Each entry must be spent at most once: two remasking proofs with the same `w` reveal `alpha`,
+// which un-remasks the card. Entries are therefore neither `Clone` nor `Copy`, the pool itself is
+// not `Clone`, and spending an entry moves it out of the pool.
+//
+// A shuffle takes its masking factors and encryptions of zero from the pool as well, but not the
+// randomness of its proof: the shuffle argument is computed by proof-essentials, which draws its
+// commitment randomness internally, so the proof of a shuffle is still computed online in full.
+
+use super::{DLCards, MaskedCard, PreparedKey, PreparedParameters, ProofCommitment};
+use super::{TranscriptHash, ZKProofShuffle, REMASKING_RNG_SEED};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::ProjectiveCurve;
+use ark_ff::UniformRand;
+use ark_std::rand::Rng;
+use ark_std::vec::Vec;
+use proof_essentials::utils::permutation::Permutation;
+use zeroize::Zeroize;
+
+/// A masking factor, its encryption of zero and a commitment for the remasking proof. The masking
+/// factor and the proof randomness are wiped when the entry is dropped.
+struct PoolEntry<C: ProjectiveCurve> {
+    alpha: C::ScalarField,
+    masking_point: MaskedCard<C>,
+    commitment: ProofCommitment<C>,
+}
+
+impl<C: ProjectiveCurve> Drop for PoolEntry<C> {
+    fn drop(&mut self) {
+        self.alpha.zeroize();
+        self.commitment.0.zeroize();
+    }
+}
+
+/// Precomputed remaskings under fixed parameters and aggregate key.
+pub struct RemaskPool<C: ProjectiveCurve> {
+    parameters: PreparedParameters<C>,
+    shared_key: PreparedKey<C>,
+    entries: Vec<PoolEntry<C>>,
+}
+
+impl<C: ProjectiveCurve> RemaskPool<C> {
+    /// An empty pool for remasking under `parameters` and `shared_key`.
+    pub fn new(parameters: PreparedParameters<C>, shared_key: PreparedKey<C>) -> Self {
+        Self {
+            parameters,
+            shared_key,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn parameters(&self) -> &PreparedParameters<C> {
+        &self.parameters
+    }
+
+    pub fn shared_key(&self) -> &PreparedKey<C> {
+        &self.shared_key
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Precompute `count` more entries. A remask consumes one entry and a shuffle one entry per
+    /// card; a shuffle discards the proof commitments of its entries.
+    pub fn fill<R: Rng>(&mut self, rng: &mut R, count: usize) {
+        self.entries.reserve(count);
+        for _ in 0..count {
+            let alpha = C::ScalarField::rand(rng);
+            let masking_point =
+                DLCards::<C>::masking_point(&self.parameters, &self.shared_key, &alpha);
+            let commitment =
+                DLCards::<C>::proof_commitment(rng, &self.parameters, &self.shared_key);
+            self.entries.push(PoolEntry {
+                alpha,
+                masking_point,
+                commitment,
+            });
+        }
+    }
+
+    /// Remove `count` entries, or none if the pool holds fewer.
+    fn take(&mut self, count: usize) -> Result<Vec<PoolEntry<C>>, CardProtocolError> {
+        if count > self.entries.len() {
+            return Err(CardProtocolError::RemaskPoolExhausted(
+                count,
+                self.entries.len(),
+            ));
+        }
+
+        Ok(self.entries.split_off(self.entries.len() - count))
+    }
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> DLCards<C, D> {
+    /// Same as `remask`, with the masking factor, encryption of zero and proof commitment taken
+    /// from `pool`.
+    pub fn remask_from_pool(
+        pool: &mut RemaskPool<C>,
+        original_masked: &MaskedCard<C>,
+    ) -> Result<
+        (
+            MaskedCard<C>,
+            <Self as BarnettSmartProtocol>::ZKProofRemasking,
+        ),
+        CardProtocolError,
+    > {
+        let entries = pool.take(1)?;
+        let entry = &entries[0];
+        let remasked = *original_masked + entry.masking_point;
+        let proof = Self::prove_committed(
+            REMASKING_RNG_SEED,
+            &pool.parameters,
+            &pool.shared_key,
+            (&entry.masking_point.0, &entry.masking_point.1),
+            &entry.alpha,
+            &entry.commitment,
+        )?;
+
+        Ok((remasked, proof))
+    }
+
+    /// Same as `shuffle_and_remask`, with the masking factors and encryptions of zero taken from
+    /// `pool`.
+    pub fn shuffle_and_remask_from_pool<R: Rng>(
+        rng: &mut R,
+        pool: &mut RemaskPool<C>,
+        deck: &Vec<MaskedCard<C>>,
+        permutation: &Permutation,
+    ) -> Result<(Vec<MaskedCard<C>>, ZKProofShuffle<C>), CardProtocolError> {
+        if permutation.size != deck.len() {
+            return Err(CardProtocolError::BatchSizeMismatch);
+        }
+
+        let entries = pool.take(deck.len())?;
+        let mut masking_factors = entries.iter().map(|entry| entry.alpha).collect::<Vec<_>>();
+
+        let masked_shuffled = permutation
+            .permute_array(deck)
+            .iter()
+            .zip(entries.iter())
+            .map(|(masked_card, entry)| *masked_card + entry.masking_point)
+            .collect::<Vec<_>>();
+        let proof = Self::prove_shuffle(
+            rng,
+            pool.parameters.parameters(),
+            pool.shared_key.key(),
+            deck,
+            &masked_shuffled,
+            &masking_factors,
+            permutation,
+        );
+        masking_factors.zeroize();
+
+        Ok((masked_shuffled, proof?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::discrete_log_cards::test_utils::setup_players;
+    use crate::discrete_log_cards::{self, PreparedKey, PreparedParameters, RemaskPool};
+    use crate::error::CardProtocolError;
+    use crate::BarnettSmartProtocol;
+
+    use proof_essentials::utils::permutation::Permutation;
+    use proof_essentials::utils::rand::sample_vector;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+
+    type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
+
+    #[test]
+    fn test_remask_pool() {
+        let rng = &mut thread_rng();
+        let m = 2;
+        let n = 4;
+
+        let parameters = CardProtocol::setup(rng, m, n).unwrap();
+        let (_, aggregate_key) = setup_players(rng, &parameters, 3);
+
+        // Offline
+        let mut pool = RemaskPool::new(
+            PreparedParameters::new(&parameters),
+            PreparedKey::new(&aggregate_key),
+        );
+        pool.fill(rng, m * n + 1);
+
+        // Online
+        let deck: Vec<MaskedCard> = sample_vector(rng, m * n);
+        let permutation = Permutation::new(rng, m * n);
+        let (shuffled, proof) =
+            CardProtocol::shuffle_and_remask_from_pool(rng, &mut pool, &deck, &permutation)
+                .unwrap();
+        assert_eq!(
+            CardProtocol::verify_shuffle(&parameters, &aggregate_key, &deck, &shuffled, &proof),
+            Ok(())
+        );
+        assert_eq!(pool.len(), 1);
+
+        let (remasked, proof) = CardProtocol::remask_from_pool(&mut pool, &shuffled[0]).unwrap();
+        assert_eq!(
+            CardProtocol::verify_remask(
+                &parameters,
+                &aggregate_key,
+                &shuffled[0],
+                &remasked,
+                &proof
+            ),
+            Ok(())
+        );
+        assert!(pool.is_empty());
+
+        assert_eq!(
+            CardProtocol::remask_from_pool(&mut pool, &remasked).err(),
+            Some(CardProtocolError::RemaskPoolExhausted(1, 0))
+        );
+    }
+}
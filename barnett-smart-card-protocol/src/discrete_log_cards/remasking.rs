@@ -44,7 +44,8 @@ mod test {
 
     type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
 
-    type RemaskingProof = chaum_pedersen_dl_equality::proof::Proof<Curve>;
+    type RemaskingProof =
+        discrete_log_cards::TaggedProof<chaum_pedersen_dl_equality::proof::Proof<Curve>>;
 
     fn setup_players<R: Rng>(
         rng: &mut R,
@@ -38,7 +38,8 @@ mod test {
     type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
     type RevealToken = discrete_log_cards::RevealToken<Curve>;
 
-    type RevealProof = chaum_pedersen_dl_equality::proof::Proof<Curve>;
+    type RevealProof =
+        discrete_log_cards::TaggedProof<chaum_pedersen_dl_equality::proof::Proof<Curve>>;
 
     #[test]
     fn test_verify_reveal() {
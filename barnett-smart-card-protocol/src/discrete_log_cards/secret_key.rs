@@ -0,0 +1,34 @@
+// Player secret keys. The password-protected export format lives in `keystore`.
+
+use ark_ec::ProjectiveCurve;
+use ark_std::fmt;
+use zeroize::Zeroize;
+
+/// Secret key of a player. The underlying scalar is wiped when the key is dropped and never shows
+/// up in `Debug` output. The key is deliberately not `Copy`: clone it explicitly when needed. It
+/// has no canonical encoding either, so that `encrypt` is the only way to export it.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PlayerSecretKey<C: ProjectiveCurve>(C::ScalarField);
+
+impl<C: ProjectiveCurve> PlayerSecretKey<C> {
+    pub fn new(scalar: C::ScalarField) -> Self {
+        Self(scalar)
+    }
+
+    /// Access the secret scalar. Avoid copying the returned value into long-lived storage.
+    pub fn expose(&self) -> &C::ScalarField {
+        &self.0
+    }
+}
+
+impl<C: ProjectiveCurve> Drop for PlayerSecretKey<C> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<C: ProjectiveCurve> fmt::Debug for PlayerSecretKey<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PlayerSecretKey(<redacted>)")
+    }
+}
@@ -0,0 +1,361 @@
+// The sequence of shuffles every player performs on the masked deck before the deal.
+//
+// Each link is checked against the output of the previous one, so the final deck is a correct
+// shuffle of the initial deck as long as one player in the chain is honest. Only a complete,
+// verified chain yields a `CertifiedDeck`, which is what `DealLog` deals from.
+//
+// The chain remembers how many of its links were verified, and with which parameters and
+// aggregate key, so that each shuffle proof is only checked once. A deserialized chain starts with
+// nothing verified.
+
+use super::{DLCards, MaskedCard, Parameters, PublicKey, TranscriptHash, ZKProofShuffle};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::ProjectiveCurve;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::io::{Read, Write};
+use ark_std::marker::PhantomData;
+use ark_std::vec::Vec;
+use ark_std::Zero;
+use blake2::Blake2s;
+
+/// The shuffle of `player`: their output deck and the proof that it shuffles the previous deck.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct ShuffleLink<C: ProjectiveCurve> {
+    pub player: usize,
+    pub shuffled_deck: Vec<MaskedCard<C>>,
+    pub proof: ZKProofShuffle<C>,
+}
+
+/// The initial deck and the shuffles applied to it so far, in the agreed order of players. The
+/// shuffle proofs are checked with transcript hash `D`.
+pub struct ShuffleChain<C: ProjectiveCurve, D: TranscriptHash = Blake2s> {
+    initial_deck: Vec<MaskedCard<C>>,
+    order: Vec<usize>,
+    links: Vec<ShuffleLink<C>>,
+    /// Number of leading links verified with the parameters and key in `verified_with`.
+    verified: usize,
+    verified_with: Option<(Parameters<C>, PublicKey<C>)>,
+    _hash: PhantomData<D>,
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> CanonicalSerialize for ShuffleChain<C, D> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.initial_deck.serialize(&mut writer)?;
+        self.order.serialize(&mut writer)?;
+        self.links.serialize(&mut writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.initial_deck.serialized_size()
+            + self.order.serialized_size()
+            + self.links.serialized_size()
+    }
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> CanonicalDeserialize for ShuffleChain<C, D> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        Ok(Self {
+            initial_deck: Vec::deserialize(&mut reader)?,
+            order: Vec::deserialize(&mut reader)?,
+            links: Vec::deserialize(&mut reader)?,
+            verified: 0,
+            verified_with: None,
+            _hash: PhantomData,
+        })
+    }
+}
+
+/// Final deck of a complete and verified shuffle chain, and the aggregate key it was shuffled
+/// under.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertifiedDeck<C: ProjectiveCurve> {
+    deck: Vec<MaskedCard<C>>,
+    aggregate_key: PublicKey<C>,
+    num_of_players: usize,
+}
+
+impl<C: ProjectiveCurve> CertifiedDeck<C> {
+    pub fn cards(&self) -> &[MaskedCard<C>] {
+        &self.deck
+    }
+
+    /// Aggregate key the shuffle proofs were verified with.
+    pub fn aggregate_key(&self) -> &PublicKey<C> {
+        &self.aggregate_key
+    }
+
+    /// Number of players who shuffled the deck.
+    pub fn num_of_players(&self) -> usize {
+        self.num_of_players
+    }
+
+    /// Check that the deck was shuffled under the sum of `keys`, i.e. by the players holding them.
+    pub fn check_keys(&self, keys: &[PublicKey<C>]) -> Result<(), CardProtocolError> {
+        let aggregate_key = keys
+            .iter()
+            .fold(PublicKey::<C>::zero(), |acc, pk| acc + *pk);
+        if aggregate_key != self.aggregate_key {
+            return Err(CardProtocolError::AggregateKeyMismatch);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn into_cards(self) -> Vec<MaskedCard<C>> {
+        self.deck
+    }
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> ShuffleChain<C, D> {
+    /// Start a chain on `initial_deck`. `order` lists every registered player (0 to n - 1)
+    /// exactly once, in the order they shuffle.
+    pub fn new(
+        initial_deck: Vec<MaskedCard<C>>,
+        order: Vec<usize>,
+    ) -> Result<Self, CardProtocolError> {
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        if sorted.iter().enumerate().any(|(i, player)| i != *player) {
+            return Err(CardProtocolError::InvalidShuffleOrder);
+        }
+
+        Ok(Self {
+            initial_deck,
+            order,
+            links: Vec::new(),
+            verified: 0,
+            verified_with: None,
+            _hash: PhantomData,
+        })
+    }
+
+    pub fn initial_deck(&self) -> &[MaskedCard<C>] {
+        &self.initial_deck
+    }
+
+    pub fn links(&self) -> &[ShuffleLink<C>] {
+        &self.links
+    }
+
+    /// Deck the next player shuffles.
+    pub fn current_deck(&self) -> &[MaskedCard<C>] {
+        self.links
+            .last()
+            .map_or(&self.initial_deck, |link| &link.shuffled_deck)
+    }
+
+    /// Player whose shuffle is expected next, or `None` once everyone has shuffled.
+    pub fn next_shuffler(&self) -> Option<usize> {
+        self.order.get(self.links.len()).copied()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.next_shuffler().is_none()
+    }
+
+    /// Verify a shuffle against the current deck and append it. The shuffle must come from the
+    /// next player in the agreed order.
+    pub fn push(
+        &mut self,
+        pp: &<DLCards<C, D> as BarnettSmartProtocol>::Parameters,
+        shared_key: &<DLCards<C, D> as BarnettSmartProtocol>::AggregatePublicKey,
+        link: ShuffleLink<C>,
+    ) -> Result<(), CardProtocolError> {
+        self.verify(pp, shared_key)?;
+        self.check_link(pp, shared_key, self.links.len(), self.current_deck(), &link)?;
+        self.links.push(link);
+        self.verified = self.links.len();
+
+        Ok(())
+    }
+
+    /// Verify the links that have not been verified with `pp` and `shared_key` yet, e.g. all of
+    /// them after receiving the chain in one piece.
+    pub fn verify(
+        &mut self,
+        pp: &<DLCards<C, D> as BarnettSmartProtocol>::Parameters,
+        shared_key: &<DLCards<C, D> as BarnettSmartProtocol>::AggregatePublicKey,
+    ) -> Result<(), CardProtocolError> {
+        match &self.verified_with {
+            Some((parameters, key)) if parameters == pp && key == shared_key => {}
+            _ => {
+                self.verified = 0;
+                self.verified_with = Some((pp.clone(), *shared_key));
+            }
+        }
+
+        for index in self.verified..self.links.len() {
+            let deck = match index {
+                0 => &self.initial_deck,
+                _ => &self.links[index - 1].shuffled_deck,
+            };
+            self.check_link(pp, shared_key, index, deck, &self.links[index])?;
+            self.verified = index + 1;
+        }
+
+        Ok(())
+    }
+
+    /// Verify the chain and check that every player shuffled, yielding the deck to deal from.
+    pub fn certify(
+        mut self,
+        pp: &<DLCards<C, D> as BarnettSmartProtocol>::Parameters,
+        shared_key: &<DLCards<C, D> as BarnettSmartProtocol>::AggregatePublicKey,
+    ) -> Result<CertifiedDeck<C>, CardProtocolError> {
+        if let Some(player) = self.next_shuffler() {
+            return Err(CardProtocolError::MissingShuffle(player));
+        }
+        self.verify(pp, shared_key)?;
+
+        Ok(CertifiedDeck {
+            deck: self.current_deck().to_vec(),
+            aggregate_key: *shared_key,
+            num_of_players: self.order.len(),
+        })
+    }
+
+    fn check_link(
+        &self,
+        pp: &<DLCards<C, D> as BarnettSmartProtocol>::Parameters,
+        shared_key: &<DLCards<C, D> as BarnettSmartProtocol>::AggregatePublicKey,
+        index: usize,
+        deck: &[MaskedCard<C>],
+        link: &ShuffleLink<C>,
+    ) -> Result<(), CardProtocolError> {
+        match self.order.get(index) {
+            Some(expected) if *expected == link.player => {}
+            Some(expected) => {
+                return Err(CardProtocolError::ShuffleOutOfOrder(*expected, link.player))
+            }
+            None => return Err(CardProtocolError::UnknownPlayer(link.player)),
+        }
+
+        DLCards::<C, D>::verify_shuffle(
+            pp,
+            shared_key,
+            &deck.to_vec(),
+            &link.shuffled_deck,
+            &link.proof,
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::discrete_log_cards::test_utils::setup_players;
+    use crate::discrete_log_cards::{self, ShuffleChain, ShuffleLink};
+    use crate::error::CardProtocolError;
+    use crate::BarnettSmartProtocol;
+
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::rand::Rng;
+    use proof_essentials::utils::permutation::Permutation;
+    use proof_essentials::utils::rand::sample_vector;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+    type CardParameters = discrete_log_cards::Parameters<Curve>;
+    type PublicKey = discrete_log_cards::PublicKey<Curve>;
+
+    type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
+
+    fn shuffle<R: Rng>(
+        rng: &mut R,
+        parameters: &CardParameters,
+        aggregate_key: &PublicKey,
+        player: usize,
+        deck: &[MaskedCard],
+    ) -> ShuffleLink<Curve> {
+        let permutation = Permutation::new(rng, deck.len());
+        let masking_factors: Vec<Scalar> = sample_vector(rng, deck.len());
+        let (shuffled_deck, proof) = CardProtocol::shuffle_and_remask(
+            rng,
+            parameters,
+            aggregate_key,
+            &deck.to_vec(),
+            &masking_factors,
+            &permutation,
+        )
+        .unwrap();
+
+        ShuffleLink {
+            player,
+            shuffled_deck,
+            proof,
+        }
+    }
+
+    #[test]
+    fn test_shuffle_chain() {
+        let rng = &mut thread_rng();
+        let m = 2;
+        let n = 4;
+
+        let parameters = CardProtocol::setup(rng, m, n).unwrap();
+        let (players, aggregate_key) = setup_players(rng, &parameters, 3);
+        let initial_deck: Vec<MaskedCard> = sample_vector(rng, m * n);
+
+        assert!(ShuffleChain::<Curve>::new(initial_deck.clone(), vec![0, 2]).is_err());
+
+        let mut chain = ShuffleChain::<Curve>::new(initial_deck, vec![2, 0, 1]).unwrap();
+
+        // Player 0 cannot shuffle before player 2
+        let link = shuffle(rng, &parameters, &aggregate_key, 0, chain.current_deck());
+        assert_eq!(
+            chain.push(&parameters, &aggregate_key, link).err(),
+            Some(CardProtocolError::ShuffleOutOfOrder(2, 0))
+        );
+
+        for player in [2, 0] {
+            let link = shuffle(
+                rng,
+                &parameters,
+                &aggregate_key,
+                player,
+                chain.current_deck(),
+            );
+            chain.push(&parameters, &aggregate_key, link).unwrap();
+        }
+
+        // A shuffle of a stale deck is rejected
+        let stale = shuffle(rng, &parameters, &aggregate_key, 1, chain.initial_deck());
+        assert!(chain.push(&parameters, &aggregate_key, stale).is_err());
+        assert_eq!(chain.next_shuffler(), Some(1));
+
+        let link = shuffle(rng, &parameters, &aggregate_key, 1, chain.current_deck());
+        chain.push(&parameters, &aggregate_key, link).unwrap();
+
+        // A received chain is verified again, under the key it is certified with
+        let mut bytes = Vec::new();
+        chain.serialize(&mut bytes).unwrap();
+        let (_, other_key) = setup_players(rng, &parameters, 3);
+        let received = ShuffleChain::<Curve>::deserialize(&bytes[..]).unwrap();
+        assert!(received.certify(&parameters, &other_key).is_err());
+        let received = ShuffleChain::<Curve>::deserialize(&bytes[..]).unwrap();
+        assert!(received.certify(&parameters, &aggregate_key).is_ok());
+
+        let final_deck = chain.current_deck().to_vec();
+        assert!(chain.verify(&parameters, &other_key).is_err());
+        let certified = chain.certify(&parameters, &aggregate_key).unwrap();
+        assert_eq!(certified.cards(), final_deck.as_slice());
+        assert_eq!(certified.aggregate_key(), &aggregate_key);
+        assert_eq!(certified.num_of_players(), 3);
+
+        let mut keys: Vec<PublicKey> = players.iter().map(|(pk, _)| *pk).collect();
+        assert!(certified.check_keys(&keys).is_ok());
+        keys.pop();
+        assert_eq!(
+            certified.check_keys(&keys),
+            Err(CardProtocolError::AggregateKeyMismatch)
+        );
+    }
+}
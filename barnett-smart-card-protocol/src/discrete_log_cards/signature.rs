@@ -0,0 +1,119 @@
+// Schnorr signatures under the player keys, so that protocol messages can be attributed to their
+// sender by anyone, not only by the player who received them.
+
+use super::{DLCards, PublicKey};
+use crate::BarnettSmartProtocol;
+
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{to_bytes, PrimeField, UniformRand};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::io::{Read, Write};
+use ark_std::rand::Rng;
+use ark_std::string::String;
+use blake2::{Blake2s, Digest};
+use proof_essentials::error::CryptoError;
+
+const SIGNATURE_TAG: &[u8] = b"Player Signature";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Signature<C: ProjectiveCurve> {
+    pub commitment: C::Affine,
+    pub response: C::ScalarField,
+}
+
+impl<C: ProjectiveCurve> DLCards<C> {
+    /// Sign `message` with the secret key of a player.
+    pub fn sign<R: Rng>(
+        rng: &mut R,
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        sk: &<Self as BarnettSmartProtocol>::PlayerSecretKey,
+        pk: &PublicKey<C>,
+        message: &[u8],
+    ) -> Result<Signature<C>, CryptoError> {
+        let nonce = C::ScalarField::rand(rng);
+        let commitment = pp
+            .enc_parameters
+            .generator
+            .mul(nonce.into_repr())
+            .into_affine();
+        let challenge = signature_challenge::<C>(&commitment, pk, message)?;
+
+        Ok(Signature {
+            commitment,
+            response: nonce + challenge * sk.expose(),
+        })
+    }
+
+    pub fn verify_signature(
+        pp: &<Self as BarnettSmartProtocol>::Parameters,
+        pk: &PublicKey<C>,
+        message: &[u8],
+        signature: &Signature<C>,
+    ) -> Result<(), CryptoError> {
+        let challenge = signature_challenge::<C>(&signature.commitment, pk, message)?;
+
+        let lhs = pp
+            .enc_parameters
+            .generator
+            .mul(signature.response.into_repr());
+        let rhs = signature.commitment.into_projective() + pk.mul(challenge.into_repr());
+        if lhs != rhs {
+            return Err(CryptoError::ProofVerificationError(String::from(
+                "Signature",
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+fn signature_challenge<C: ProjectiveCurve>(
+    commitment: &C::Affine,
+    pk: &PublicKey<C>,
+    message: &[u8],
+) -> Result<C::ScalarField, CryptoError> {
+    let digest = Blake2s::digest(&to_bytes![SIGNATURE_TAG, commitment, pk, message]?);
+
+    Ok(C::ScalarField::from_le_bytes_mod_order(&digest))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::discrete_log_cards;
+    use crate::BarnettSmartProtocol;
+
+    use proof_essentials::error::CryptoError;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+
+    #[test]
+    fn test_signature() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 2, 4).unwrap();
+        let (pk, sk) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+        let (other_pk, _) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+
+        let signature = CardProtocol::sign(rng, &parameters, &sk, &pk, b"shuffle").unwrap();
+        assert_eq!(
+            CardProtocol::verify_signature(&parameters, &pk, b"shuffle", &signature),
+            Ok(())
+        );
+
+        let expected_error = Err(CryptoError::ProofVerificationError(String::from(
+            "Signature",
+        )));
+        assert_eq!(
+            CardProtocol::verify_signature(&parameters, &pk, b"reveal", &signature),
+            expected_error
+        );
+        assert_eq!(
+            CardProtocol::verify_signature(&parameters, &other_pk, b"shuffle", &signature),
+            expected_error
+        );
+    }
+}
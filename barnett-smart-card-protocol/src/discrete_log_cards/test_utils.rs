@@ -0,0 +1,26 @@
+// Helpers shared by the test modules of the crate.
+
+use super::{DLCards, Parameters, PlayerSecretKey, PublicKey};
+use crate::BarnettSmartProtocol;
+
+use ark_ec::ProjectiveCurve;
+use ark_std::{rand::Rng, Zero};
+
+/// The public and secret key of a player.
+pub(crate) type PlayerKeys<C> = (PublicKey<C>, PlayerSecretKey<C>);
+
+/// Generate the keys of `num_of_players` players, along with their aggregate key.
+pub(crate) fn setup_players<C: ProjectiveCurve, R: Rng>(
+    rng: &mut R,
+    parameters: &Parameters<C>,
+    num_of_players: usize,
+) -> (Vec<PlayerKeys<C>>, PublicKey<C>) {
+    let players = (0..num_of_players)
+        .map(|_| DLCards::<C>::player_keygen(rng, parameters).unwrap())
+        .collect::<Vec<_>>();
+    let aggregate_key = players
+        .iter()
+        .fold(PublicKey::<C>::zero(), |key, (pk, _)| key + *pk);
+
+    (players, aggregate_key)
+}
@@ -64,7 +64,7 @@ mod test {
             CardProtocol::verify_key_ownership(&parameters, &pk, &player_name, &p1_keyproof)
         );
 
-        let other_key = Scalar::rand(rng);
+        let other_key = SecretKey::new(Scalar::rand(rng));
         let wrong_proof =
             CardProtocol::prove_key_ownership(rng, &parameters, &pk, &other_key, &player_name)
                 .unwrap();
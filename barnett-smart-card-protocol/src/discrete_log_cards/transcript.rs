@@ -0,0 +1,640 @@
+// An append-only record of every public protocol message of a game.
+//
+// Each entry commits to the hash of the entry before it, so a transcript cannot be edited or
+// reordered without breaking the chain. `replay_verify` re-checks every proof in the order the
+// messages were sent and reconstructs the cards that were publicly revealed, which lets a third
+// party settle a dispute from the transcript alone.
+
+use super::{
+    Card, DLCards, FaceDownCard, MaskedCard, Parameters, PublicKey, RevealToken, ShuffleChain,
+    ShuffleLink, TranscriptHash,
+};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::ProjectiveCurve;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::io::{Read, Write};
+use ark_std::{string::ToString, vec, vec::Vec};
+use blake2::{Blake2s, Digest};
+
+const TRANSCRIPT_TAG: &[u8] = b"Game Transcript";
+
+/// A player's public key, the public information it was registered with and the proof of key
+/// ownership.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KeyRegistration<C: ProjectiveCurve> {
+    pub player: usize,
+    pub public_key: PublicKey<C>,
+    pub info: Vec<u8>,
+    pub proof: <DLCards<C> as BarnettSmartProtocol>::ZKProofKeyOwnership,
+}
+
+/// The open cards of the game, their initial masking under the aggregate key and the masking
+/// proofs.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MaskedDeck<C: ProjectiveCurve> {
+    pub cards: Vec<Card<C>>,
+    pub masked_cards: Vec<MaskedCard<C>>,
+    pub proofs: Vec<<DLCards<C> as BarnettSmartProtocol>::ZKProofMasking>,
+}
+
+/// A reveal token published by `player` for the card at `position` of the current deck.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PublishedReveal<C: ProjectiveCurve> {
+    pub player: usize,
+    pub position: usize,
+    pub token: RevealToken<C>,
+    pub proof: <DLCards<C> as BarnettSmartProtocol>::ZKProofReveal,
+}
+
+/// A public protocol message. Positions always refer to the current deck, i.e. the output of the
+/// latest shuffle.
+pub enum TranscriptMessage<C: ProjectiveCurve> {
+    Parameters(Parameters<C>),
+    KeyRegistration(KeyRegistration<C>),
+    MaskedDeck(MaskedDeck<C>),
+    Shuffle(ShuffleLink<C>),
+    /// The card at `position` remasked by `player`.
+    Remask(FaceDownCard<C>),
+    Reveal(PublishedReveal<C>),
+}
+
+impl<C: ProjectiveCurve> CanonicalSerialize for TranscriptMessage<C> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        match self {
+            TranscriptMessage::Parameters(parameters) => {
+                0u8.serialize(&mut writer)?;
+                parameters.serialize(&mut writer)
+            }
+            TranscriptMessage::KeyRegistration(registration) => {
+                1u8.serialize(&mut writer)?;
+                registration.serialize(&mut writer)
+            }
+            TranscriptMessage::MaskedDeck(deck) => {
+                2u8.serialize(&mut writer)?;
+                deck.serialize(&mut writer)
+            }
+            TranscriptMessage::Shuffle(link) => {
+                3u8.serialize(&mut writer)?;
+                link.serialize(&mut writer)
+            }
+            TranscriptMessage::Remask(remask) => {
+                4u8.serialize(&mut writer)?;
+                remask.serialize(&mut writer)
+            }
+            TranscriptMessage::Reveal(reveal) => {
+                5u8.serialize(&mut writer)?;
+                reveal.serialize(&mut writer)
+            }
+        }
+    }
+
+    fn serialized_size(&self) -> usize {
+        1 + match self {
+            TranscriptMessage::Parameters(parameters) => parameters.serialized_size(),
+            TranscriptMessage::KeyRegistration(registration) => registration.serialized_size(),
+            TranscriptMessage::MaskedDeck(deck) => deck.serialized_size(),
+            TranscriptMessage::Shuffle(link) => link.serialized_size(),
+            TranscriptMessage::Remask(remask) => remask.serialized_size(),
+            TranscriptMessage::Reveal(reveal) => reveal.serialized_size(),
+        }
+    }
+}
+
+impl<C: ProjectiveCurve> CanonicalDeserialize for TranscriptMessage<C> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        match u8::deserialize(&mut reader)? {
+            0 => Ok(TranscriptMessage::Parameters(Parameters::deserialize(
+                &mut reader,
+            )?)),
+            1 => Ok(TranscriptMessage::KeyRegistration(
+                KeyRegistration::deserialize(&mut reader)?,
+            )),
+            2 => Ok(TranscriptMessage::MaskedDeck(MaskedDeck::deserialize(
+                &mut reader,
+            )?)),
+            3 => Ok(TranscriptMessage::Shuffle(ShuffleLink::deserialize(
+                &mut reader,
+            )?)),
+            4 => Ok(TranscriptMessage::Remask(FaceDownCard::deserialize(
+                &mut reader,
+            )?)),
+            5 => Ok(TranscriptMessage::Reveal(PublishedReveal::deserialize(
+                &mut reader,
+            )?)),
+            _ => Err(SerializationError::InvalidData),
+        }
+    }
+}
+
+/// A message and the hashes chaining it to the rest of the transcript.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct TranscriptEntry<C: ProjectiveCurve> {
+    pub previous: Vec<u8>,
+    pub message: TranscriptMessage<C>,
+    pub hash: Vec<u8>,
+}
+
+impl<C: ProjectiveCurve> TranscriptEntry<C> {
+    fn compute_hash(
+        index: usize,
+        previous: &[u8],
+        message: &TranscriptMessage<C>,
+    ) -> Result<Vec<u8>, CardProtocolError> {
+        let mut bytes = Vec::new();
+        message
+            .serialize(&mut bytes)
+            .map_err(|e| CardProtocolError::IoError(e.to_string()))?;
+
+        let mut hasher = Blake2s::new();
+        hasher.update(TRANSCRIPT_TAG);
+        hasher.update((index as u64).to_le_bytes());
+        hasher.update(previous);
+        hasher.update(&bytes);
+
+        Ok(hasher.finalize().to_vec())
+    }
+}
+
+/// Hash-chained log of the public messages of a game.
+#[derive(Default, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Transcript<C: ProjectiveCurve> {
+    entries: Vec<TranscriptEntry<C>>,
+}
+
+impl<C: ProjectiveCurve> Transcript<C> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn entries(&self) -> &[TranscriptEntry<C>] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Hash of the latest entry, which commits to the whole transcript. Empty for an empty
+    /// transcript.
+    pub fn head(&self) -> &[u8] {
+        self.entries.last().map_or(&[], |entry| &entry.hash)
+    }
+
+    /// Append a message, chaining it to the current head. Returns the hash of the new entry.
+    pub fn append(&mut self, message: TranscriptMessage<C>) -> Result<&[u8], CardProtocolError> {
+        let previous = self.head().to_vec();
+        let hash = TranscriptEntry::compute_hash(self.entries.len(), &previous, &message)?;
+        self.entries.push(TranscriptEntry {
+            previous,
+            message,
+            hash,
+        });
+
+        Ok(self.head())
+    }
+
+    /// Check that every entry extends the one before it and that its hash is correct.
+    pub fn verify_chain(&self) -> Result<(), CardProtocolError> {
+        let mut previous: &[u8] = &[];
+        for (index, entry) in self.entries.iter().enumerate() {
+            let hash = TranscriptEntry::compute_hash(index, previous, &entry.message)?;
+            if entry.previous != previous || entry.hash != hash {
+                return Err(CardProtocolError::BrokenTranscript(index));
+            }
+            previous = &entry.hash;
+        }
+
+        Ok(())
+    }
+}
+
+/// Public outcome of a game, reconstructed from its transcript.
+pub struct ReplayedGame<C: ProjectiveCurve> {
+    pub parameters: Parameters<C>,
+    pub player_keys: Vec<PublicKey<C>>,
+    pub aggregate_key: PublicKey<C>,
+    /// Open cards of the initial deck.
+    pub cards: Vec<Card<C>>,
+    /// Deck at the end of the game.
+    pub deck: Vec<MaskedCard<C>>,
+    /// Card at each position of the final deck, for the positions every player published a
+    /// reveal token for.
+    pub revealed: Vec<Option<Card<C>>>,
+}
+
+/// Copy of a shuffle from the transcript, for a `ShuffleChain` to own.
+fn copy_link<C: ProjectiveCurve>(
+    link: &ShuffleLink<C>,
+) -> Result<ShuffleLink<C>, CardProtocolError> {
+    let mut bytes = Vec::new();
+    link.serialize(&mut bytes)
+        .map_err(|e| CardProtocolError::IoError(e.to_string()))?;
+    ShuffleLink::deserialize(&bytes[..]).map_err(|e| CardProtocolError::IoError(e.to_string()))
+}
+
+/// The aggregate key, the open cards of the masked deck and the shuffles of that deck.
+type DeckSetup<C, D> = (PublicKey<C>, Vec<Card<C>>, ShuffleChain<C, D>);
+
+type PublishedToken<C> = (
+    RevealToken<C>,
+    <DLCards<C> as BarnettSmartProtocol>::ZKProofReveal,
+    PublicKey<C>,
+);
+
+impl<C: ProjectiveCurve, D: TranscriptHash> DLCards<C, D> {
+    /// Verify the hash chain of `transcript` and every proof it contains, in order, and
+    /// reconstruct the publicly revealed cards.
+    ///
+    /// The transcript must start with the parameters, followed by the key registrations of
+    /// players 0 to n - 1, the masked deck and one shuffle by each player, in the order they
+    /// registered. Remasks and reveals of the shuffled deck can then follow in any order, and no
+    /// more shuffles are accepted. A remask resets the tokens of the remasked position.
+    pub fn replay_verify(transcript: &Transcript<C>) -> Result<ReplayedGame<C>, CardProtocolError> {
+        transcript.verify_chain()?;
+
+        let mut entries = transcript.entries().iter().enumerate();
+
+        let parameters = match entries.next() {
+            Some((_, entry)) => match &entry.message {
+                TranscriptMessage::Parameters(parameters) => parameters.clone(),
+                _ => return Err(CardProtocolError::UnexpectedTranscriptEntry(0)),
+            },
+            None => return Err(CardProtocolError::UnexpectedTranscriptEntry(0)),
+        };
+
+        let mut registrations: Vec<(PublicKey<C>, Vec<u8>)> = Vec::new();
+        let mut setup: Option<DeckSetup<C, D>> = None;
+        let mut deck: Vec<MaskedCard<C>> = Vec::new();
+        let mut tokens: Vec<Vec<Option<PublishedToken<C>>>> = Vec::new();
+        let mut revealed: Vec<Option<Card<C>>> = Vec::new();
+
+        for (index, entry) in entries {
+            let unexpected = CardProtocolError::UnexpectedTranscriptEntry(index);
+
+            match (&entry.message, &mut setup) {
+                (TranscriptMessage::KeyRegistration(registration), None) => {
+                    if registration.player != registrations.len() {
+                        return Err(unexpected);
+                    }
+                    Self::verify_key_ownership(
+                        &parameters,
+                        &registration.public_key,
+                        &registration.info,
+                        &registration.proof,
+                    )?;
+                    registrations.push((registration.public_key, registration.info.clone()));
+                }
+                (TranscriptMessage::MaskedDeck(masked_deck), None) => {
+                    let num_of_cards = masked_deck.cards.len();
+                    if registrations.is_empty()
+                        || masked_deck.masked_cards.len() != num_of_cards
+                        || masked_deck.proofs.len() != num_of_cards
+                    {
+                        return Err(unexpected);
+                    }
+
+                    let aggregate_key =
+                        Self::compute_aggregate_key(&parameters, &registrations, None)?;
+                    for ((card, masked_card), proof) in masked_deck
+                        .cards
+                        .iter()
+                        .zip(masked_deck.masked_cards.iter())
+                        .zip(masked_deck.proofs.iter())
+                    {
+                        Self::verify_mask(&parameters, &aggregate_key, card, masked_card, proof)?;
+                    }
+
+                    let chain = ShuffleChain::new(
+                        masked_deck.masked_cards.clone(),
+                        (0..registrations.len()).collect(),
+                    )?;
+
+                    deck = masked_deck.masked_cards.clone();
+                    tokens = vec![vec![None; registrations.len()]; num_of_cards];
+                    revealed = vec![None; num_of_cards];
+                    setup = Some((aggregate_key, masked_deck.cards.clone(), chain));
+                }
+                (TranscriptMessage::Shuffle(link), Some((aggregate_key, _, chain))) => {
+                    // Every player has shuffled, so the cards are being dealt
+                    if chain.is_complete() {
+                        return Err(unexpected);
+                    }
+                    chain.push(&parameters, aggregate_key, copy_link(link)?)?;
+
+                    deck = chain.current_deck().to_vec();
+                }
+                (TranscriptMessage::Remask(remask), Some((aggregate_key, _, chain))) => {
+                    if !chain.is_complete()
+                        || remask.player >= registrations.len()
+                        || remask.position >= deck.len()
+                    {
+                        return Err(unexpected);
+                    }
+                    Self::verify_remask(
+                        &parameters,
+                        aggregate_key,
+                        &deck[remask.position],
+                        &remask.masked_card,
+                        &remask.proof,
+                    )?;
+
+                    deck[remask.position] = remask.masked_card;
+                    tokens[remask.position] = vec![None; registrations.len()];
+                }
+                (TranscriptMessage::Reveal(reveal), Some((_, _, chain))) => {
+                    if !chain.is_complete()
+                        || reveal.player >= registrations.len()
+                        || reveal.position >= deck.len()
+                    {
+                        return Err(unexpected);
+                    }
+                    let pk = registrations[reveal.player].0;
+                    Self::verify_reveal(
+                        &parameters,
+                        &pk,
+                        &reveal.token,
+                        &deck[reveal.position],
+                        &reveal.proof,
+                    )?;
+
+                    let position_tokens = &mut tokens[reveal.position];
+                    position_tokens[reveal.player] = Some((reveal.token, reveal.proof, pk));
+                    if position_tokens.iter().all(Option::is_some) {
+                        let decryption_key = position_tokens.iter().flatten().copied().collect();
+                        revealed[reveal.position] = Some(Self::unmask(
+                            &parameters,
+                            &decryption_key,
+                            &deck[reveal.position],
+                            true,
+                        )?);
+                    }
+                }
+                _ => return Err(unexpected),
+            }
+        }
+
+        let (aggregate_key, cards, _) = setup.ok_or(
+            CardProtocolError::UnexpectedTranscriptEntry(transcript.len()),
+        )?;
+
+        Ok(ReplayedGame {
+            parameters,
+            player_keys: registrations.into_iter().map(|(pk, _)| pk).collect(),
+            aggregate_key,
+            cards,
+            deck,
+            revealed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::discrete_log_cards::{
+        self, FaceDownCard, KeyRegistration, MaskedDeck, PublishedReveal, ShuffleLink, Transcript,
+        TranscriptMessage,
+    };
+    use crate::error::CardProtocolError;
+    use crate::BarnettSmartProtocol;
+
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::{UniformRand, Zero};
+    use proof_essentials::utils::permutation::Permutation;
+    use proof_essentials::utils::rand::sample_vector;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+    type PublicKey = discrete_log_cards::PublicKey<Curve>;
+    type Card = discrete_log_cards::Card<Curve>;
+    type MaskedCard = discrete_log_cards::MaskedCard<Curve>;
+
+    #[test]
+    fn test_replay() {
+        let rng = &mut thread_rng();
+        let m = 2;
+        let n = 4;
+        let num_of_players = 3;
+
+        let parameters = CardProtocol::setup(rng, m, n).unwrap();
+        let mut transcript = Transcript::new();
+        transcript
+            .append(TranscriptMessage::Parameters(parameters.clone()))
+            .unwrap();
+
+        let mut players = Vec::with_capacity(num_of_players);
+        for player in 0..num_of_players {
+            let (pk, sk) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+            let info = vec![player as u8];
+            let proof =
+                CardProtocol::prove_key_ownership(rng, &parameters, &pk, &sk, &info).unwrap();
+            transcript
+                .append(TranscriptMessage::KeyRegistration(KeyRegistration {
+                    player,
+                    public_key: pk,
+                    info,
+                    proof,
+                }))
+                .unwrap();
+            players.push((pk, sk));
+        }
+        let aggregate_key = players
+            .iter()
+            .fold(PublicKey::zero(), |acc, (pk, _)| acc + *pk);
+
+        let cards: Vec<Card> = sample_vector(rng, m * n);
+        let (masked_cards, proofs): (Vec<_>, Vec<_>) = cards
+            .iter()
+            .map(|card| {
+                let alpha = Scalar::rand(rng);
+                CardProtocol::mask(rng, &parameters, &aggregate_key, card, &alpha).unwrap()
+            })
+            .unzip();
+        transcript
+            .append(TranscriptMessage::MaskedDeck(MaskedDeck {
+                cards: cards.clone(),
+                masked_cards: masked_cards.clone(),
+                proofs,
+            }))
+            .unwrap();
+
+        let mut deck = masked_cards;
+        let mut shuffled_decks = Vec::with_capacity(num_of_players);
+        for player in 0..num_of_players {
+            let permutation = Permutation::new(rng, m * n);
+            let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
+            let (shuffled_deck, proof) = CardProtocol::shuffle_and_remask(
+                rng,
+                &parameters,
+                &aggregate_key,
+                &deck,
+                &masking_factors,
+                &permutation,
+            )
+            .unwrap();
+            transcript
+                .append(TranscriptMessage::Shuffle(ShuffleLink {
+                    player,
+                    shuffled_deck: shuffled_deck.clone(),
+                    proof,
+                }))
+                .unwrap();
+            shuffled_decks.push(shuffled_deck.clone());
+            deck = shuffled_deck;
+        }
+
+        // Player 1 remasks the card at position 2, then everyone reveals it. Position 5 only gets
+        // the tokens of players 0 and 1.
+        let alpha = Scalar::rand(rng);
+        let (masked_card, proof) =
+            CardProtocol::remask(rng, &parameters, &aggregate_key, &deck[2], &alpha).unwrap();
+        transcript
+            .append(TranscriptMessage::Remask(FaceDownCard {
+                player: 1,
+                position: 2,
+                masked_card,
+                proof,
+            }))
+            .unwrap();
+        deck[2] = masked_card;
+
+        for (position, revealers) in [(2, 0..3), (5, 0..2)] {
+            for player in revealers {
+                let (pk, sk) = &players[player];
+                let (token, proof) =
+                    CardProtocol::compute_reveal_token(rng, &parameters, sk, pk, &deck[position])
+                        .unwrap();
+                transcript
+                    .append(TranscriptMessage::Reveal(PublishedReveal {
+                        player,
+                        position,
+                        token,
+                        proof,
+                    }))
+                    .unwrap();
+            }
+        }
+
+        let replayed = CardProtocol::replay_verify(&transcript).unwrap();
+        assert_eq!(replayed.aggregate_key, aggregate_key);
+        assert_eq!(replayed.deck, deck);
+        assert!(cards.contains(&replayed.revealed[2].unwrap()));
+        assert_eq!(
+            replayed
+                .revealed
+                .iter()
+                .filter(|card| card.is_some())
+                .count(),
+            1
+        );
+
+        // The transcript survives serialization
+        let mut bytes = Vec::new();
+        transcript.serialize(&mut bytes).unwrap();
+        let stored = Transcript::<Curve>::deserialize(&bytes[..]).unwrap();
+        assert_eq!(stored.head(), transcript.head());
+        assert!(CardProtocol::replay_verify(&stored).is_ok());
+
+        // Any tampering breaks the chain
+        let last = transcript.len() - 1;
+        let mut tampered = Transcript::<Curve>::deserialize(&bytes[..]).unwrap();
+        if let TranscriptMessage::Reveal(reveal) = &mut tampered.entries[last].message {
+            reveal.position = 4;
+        }
+        assert_eq!(
+            CardProtocol::replay_verify(&tampered).err(),
+            Some(CardProtocolError::BrokenTranscript(last))
+        );
+
+        // A re-chained transcript with a reveal token for the wrong card fails its proof
+        let mut forged = Transcript::new();
+        for entry in tampered.entries {
+            forged.append(entry.message).unwrap();
+        }
+        assert!(matches!(
+            CardProtocol::replay_verify(&forged),
+            Err(CardProtocolError::ProofVerificationError(_))
+        ));
+
+        let mut shuffle = |player: usize, deck: &Vec<MaskedCard>| {
+            let permutation = Permutation::new(rng, m * n);
+            let masking_factors: Vec<Scalar> = sample_vector(rng, m * n);
+            let (shuffled_deck, proof) = CardProtocol::shuffle_and_remask(
+                rng,
+                &parameters,
+                &aggregate_key,
+                deck,
+                &masking_factors,
+                &permutation,
+            )
+            .unwrap();
+            TranscriptMessage::Shuffle(ShuffleLink {
+                player,
+                shuffled_deck,
+                proof,
+            })
+        };
+
+        // No shuffle once the cards are being dealt
+        let mut reshuffled = prefix(&bytes, transcript.len());
+        reshuffled.append(shuffle(0, &deck)).unwrap();
+        assert_eq!(
+            CardProtocol::replay_verify(&reshuffled).err(),
+            Some(CardProtocolError::UnexpectedTranscriptEntry(
+                transcript.len()
+            ))
+        );
+
+        // Players shuffle once each, in the order they registered
+        let first_shuffle = 1 + num_of_players + 1;
+        let mut repeated = prefix(&bytes, first_shuffle + 1);
+        repeated.append(shuffle(0, &shuffled_decks[0])).unwrap();
+        assert_eq!(
+            CardProtocol::replay_verify(&repeated).err(),
+            Some(CardProtocolError::ShuffleOutOfOrder(1, 0))
+        );
+
+        // No reveal before every player has shuffled
+        let mut early = prefix(&bytes, first_shuffle + 1);
+        let (pk, sk) = &players[0];
+        let (token, proof) =
+            CardProtocol::compute_reveal_token(rng, &parameters, sk, pk, &shuffled_decks[0][0])
+                .unwrap();
+        early
+            .append(TranscriptMessage::Reveal(PublishedReveal {
+                player: 0,
+                position: 0,
+                token,
+                proof,
+            }))
+            .unwrap();
+        assert_eq!(
+            CardProtocol::replay_verify(&early).err(),
+            Some(CardProtocolError::UnexpectedTranscriptEntry(
+                first_shuffle + 1
+            ))
+        );
+    }
+
+    /// The first `len` messages of the encoded transcript `bytes`, chained anew.
+    fn prefix(bytes: &[u8], len: usize) -> Transcript<Curve> {
+        let stored = Transcript::<Curve>::deserialize(bytes).unwrap();
+        let mut transcript = Transcript::new();
+        for entry in stored.entries.into_iter().take(len) {
+            transcript.append(entry.message).unwrap();
+        }
+        transcript
+    }
+}
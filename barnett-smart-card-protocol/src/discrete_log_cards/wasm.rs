@@ -1,18 +1,24 @@
 // a module wrapping the rest of this so it's compabitle with wasm.
 // since wasm functions can't use type parameters, we fix the curve to edwards on bn254 and pass serrialized buffers to/from js
 
-use crate::discrete_log_cards::DLCards;
+use crate::discrete_log_cards::{DLCards, EncryptedSecretKey};
+use crate::error::CardProtocolError;
+#[cfg(feature = "js")]
+use crate::games::CardSemantics;
+use crate::games::{FrenchCard, FrenchDeck, StandardCard};
 use crate::BarnettSmartProtocol;
 use ark_ec::ProjectiveCurve;
 use ark_ed_on_bn254::{EdwardsProjective, Fr};
 use ark_ff::One;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use ark_std::io::{Read, Write};
+#[cfg(feature = "js")]
+use blake2::{Blake2s, Digest};
 use proof_essentials::homomorphic_encryption::el_gamal;
 use proof_essentials::utils::permutation::Permutation;
 use proof_essentials::utils::rand::sample_vector;
 use rand::rngs::StdRng;
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "js")]
@@ -24,10 +30,40 @@ use borsh::{BorshDeserialize, BorshSerialize};
 #[cfg(feature = "js")]
 use serde_wasm_bindgen::{from_value, to_value};
 
+#[cfg(feature = "js")]
+use crate::discrete_log_cards::WasmKeypair;
+
 use crate::discrete_log_cards::{
     Card, MaskedCard, Parameters, PlayerSecretKey, PublicKey, RevealToken, ZKProofShuffle,
 };
 
+#[cfg(feature = "js")]
+const JS_RNG_TAG: &[u8] = b"Wasm RNG";
+
+/// The randomness of a single wasm call. It is seeded from the OS, i.e. `crypto.getRandomValues`
+/// in the browser. Caller `entropy` of at least 32 bytes may be mixed in, along with a `domain`
+/// naming the call, but it never replaces the OS randomness, so reusing it is harmless.
+#[cfg(feature = "js")]
+pub(crate) fn js_rng(domain: &str, entropy: Option<Vec<u8>>) -> Result<StdRng, JsError> {
+    let mut os_seed = [0u8; 32];
+    getrandom::getrandom(&mut os_seed)
+        .map_err(|e| JsError::new(&format!("no randomness available: {}", e)))?;
+
+    let mut hasher = Blake2s::new();
+    hasher.update(JS_RNG_TAG);
+    hasher.update((domain.len() as u64).to_le_bytes());
+    hasher.update(domain.as_bytes());
+    hasher.update(os_seed);
+    if let Some(entropy) = entropy {
+        if entropy.len() < 32 {
+            return Err(JsError::new("entropy must be >= 32 bytes"));
+        }
+        hasher.update(entropy);
+    }
+
+    Ok(StdRng::from_seed(hasher.finalize().into()))
+}
+
 pub type BnScalar = Fr;
 pub type BnPublicKey = PublicKey<EdwardsProjective>;
 pub type BnPlayerSecretKey = PlayerSecretKey<EdwardsProjective>;
@@ -78,37 +114,39 @@ impl BnPublicKeyBuf {
     }
 }
 
+/// Player secret keys only cross the wasm boundary encrypted under a password.
 #[derive(Clone, Debug, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
-pub struct BnPlayerSecretKeyBuf {
-    pub(crate) buf: Vec<u8>,
+pub struct BnEncryptedSecretKeyBuf {
+    pub(crate) keystore: EncryptedSecretKey,
 }
 
-impl BnPlayerSecretKeyBuf {
-    pub fn new(buf: Vec<u8>) -> Self {
-        Self { buf }
-    }
-
-    pub fn serialize(item: BnPlayerSecretKey) -> Result<Self, SerializationError> {
-        let mut buf = Vec::new();
-        item.serialize_unchecked(&mut buf)?;
-        Ok(Self { buf })
+impl BnEncryptedSecretKeyBuf {
+    pub fn new(keystore: EncryptedSecretKey) -> Self {
+        Self { keystore }
     }
 
-    pub fn deserialize(&self) -> Result<BnPlayerSecretKey, SerializationError> {
-        BnPlayerSecretKey::deserialize_unchecked(self.buf.as_slice())
+    pub fn encrypt<R: Rng>(
+        rng: &mut R,
+        item: &BnPlayerSecretKey,
+        password: &[u8],
+    ) -> Result<Self, CardProtocolError> {
+        Ok(Self {
+            keystore: item.encrypt(rng, password)?,
+        })
     }
 
-    #[cfg(feature = "js")]
-    pub fn from_js(val: JsValue) -> Result<BnPlayerSecretKey, JsError> {
-        let s: Self = from_value(val).map_err(|_| JsError::new("serialization from js failed"))?;
-        s.deserialize()
-            .map_err(|_| JsError::new("deserialization to arkworks failed"))
+    pub fn decrypt(&self, password: &[u8]) -> Result<BnPlayerSecretKey, CardProtocolError> {
+        self.keystore.decrypt(password)
     }
 
     #[cfg(feature = "js")]
-    pub fn to_js(val: BnPlayerSecretKey) -> Result<JsValue, JsError> {
-        let s =
-            Self::serialize(val).map_err(|_| JsError::new("serialization to arkworks failed"))?;
+    pub fn to_js<R: Rng>(
+        rng: &mut R,
+        val: &BnPlayerSecretKey,
+        password: &str,
+    ) -> Result<JsValue, JsError> {
+        let s = Self::encrypt(rng, val, password.as_bytes())
+            .map_err(|_| JsError::new("failed to encrypt secret key"))?;
         to_value(&s).map_err(|_| JsError::new("serialization to js failed"))
     }
 }
@@ -431,39 +469,56 @@ impl BnParamsBuf {
 #[derive(Clone, Debug, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 pub struct BnKeypairBuf {
     pub(crate) pk: Vec<u8>,
-    pub(crate) sk: Vec<u8>,
+    pub(crate) sk: EncryptedSecretKey,
 }
 
 impl BnKeypairBuf {
-    pub fn new(pk: Vec<u8>, sk: Vec<u8>) -> Self {
+    pub fn new(pk: Vec<u8>, sk: EncryptedSecretKey) -> Self {
         Self { pk, sk }
     }
 
-    pub fn serialize(_pk: BnPublicKey, _sk: BnPlayerSecretKey) -> Result<Self, SerializationError> {
+    pub fn serialize<R: Rng>(
+        rng: &mut R,
+        _pk: BnPublicKey,
+        _sk: &BnPlayerSecretKey,
+        password: &[u8],
+    ) -> Result<Self, CardProtocolError> {
         let mut pk = Vec::new();
-        let mut sk = Vec::new();
-        _pk.serialize_unchecked(&mut pk)?;
-        _sk.serialize_unchecked(&mut sk)?;
+        _pk.serialize_unchecked(&mut pk)
+            .map_err(|e| CardProtocolError::IoError(e.to_string()))?;
+        let sk = _sk.encrypt(rng, password)?;
         Ok(Self { pk, sk })
     }
 
-    pub fn deserialize(&self) -> Result<(BnPublicKey, BnPlayerSecretKey), SerializationError> {
-        let pk = BnPublicKey::deserialize_unchecked(self.pk.as_slice())?;
-        let sk = BnPlayerSecretKey::deserialize_unchecked(self.sk.as_slice())?;
+    pub fn deserialize(
+        &self,
+        password: &[u8],
+    ) -> Result<(BnPublicKey, BnPlayerSecretKey), CardProtocolError> {
+        let pk = BnPublicKey::deserialize_unchecked(self.pk.as_slice())
+            .map_err(|e| CardProtocolError::IoError(e.to_string()))?;
+        let sk = self.sk.decrypt(password)?;
         Ok((pk, sk))
     }
 
     #[cfg(feature = "js")]
-    pub fn from_js(val: JsValue) -> Result<(BnPublicKey, BnPlayerSecretKey), JsError> {
+    pub fn from_js(
+        val: JsValue,
+        password: &str,
+    ) -> Result<(BnPublicKey, BnPlayerSecretKey), JsError> {
         let s: Self = from_value(val).map_err(|_| JsError::new("serialization from js failed"))?;
-        s.deserialize()
-            .map_err(|_| JsError::new("deserialization to arkworks failed"))
+        s.deserialize(password.as_bytes())
+            .map_err(|_| JsError::new("failed to decrypt keypair"))
     }
 
     #[cfg(feature = "js")]
-    pub fn to_js(pk: BnPublicKey, sk: BnPlayerSecretKey) -> Result<JsValue, JsError> {
-        let s = Self::serialize(pk, sk)
-            .map_err(|_| JsError::new("serialization to arkworks failed"))?;
+    pub fn to_js<R: Rng>(
+        rng: &mut R,
+        pk: BnPublicKey,
+        sk: &BnPlayerSecretKey,
+        password: &str,
+    ) -> Result<JsValue, JsError> {
+        let s = Self::serialize(rng, pk, sk, password.as_bytes())
+            .map_err(|_| JsError::new("failed to encrypt keypair"))?;
         to_value(&s).map_err(|_| JsError::new("serialization to js failed"))
     }
 }
@@ -619,6 +674,8 @@ impl BnRevealTokenWithProofBuf {
     }
 }
 
+/// Stateless bindings that parse every argument on each call. The classes of `wasm_classes` keep
+/// parsed values between calls.
 #[cfg_attr(feature = "js", wasm_bindgen)]
 pub struct WasmBnDlCards;
 
@@ -630,36 +687,37 @@ impl WasmBnDlCards {
         Self
     }
 
-    pub fn player_keygen(params: JsValue, entropy: &[u8]) -> Result<JsValue, JsError> {
-        let mut rng = StdRng::from_seed(
-            entropy[0..32]
-                .try_into()
-                .map_err(|_| JsError::new("entropy must be >= 32 bytes"))?,
-        );
+    /// Generate a keypair. The secret key is returned encrypted under `password`.
+    pub fn player_keygen(
+        params: JsValue,
+        password: &str,
+        entropy: Option<Vec<u8>>,
+    ) -> Result<JsValue, JsError> {
+        let mut rng = js_rng("player_keygen", entropy)?;
         let params = BnParamsBuf::from_js(params)?;
         let (pk, sk) = BnCardProtocol::player_keygen(&mut rng, &params)
             .map_err(|_| JsError::new("failed to generate keypair"))?;
-        BnKeypairBuf::to_js(pk, sk)
+        BnKeypairBuf::to_js(&mut rng, pk, &sk, password)
+    }
+
+    /// Decrypt a keypair from `player_keygen` once, for the calls that need the secret key.
+    pub fn unlock_keypair(keypair: JsValue, password: &str) -> Result<WasmKeypair, JsError> {
+        let (pk, sk) = BnKeypairBuf::from_js(keypair, password)?;
+        Ok(WasmKeypair::new(pk, sk))
     }
 
     pub fn prove_key_ownership(
         params: JsValue,
-        pk: JsValue,
-        sk: JsValue,
+        keypair: &WasmKeypair,
         player_id: &str,
-        entropy: &[u8],
+        entropy: Option<Vec<u8>>,
     ) -> Result<JsValue, JsError> {
-        let mut rng = StdRng::from_seed(
-            entropy[0..32]
-                .try_into()
-                .map_err(|_| JsError::new("entropy must be >= 32 bytes"))?,
-        );
+        let mut rng = js_rng("prove_key_ownership", entropy)?;
         let params = BnParamsBuf::from_js(params)?;
-        let pk = BnPublicKeyBuf::from_js(pk)?;
-        let sk = BnPlayerSecretKeyBuf::from_js(sk)?;
+        let (pk, sk) = keypair.keys();
 
         let proof =
-            BnCardProtocol::prove_key_ownership(&mut rng, &params, &pk, &sk, player_id.as_bytes())
+            BnCardProtocol::prove_key_ownership(&mut rng, &params, pk, sk, player_id.as_bytes())
                 .map_err(|_| JsError::new("failed to generate proof"))?;
         BnZKProofKeyOwnershipBuf::to_js(proof)
     }
@@ -668,16 +726,12 @@ impl WasmBnDlCards {
         pp: JsValue,
         shared_key: JsValue,
         original_card: JsValue,
-        entropy: &[u8],
+        entropy: Option<Vec<u8>>,
     ) -> Result<JsValue, JsError> {
         let pp = BnParamsBuf::from_js(pp)?;
         let shared_key = BnPublicKeyBuf::from_js(shared_key)?;
         let original_card = BnCardBuf::from_js(original_card)?;
-        let mut rng = StdRng::from_seed(
-            entropy[0..32]
-                .try_into()
-                .map_err(|_| JsError::new("entropy must be >= 32 bytes"))?,
-        );
+        let mut rng = js_rng("init_mask", entropy)?;
 
         let (masked_card, proof) =
             BnCardProtocol::mask(&mut rng, &pp, &shared_key, &original_card, &BnScalar::one())
@@ -689,7 +743,7 @@ impl WasmBnDlCards {
         pp: JsValue,
         shared_key: JsValue,
         deck: Vec<JsValue>,
-        entropy: &[u8],
+        entropy: Option<Vec<u8>>,
     ) -> Result<JsValue, JsError> {
         let pp = BnParamsBuf::from_js(pp)?;
         if pp.num_cards() != deck.len() {
@@ -703,11 +757,7 @@ impl WasmBnDlCards {
         }
 
         let shared_key = BnPublicKeyBuf::from_js(shared_key)?;
-        let mut rng = StdRng::from_seed(
-            entropy[0..32]
-                .try_into()
-                .map_err(|_| JsError::new("entropy must be >= 32 bytes"))?,
-        );
+        let mut rng = js_rng("shuffle_and_remask", entropy)?;
 
         let masking_factors = sample_vector(&mut rng, deck.len());
         let permutation = Permutation::new(&mut rng, deck.len());
@@ -731,23 +781,17 @@ impl WasmBnDlCards {
 
     pub fn compute_reveal_token(
         pp: JsValue,
-        sk: JsValue,
-        pk: JsValue,
+        keypair: &WasmKeypair,
         masked_card: JsValue,
-        entropy: &[u8],
+        entropy: Option<Vec<u8>>,
     ) -> Result<JsValue, JsError> {
         let pp = BnParamsBuf::from_js(pp)?;
-        let sk = BnPlayerSecretKeyBuf::from_js(sk)?;
-        let pk = BnPublicKeyBuf::from_js(pk)?;
+        let (pk, sk) = keypair.keys();
         let masked_card = BnMaskedCardBuf::from_js(masked_card)?;
-        let mut rng = StdRng::from_seed(
-            entropy[0..32]
-                .try_into()
-                .map_err(|_| JsError::new("entropy must be >= 32 bytes"))?,
-        );
+        let mut rng = js_rng("compute_reveal_token", entropy)?;
 
         let (reveal_token, proof) =
-            BnCardProtocol::compute_reveal_token(&mut rng, &pp, &sk, &pk, &masked_card)
+            BnCardProtocol::compute_reveal_token(&mut rng, &pp, sk, pk, &masked_card)
                 .map_err(|_| JsError::new("failed to compute reveal token"))?;
         BnRevealTokenWithProofBuf::to_js(reveal_token, proof)
     }
@@ -774,30 +818,47 @@ impl WasmBnDlCards {
     }
 }
 
-pub fn get_card_elems_buf(num_cards: usize) -> Result<Vec<BnCardBuf>, SerializationError> {
+/// The open cards of a deck of `num_cards`: card `i` is `2^i` times the generator.
+pub fn get_card_elems(num_cards: usize) -> Vec<BnCard> {
     let mut card_elems = Vec::new();
     let mut g = EdwardsProjective::prime_subgroup_generator();
     for _ in 0..num_cards {
-        let card_elem = el_gamal::Plaintext(g.into_affine());
-        card_elems.push(BnCardBuf::serialize(card_elem)?);
+        card_elems.push(el_gamal::Plaintext(g.into_affine()));
 
         g = g.double();
     }
 
-    Ok(card_elems)
+    card_elems
+}
+
+/// The cards of `get_card_elems(52)`, read as a French deck.
+pub fn get_french_deck() -> FrenchDeck<EdwardsProjective> {
+    FrenchDeck::new(get_card_elems(FrenchCard::DECK_SIZE)).unwrap()
+}
+
+pub fn get_card_elems_buf(num_cards: usize) -> Result<Vec<BnCardBuf>, SerializationError> {
+    get_card_elems(num_cards)
+        .into_iter()
+        .map(BnCardBuf::serialize)
+        .collect()
 }
 
 #[cfg(feature = "js")]
 #[cfg_attr(feature = "js", wasm_bindgen)]
 pub fn get_card_elemns_js(num_cards: usize) -> Result<Vec<JsValue>, JsError> {
-    let mut card_elems = Vec::new();
-    let mut g = EdwardsProjective::prime_subgroup_generator();
-    for _ in 0..num_cards {
-        let card_elem = el_gamal::Plaintext(g.into_affine());
-        card_elems.push(BnCardBuf::to_js(card_elem)?);
-
-        g = g.double();
-    }
+    get_card_elems(num_cards)
+        .into_iter()
+        .map(BnCardBuf::to_js)
+        .collect()
+}
 
-    Ok(card_elems)
+/// Name of an opened card of the French deck, such as `"10♥"`.
+#[cfg(feature = "js")]
+#[cfg_attr(feature = "js", wasm_bindgen)]
+pub fn decode_french_card_js(card: JsValue) -> Result<String, JsError> {
+    let card = BnCardBuf::from_js(card)?;
+    get_french_deck()
+        .decode(&card)
+        .map(|card| card.to_string())
+        .map_err(|_| JsError::new("card is not in the french deck"))
 }
@@ -0,0 +1,551 @@
+// Stateful wasm classes. Each one keeps a parsed value on the Rust side, so that JS passes handles
+// around instead of reserializing the parameters, keys and deck on every call. Values only go
+// through their canonical encoding in `toBytes` and `fromBytes`, for persistence and for sending
+// them to the other players. Proofs are one-shot messages and are handed out as bytes.
+
+use super::{
+    get_card_elems, js_rng, BnCard, BnCardProtocol, BnMaskedCard, BnParameters, BnPlayerSecretKey,
+    BnPublicKey, BnRevealToken, BnScalar, BnZKProofKeyOwnership, BnZKProofMasking, BnZKProofReveal,
+    BnZKProofShuffle, EncryptedSecretKey, PreparedKey, PreparedParameters,
+};
+use crate::BarnettSmartProtocol;
+
+use ark_ed_on_bn254::EdwardsProjective;
+use ark_ff::{One, UniformRand};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use core::cell::{Ref, RefCell};
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use wasm_bindgen::prelude::*;
+
+fn encode<T: CanonicalSerialize>(value: &T) -> Result<Vec<u8>, JsError> {
+    let mut bytes = Vec::new();
+    value.serialize(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn decode<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T, JsError> {
+    Ok(T::deserialize(bytes)?)
+}
+
+/// Protocol parameters, with the window table of the generator.
+#[wasm_bindgen]
+pub struct WasmParameters {
+    inner: PreparedParameters<EdwardsProjective>,
+}
+
+impl WasmParameters {
+    fn new(parameters: &BnParameters) -> Self {
+        Self {
+            inner: PreparedParameters::new(parameters),
+        }
+    }
+
+    fn parameters(&self) -> &BnParameters {
+        self.inner.parameters()
+    }
+}
+
+#[wasm_bindgen]
+impl WasmParameters {
+    /// Generate parameters for a deck of `m * n` cards.
+    pub fn setup(m: usize, n: usize, entropy: Option<Vec<u8>>) -> Result<WasmParameters, JsError> {
+        let mut rng = js_rng("WasmParameters::setup", entropy)?;
+        let parameters = BnCardProtocol::setup(&mut rng, m, n)?;
+        Ok(Self::new(&parameters))
+    }
+
+    #[wasm_bindgen(js_name = numCards)]
+    pub fn num_cards(&self) -> usize {
+        self.parameters().num_cards()
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        encode(self.parameters())
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<WasmParameters, JsError> {
+        Ok(Self::new(&decode(bytes)?))
+    }
+}
+
+/// A player or aggregate public key. The window table of the key is built the first time it is
+/// used to mask, and kept for the following calls.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct WasmPublicKey {
+    inner: BnPublicKey,
+    prepared: RefCell<Option<PreparedKey<EdwardsProjective>>>,
+}
+
+impl WasmPublicKey {
+    fn new(inner: BnPublicKey) -> Self {
+        Self {
+            inner,
+            prepared: RefCell::new(None),
+        }
+    }
+
+    fn prepared(&self) -> Ref<'_, PreparedKey<EdwardsProjective>> {
+        if self.prepared.borrow().is_none() {
+            *self.prepared.borrow_mut() = Some(PreparedKey::new(&self.inner));
+        }
+        Ref::map(self.prepared.borrow(), |prepared| {
+            prepared.as_ref().unwrap()
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl WasmPublicKey {
+    /// The aggregate key of the players with `keys`, after checking their proofs of ownership.
+    /// `player_ids[i]` is the id the proof of `keys[i]` was bound to, and `proofs` the
+    /// concatenation of the proofs from `WasmKeypair.proveKeyOwnership`, in the order of `keys`.
+    pub fn aggregate(
+        params: &WasmParameters,
+        keys: Vec<WasmPublicKey>,
+        player_ids: Vec<String>,
+        proofs: &[u8],
+    ) -> Result<WasmPublicKey, JsError> {
+        if player_ids.len() != keys.len() {
+            return Err(JsError::new("expected one player id per key"));
+        }
+
+        let mut reader = proofs;
+        let proofs = keys
+            .iter()
+            .map(|_| BnZKProofKeyOwnership::deserialize(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !reader.is_empty() {
+            return Err(JsError::new("expected one proof per key"));
+        }
+
+        let keys_info = keys
+            .iter()
+            .zip(player_ids.iter())
+            .map(|(key, player_id)| (key.inner, player_id.as_bytes()))
+            .collect::<Vec<_>>();
+        let inner =
+            BnCardProtocol::compute_aggregate_key(params.parameters(), &keys_info, Some(&proofs))?;
+        Ok(Self::new(inner))
+    }
+
+    /// Check a proof from `WasmKeypair.proveKeyOwnership` for this key and `player_id`.
+    #[wasm_bindgen(js_name = verifyKeyOwnership)]
+    pub fn verify_key_ownership(
+        &self,
+        params: &WasmParameters,
+        player_id: &str,
+        proof: &[u8],
+    ) -> Result<(), JsError> {
+        let proof: BnZKProofKeyOwnership = decode(proof)?;
+        BnCardProtocol::verify_key_ownership(
+            params.parameters(),
+            &self.inner,
+            player_id.as_bytes(),
+            &proof,
+        )?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        encode(&self.inner)
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<WasmPublicKey, JsError> {
+        Ok(Self::new(decode(bytes)?))
+    }
+}
+
+/// The keypair of the local player. The secret key never leaves the wasm memory unencrypted.
+#[wasm_bindgen]
+pub struct WasmKeypair {
+    pk: BnPublicKey,
+    sk: BnPlayerSecretKey,
+}
+
+impl WasmKeypair {
+    pub(crate) fn new(pk: BnPublicKey, sk: BnPlayerSecretKey) -> Self {
+        Self { pk, sk }
+    }
+
+    pub(crate) fn keys(&self) -> (&BnPublicKey, &BnPlayerSecretKey) {
+        (&self.pk, &self.sk)
+    }
+}
+
+#[wasm_bindgen]
+impl WasmKeypair {
+    pub fn generate(
+        params: &WasmParameters,
+        entropy: Option<Vec<u8>>,
+    ) -> Result<WasmKeypair, JsError> {
+        let mut rng = js_rng("WasmKeypair::generate", entropy)?;
+        let (pk, sk) = BnCardProtocol::player_keygen(&mut rng, params.parameters())?;
+        Ok(Self { pk, sk })
+    }
+
+    #[wasm_bindgen(js_name = publicKey)]
+    pub fn public_key(&self) -> WasmPublicKey {
+        WasmPublicKey::new(self.pk)
+    }
+
+    /// Prove ownership of the public key, binding the proof to `player_id`.
+    #[wasm_bindgen(js_name = proveKeyOwnership)]
+    pub fn prove_key_ownership(
+        &self,
+        params: &WasmParameters,
+        player_id: &str,
+        entropy: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, JsError> {
+        let mut rng = js_rng("WasmKeypair::prove_key_ownership", entropy)?;
+        let proof: BnZKProofKeyOwnership = BnCardProtocol::prove_key_ownership(
+            &mut rng,
+            params.parameters(),
+            &self.pk,
+            &self.sk,
+            player_id.as_bytes(),
+        )?;
+        encode(&proof)
+    }
+
+    #[wasm_bindgen(js_name = computeRevealToken)]
+    pub fn compute_reveal_token(
+        &self,
+        params: &WasmParameters,
+        card: &WasmMaskedCard,
+        entropy: Option<Vec<u8>>,
+    ) -> Result<WasmRevealToken, JsError> {
+        let mut rng = js_rng("WasmKeypair::compute_reveal_token", entropy)?;
+        let (token, proof) = BnCardProtocol::compute_reveal_token(
+            &mut rng,
+            params.parameters(),
+            &self.sk,
+            &self.pk,
+            &card.inner,
+        )?;
+        Ok(WasmRevealToken {
+            token,
+            proof,
+            pk: self.pk,
+        })
+    }
+
+    /// Encode as the public key followed by the secret key encrypted under `password`.
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self, password: &str, entropy: Option<Vec<u8>>) -> Result<Vec<u8>, JsError> {
+        let mut rng = js_rng("WasmKeypair::to_bytes", entropy)?;
+        let mut bytes = encode(&self.pk)?;
+        bytes.extend_from_slice(&self.sk.encrypt(&mut rng, password.as_bytes())?.to_bytes());
+        Ok(bytes)
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8], password: &str) -> Result<WasmKeypair, JsError> {
+        let mut reader = bytes;
+        let pk = BnPublicKey::deserialize(&mut reader)?;
+        let sk = EncryptedSecretKey::from_bytes(reader)?.decrypt(password.as_bytes())?;
+        Ok(Self { pk, sk })
+    }
+}
+
+/// An open card.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct WasmCard {
+    inner: BnCard,
+}
+
+#[wasm_bindgen]
+impl WasmCard {
+    /// The card at `index` of the open deck.
+    #[wasm_bindgen(js_name = fromIndex)]
+    pub fn from_index(params: &WasmParameters, index: usize) -> Result<WasmCard, JsError> {
+        let inner = get_card_elems(params.num_cards())
+            .get(index)
+            .copied()
+            .ok_or_else(|| JsError::new("card index out of range"))?;
+        Ok(Self { inner })
+    }
+
+    /// Mask the card under the aggregate key with a fresh masking factor.
+    pub fn mask(
+        &self,
+        params: &WasmParameters,
+        shared_key: &WasmPublicKey,
+        entropy: Option<Vec<u8>>,
+    ) -> Result<WasmMask, JsError> {
+        let mut rng = js_rng("WasmCard::mask", entropy)?;
+        let alpha = BnScalar::rand(&mut rng);
+        let (card, proof) = BnCardProtocol::mask_prepared(
+            &mut rng,
+            &params.inner,
+            &shared_key.prepared(),
+            &self.inner,
+            &alpha,
+        )?;
+        Ok(WasmMask {
+            card: WasmMaskedCard { inner: card },
+            proof,
+        })
+    }
+
+    /// Position of the card in the open deck, if it is a card of this deck.
+    pub fn index(&self, params: &WasmParameters) -> Option<usize> {
+        get_card_elems(params.num_cards())
+            .iter()
+            .position(|card| *card == self.inner)
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        encode(&self.inner)
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<WasmCard, JsError> {
+        Ok(Self {
+            inner: decode(bytes)?,
+        })
+    }
+}
+
+/// A masked card.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct WasmMaskedCard {
+    inner: BnMaskedCard,
+}
+
+#[wasm_bindgen]
+impl WasmMaskedCard {
+    /// Unmask with the reveal tokens of every player. The tokens are consumed.
+    pub fn unmask(
+        &self,
+        params: &WasmParameters,
+        tokens: Vec<WasmRevealToken>,
+    ) -> Result<WasmCard, JsError> {
+        let decryption_key = tokens
+            .into_iter()
+            .map(|token| (token.token, token.proof, token.pk))
+            .collect::<Vec<_>>();
+        let inner =
+            BnCardProtocol::unmask(params.parameters(), &decryption_key, &self.inner, false)?;
+        Ok(WasmCard { inner })
+    }
+
+    /// Check that this is `card` masked under the aggregate key, with a proof from
+    /// `WasmCard.mask`.
+    #[wasm_bindgen(js_name = verifyMask)]
+    pub fn verify_mask(
+        &self,
+        params: &WasmParameters,
+        shared_key: &WasmPublicKey,
+        card: &WasmCard,
+        proof: &[u8],
+    ) -> Result<(), JsError> {
+        let proof: BnZKProofMasking = decode(proof)?;
+        BnCardProtocol::verify_mask(
+            params.parameters(),
+            &shared_key.inner,
+            &card.inner,
+            &self.inner,
+            &proof,
+        )?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        encode(&self.inner)
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<WasmMaskedCard, JsError> {
+        Ok(Self {
+            inner: decode(bytes)?,
+        })
+    }
+}
+
+/// A masked card and the proof to publish along with it.
+#[wasm_bindgen]
+pub struct WasmMask {
+    card: WasmMaskedCard,
+    proof: BnZKProofMasking,
+}
+
+#[wasm_bindgen]
+impl WasmMask {
+    pub fn card(&self) -> WasmMaskedCard {
+        self.card
+    }
+
+    pub fn proof(&self) -> Result<Vec<u8>, JsError> {
+        encode(&self.proof)
+    }
+}
+
+/// A reveal token with its proof and the public key of the player who computed it.
+#[wasm_bindgen]
+pub struct WasmRevealToken {
+    token: BnRevealToken,
+    proof: BnZKProofReveal,
+    pk: BnPublicKey,
+}
+
+#[wasm_bindgen]
+impl WasmRevealToken {
+    /// Check that the token was computed for `card` with the key of the player who sent it.
+    pub fn verify(&self, params: &WasmParameters, card: &WasmMaskedCard) -> Result<(), JsError> {
+        BnCardProtocol::verify_reveal(
+            params.parameters(),
+            &self.pk,
+            &self.token,
+            &card.inner,
+            &self.proof,
+        )?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        encode(&(self.token, self.proof, self.pk))
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<WasmRevealToken, JsError> {
+        let (token, proof, pk) = decode(bytes)?;
+        Ok(Self { token, proof, pk })
+    }
+}
+
+/// A deck of masked cards.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct WasmDeck {
+    cards: Vec<BnMaskedCard>,
+}
+
+#[wasm_bindgen]
+impl WasmDeck {
+    /// Mask the open deck under the aggregate key, yielding the deck for the first shuffle.
+    pub fn mask(
+        params: &WasmParameters,
+        shared_key: &WasmPublicKey,
+        entropy: Option<Vec<u8>>,
+    ) -> Result<WasmDeck, JsError> {
+        let mut rng = js_rng("WasmDeck::mask", entropy)?;
+        let prepared_key = shared_key.prepared();
+        let cards = get_card_elems(params.num_cards())
+            .iter()
+            .map(|card| {
+                BnCardProtocol::mask_prepared(
+                    &mut rng,
+                    &params.inner,
+                    &prepared_key,
+                    card,
+                    &BnScalar::one(),
+                )
+                .map(|(masked_card, _)| masked_card)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { cards })
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    #[wasm_bindgen(js_name = isEmpty)]
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    pub fn card(&self, index: usize) -> Result<WasmMaskedCard, JsError> {
+        let inner = self
+            .cards
+            .get(index)
+            .copied()
+            .ok_or_else(|| JsError::new("card index out of range"))?;
+        Ok(WasmMaskedCard { inner })
+    }
+
+    /// Shuffle and remask the deck with a fresh permutation and masking factors.
+    pub fn shuffle(
+        &self,
+        params: &WasmParameters,
+        shared_key: &WasmPublicKey,
+        entropy: Option<Vec<u8>>,
+    ) -> Result<WasmShuffle, JsError> {
+        let mut rng = js_rng("WasmDeck::shuffle", entropy)?;
+        let permutation = Permutation::new(&mut rng, self.cards.len());
+        let masking_factors: Vec<BnScalar> = sample_vector(&mut rng, self.cards.len());
+        let (cards, proof) = BnCardProtocol::shuffle_and_remask_prepared(
+            &mut rng,
+            &params.inner,
+            &shared_key.prepared(),
+            &self.cards,
+            &masking_factors,
+            &permutation,
+        )?;
+        Ok(WasmShuffle {
+            deck: WasmDeck { cards },
+            proof,
+        })
+    }
+
+    /// Check that `shuffled` is a correct shuffle of this deck.
+    #[wasm_bindgen(js_name = verifyShuffle)]
+    pub fn verify_shuffle(
+        &self,
+        params: &WasmParameters,
+        shared_key: &WasmPublicKey,
+        shuffled: &WasmDeck,
+        proof: &[u8],
+    ) -> Result<(), JsError> {
+        let proof: BnZKProofShuffle = decode(proof)?;
+        BnCardProtocol::verify_shuffle(
+            params.parameters(),
+            &shared_key.inner,
+            &self.cards,
+            &shuffled.cards,
+            &proof,
+        )?;
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        encode(&self.cards)
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<WasmDeck, JsError> {
+        Ok(Self {
+            cards: decode(bytes)?,
+        })
+    }
+}
+
+/// A shuffled deck and the proof to publish along with it.
+#[wasm_bindgen]
+pub struct WasmShuffle {
+    deck: WasmDeck,
+    proof: BnZKProofShuffle,
+}
+
+#[wasm_bindgen]
+impl WasmShuffle {
+    pub fn deck(&self) -> WasmDeck {
+        self.deck.clone()
+    }
+
+    pub fn proof(&self) -> Result<Vec<u8>, JsError> {
+        encode(&self.proof)
+    }
+}
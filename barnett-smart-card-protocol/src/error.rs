@@ -1,18 +1,193 @@
+use ark_std::fmt;
+use ark_std::string::{String, ToString};
 use proof_essentials::error::CryptoError;
-use thiserror::Error;
 
 /// This is an error that could occur when running a cryptograhic primitive
-#[derive(Error, Debug, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub enum CardProtocolError {
-    #[error("Failed to verify proof")]
-    ProofVerificationError(#[from] CryptoError),
-
-    #[error("IoError: {0}")]
+    ProofVerificationError(CryptoError),
     IoError(String),
+    ClaimNotSatisfied,
+    UnknownPlayer(usize),
+    DeckExhausted,
+    NotInHand(usize, usize),
+    NoPendingReplacement(usize),
+    UnknownDiscard(usize),
+    UnknownPlay(usize),
+    KeystoreError,
+    NoEscrow(usize),
+    InvalidEscrow(usize),
+    InvalidEscrowShare(usize, usize),
+    NoDropoutRecorded(usize),
+    NotEnoughShares(usize, usize),
+    Disconnected(usize),
+    InvalidShuffleOrder,
+    ShuffleOutOfOrder(usize, usize),
+    MissingShuffle(usize),
+    BrokenTranscript(usize),
+    UnexpectedTranscriptEntry(usize),
+    RemaskPoolExhausted(usize, usize),
+    BatchSizeMismatch,
+    InvalidProofInBatch(usize),
+    MissingRevealTokens(usize, usize),
+    UnknownCard,
+    InvalidShoe(usize),
+    ParticipantMismatch(usize, usize),
+    NotPlayersTurn(usize),
+    IllegalAction,
+    RevealPending(usize),
+    NotAwaitingReveal(usize),
+    InvalidReveal(usize),
+    GameNotOver,
+    UnevenSuits(usize, usize),
+    DeckSizeMismatch(usize, usize),
+    DuplicateCard(usize, usize),
+    NotFaceDown(usize),
+    AggregateKeyMismatch,
+    InvalidTimeoutEvidence(usize),
+    BetTooLarge(usize),
+    WrongSession(u64),
+}
+
+impl fmt::Display for CardProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ProofVerificationError(_) => f.write_str("Failed to verify proof"),
+            Self::IoError(a0) => write!(f, "IoError: {}", a0),
+            Self::ClaimNotSatisfied => f.write_str("Claim does not hold for the given hand"),
+            Self::UnknownPlayer(a0) => write!(f, "Unknown player {}", a0),
+            Self::DeckExhausted => f.write_str("No cards left in the deck"),
+            Self::NotInHand(a0, a1) => write!(
+                f,
+                "Card at position {} is not in the hand of player {}",
+                a0, a1
+            ),
+            Self::NoPendingReplacement(a0) => {
+                write!(f, "Player {} has no discard awaiting a replacement", a0)
+            }
+            Self::UnknownDiscard(a0) => write!(f, "No discard at index {}", a0),
+            Self::UnknownPlay(a0) => write!(f, "No face-down play at index {}", a0),
+            Self::KeystoreError => f.write_str("Failed to decrypt secret key"),
+            Self::NoEscrow(a0) => write!(f, "Player {} has not escrowed their key", a0),
+            Self::InvalidEscrow(a0) => write!(f, "Key escrow of player {} is malformed", a0),
+            Self::InvalidEscrowShare(a0, a1) => write!(
+                f,
+                "Escrowed share of player {} for player {} does not match the commitments",
+                a0, a1
+            ),
+            Self::NoDropoutRecorded(a0) => {
+                write!(f, "No timeout or abort recorded for player {}", a0)
+            }
+            Self::NotEnoughShares(a0, a1) => {
+                write!(f, "{} escrowed shares are needed, got {}", a0, a1)
+            }
+            Self::Disconnected(a0) => write!(f, "Lost connection to player {}", a0),
+            Self::InvalidShuffleOrder => {
+                f.write_str("Shuffle order must list every player exactly once")
+            }
+            Self::ShuffleOutOfOrder(a0, a1) => write!(
+                f,
+                "Expected a shuffle from player {}, got one from player {}",
+                a0, a1
+            ),
+            Self::MissingShuffle(a0) => write!(f, "Player {} has not shuffled the deck", a0),
+            Self::BrokenTranscript(a0) => write!(
+                f,
+                "Transcript entry {} does not extend the previous entry",
+                a0
+            ),
+            Self::UnexpectedTranscriptEntry(a0) => write!(
+                f,
+                "Transcript entry {} is not valid at this point of the game",
+                a0
+            ),
+            Self::RemaskPoolExhausted(a0, a1) => write!(
+                f,
+                "{} remask pool entries are needed, the pool holds {}",
+                a0, a1
+            ),
+            Self::BatchSizeMismatch => f.write_str("Batch inputs have different lengths"),
+            Self::InvalidProofInBatch(a0) => write!(f, "Proof {} of the batch is invalid", a0),
+            Self::MissingRevealTokens(a0, a1) => {
+                write!(f, "{} reveal tokens are needed, got {}", a0, a1)
+            }
+            Self::UnknownCard => f.write_str("Revealed card does not belong to the deck"),
+            Self::InvalidShoe(a0) => {
+                write!(f, "A shoe of {} cards does not hold whole decks", a0)
+            }
+            Self::ParticipantMismatch(a0, a1) => write!(
+                f,
+                "{} participants shuffled the deck, the table seats {}",
+                a0, a1
+            ),
+            Self::NotPlayersTurn(a0) => write!(f, "It is not the turn of player {}", a0),
+            Self::IllegalAction => {
+                f.write_str("Action is not allowed in the current state of the hand")
+            }
+            Self::RevealPending(a0) => {
+                write!(f, "Card at position {} has not been revealed yet", a0)
+            }
+            Self::NotAwaitingReveal(a0) => {
+                write!(f, "Card at position {} is not awaiting a reveal", a0)
+            }
+            Self::InvalidReveal(a0) => {
+                write!(
+                    f,
+                    "Reveal of the card at position {} does not check out",
+                    a0
+                )
+            }
+            Self::GameNotOver => f.write_str("The game is not over yet"),
+            Self::UnevenSuits(a0, a1) => {
+                write!(
+                    f,
+                    "A deck of {} cards does not split into suits of {}",
+                    a0, a1
+                )
+            }
+            Self::DeckSizeMismatch(a0, a1) => write!(
+                f,
+                "The shuffled deck holds {} cards, the game is played with {}",
+                a0, a1
+            ),
+            Self::DuplicateCard(a0, a1) => {
+                write!(f, "Cards at positions {} and {} are the same point", a0, a1)
+            }
+            Self::NotFaceDown(a0) => write!(f, "Card at position {} is not face-down", a0),
+            Self::AggregateKeyMismatch => {
+                f.write_str("The deck was shuffled under another aggregate key")
+            }
+            Self::InvalidTimeoutEvidence(a0) => {
+                write!(
+                    f,
+                    "Timeout evidence against player {} does not check out",
+                    a0
+                )
+            }
+            Self::BetTooLarge(a0) => write!(f, "The bet of seat {} is too large", a0),
+            Self::WrongSession(a0) => write!(f, "Message was signed for session {}", a0),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CardProtocolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ProofVerificationError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<CryptoError> for CardProtocolError {
+    fn from(err: CryptoError) -> Self {
+        Self::ProofVerificationError(err)
+    }
 }
 
-impl From<std::io::Error> for CardProtocolError {
-    fn from(err: std::io::Error) -> Self {
+impl From<ark_std::io::Error> for CardProtocolError {
+    fn from(err: ark_std::io::Error) -> Self {
         Self::IoError(err.to_string())
     }
 }
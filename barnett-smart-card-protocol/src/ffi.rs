@@ -0,0 +1,588 @@
+// C ABI for native clients such as game engines. The curve is fixed to Edwards on BN254, as in the
+// wasm bindings, and the functions mirror `WasmBnDlCards`.
+//
+// Parameters and keypairs are opaque handles, released with their `_free` function. Every other
+// value crosses the boundary in its canonical encoding: inputs are borrowed `MpSlice`s, outputs
+// are `MpBuffer`s allocated here and released with `mp_buffer_free`. A deck is the encoding of a
+// vector of masked cards, and a reveal token the encoding of the token, its proof and the public
+// key of the player who computed it.
+//
+// Every function returns an `MpStatus` and writes its outputs only on success. Pointers must be
+// null or valid for the duration of the call; null pointers are reported as
+// `MP_STATUS_NULL_POINTER`. Panics are caught at the boundary.
+//
+// `include/mental_poker.h` is generated from this file with `cbindgen --config cbindgen.toml`.
+
+#![allow(clippy::missing_safety_doc)]
+
+use crate::discrete_log_cards::{
+    get_card_elems, BnCardProtocol, BnMaskedCard, BnPlayerSecretKey, BnPublicKey, BnRevealToken,
+    BnScalar, BnZKProofKeyOwnership, BnZKProofMasking, BnZKProofReveal, BnZKProofShuffle,
+    EncryptedSecretKey, PreparedKey, PreparedParameters,
+};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ed_on_bn254::EdwardsProjective;
+use ark_ff::One;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use rand::thread_rng;
+use std::panic::{self, AssertUnwindSafe};
+use std::{ptr, slice};
+
+/// Outcome of a call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MpStatus {
+    Ok = 0,
+    /// A required pointer is null.
+    NullPointer = 1,
+    /// An input is not a valid encoding.
+    InvalidEncoding = 2,
+    /// A proof does not verify.
+    InvalidProof = 3,
+    /// Any other failure, e.g. a wrong password or an index out of range.
+    ProtocolError = 4,
+    /// The library panicked.
+    Panic = 5,
+}
+
+impl From<CardProtocolError> for MpStatus {
+    fn from(err: CardProtocolError) -> Self {
+        match err {
+            CardProtocolError::ProofVerificationError(_) => Self::InvalidProof,
+            CardProtocolError::IoError(_) => Self::InvalidEncoding,
+            _ => Self::ProtocolError,
+        }
+    }
+}
+
+impl From<SerializationError> for MpStatus {
+    fn from(_: SerializationError) -> Self {
+        Self::InvalidEncoding
+    }
+}
+
+/// Bytes borrowed from the caller. `data` may be null if `len` is 0.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct MpSlice {
+    pub data: *const u8,
+    pub len: usize,
+}
+
+impl MpSlice {
+    unsafe fn bytes<'a>(self) -> Result<&'a [u8], MpStatus> {
+        if self.len == 0 {
+            Ok(&[])
+        } else if self.data.is_null() {
+            Err(MpStatus::NullPointer)
+        } else {
+            Ok(slice::from_raw_parts(self.data, self.len))
+        }
+    }
+}
+
+/// Bytes allocated by the library. Release them with `mp_buffer_free`.
+#[repr(C)]
+pub struct MpBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl MpBuffer {
+    fn new(bytes: Vec<u8>) -> Self {
+        let bytes = bytes.into_boxed_slice();
+        let len = bytes.len();
+        Self {
+            data: Box::into_raw(bytes) as *mut u8,
+            len,
+        }
+    }
+}
+
+/// Protocol parameters, with the window table of the generator.
+pub struct MpParameters(PreparedParameters<EdwardsProjective>);
+
+/// The keypair of the local player.
+pub struct MpKeypair {
+    pk: BnPublicKey,
+    sk: BnPlayerSecretKey,
+}
+
+fn guard<F: FnOnce() -> Result<(), MpStatus>>(f: F) -> MpStatus {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => MpStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => MpStatus::Panic,
+    }
+}
+
+unsafe fn borrow<'a, T>(handle: *const T) -> Result<&'a T, MpStatus> {
+    handle.as_ref().ok_or(MpStatus::NullPointer)
+}
+
+unsafe fn slices<'a>(items: *const MpSlice, count: usize) -> Result<&'a [MpSlice], MpStatus> {
+    if count == 0 {
+        Ok(&[])
+    } else if items.is_null() {
+        Err(MpStatus::NullPointer)
+    } else {
+        Ok(slice::from_raw_parts(items, count))
+    }
+}
+
+fn check_out<T>(out: *mut T) -> Result<(), MpStatus> {
+    if out.is_null() {
+        Err(MpStatus::NullPointer)
+    } else {
+        Ok(())
+    }
+}
+
+unsafe fn decode<T: CanonicalDeserialize>(input: MpSlice) -> Result<T, MpStatus> {
+    Ok(T::deserialize(input.bytes()?)?)
+}
+
+fn encode<T: CanonicalSerialize>(value: &T) -> Result<MpBuffer, MpStatus> {
+    let mut bytes = Vec::new();
+    value.serialize(&mut bytes)?;
+    Ok(MpBuffer::new(bytes))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mp_buffer_free(buffer: *mut MpBuffer) {
+    if let Some(buffer) = buffer.as_mut() {
+        if !buffer.data.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                buffer.data,
+                buffer.len,
+            )));
+        }
+        buffer.data = ptr::null_mut();
+        buffer.len = 0;
+    }
+}
+
+/// Generate parameters for a deck of `m * n` cards.
+#[no_mangle]
+pub unsafe extern "C" fn mp_parameters_setup(
+    m: usize,
+    n: usize,
+    out: *mut *mut MpParameters,
+) -> MpStatus {
+    guard(|| {
+        check_out(out)?;
+        let parameters = BnCardProtocol::setup(&mut thread_rng(), m, n)?;
+        *out = Box::into_raw(Box::new(MpParameters(PreparedParameters::new(&parameters))));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mp_parameters_from_bytes(
+    bytes: MpSlice,
+    out: *mut *mut MpParameters,
+) -> MpStatus {
+    guard(|| {
+        check_out(out)?;
+        let parameters = decode(bytes)?;
+        *out = Box::into_raw(Box::new(MpParameters(PreparedParameters::new(&parameters))));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mp_parameters_to_bytes(
+    params: *const MpParameters,
+    out: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out)?;
+        *out = encode(borrow(params)?.0.parameters())?;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mp_parameters_num_cards(
+    params: *const MpParameters,
+    out: *mut usize,
+) -> MpStatus {
+    guard(|| {
+        check_out(out)?;
+        *out = borrow(params)?.0.parameters().num_cards();
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mp_parameters_free(params: *mut MpParameters) {
+    if !params.is_null() {
+        drop(Box::from_raw(params));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mp_keygen(
+    params: *const MpParameters,
+    out: *mut *mut MpKeypair,
+) -> MpStatus {
+    guard(|| {
+        check_out(out)?;
+        let params = borrow(params)?;
+        let (pk, sk) = BnCardProtocol::player_keygen(&mut thread_rng(), params.0.parameters())?;
+        *out = Box::into_raw(Box::new(MpKeypair { pk, sk }));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mp_keypair_public_key(
+    keypair: *const MpKeypair,
+    out: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out)?;
+        *out = encode(&borrow(keypair)?.pk)?;
+        Ok(())
+    })
+}
+
+/// Encode the keypair as the public key followed by the secret key encrypted under `password`.
+#[no_mangle]
+pub unsafe extern "C" fn mp_keypair_export(
+    keypair: *const MpKeypair,
+    password: MpSlice,
+    out: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out)?;
+        let keypair = borrow(keypair)?;
+        let mut bytes = Vec::new();
+        keypair.pk.serialize(&mut bytes)?;
+        let keystore = keypair.sk.encrypt(&mut thread_rng(), password.bytes()?)?;
+        bytes.extend_from_slice(&keystore.to_bytes());
+        *out = MpBuffer::new(bytes);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mp_keypair_import(
+    bytes: MpSlice,
+    password: MpSlice,
+    out: *mut *mut MpKeypair,
+) -> MpStatus {
+    guard(|| {
+        check_out(out)?;
+        let mut reader = bytes.bytes()?;
+        let pk = BnPublicKey::deserialize(&mut reader)?;
+        let sk = EncryptedSecretKey::from_bytes(reader)?.decrypt(password.bytes()?)?;
+        *out = Box::into_raw(Box::new(MpKeypair { pk, sk }));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mp_keypair_free(keypair: *mut MpKeypair) {
+    if !keypair.is_null() {
+        drop(Box::from_raw(keypair));
+    }
+}
+
+/// Prove ownership of the public key, binding the proof to the player's public info.
+#[no_mangle]
+pub unsafe extern "C" fn mp_prove_key_ownership(
+    params: *const MpParameters,
+    keypair: *const MpKeypair,
+    player_info: MpSlice,
+    out_proof: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out_proof)?;
+        let keypair = borrow(keypair)?;
+        let proof: BnZKProofKeyOwnership = BnCardProtocol::prove_key_ownership(
+            &mut thread_rng(),
+            borrow(params)?.0.parameters(),
+            &keypair.pk,
+            &keypair.sk,
+            player_info.bytes()?,
+        )
+        .map_err(CardProtocolError::from)?;
+        *out_proof = encode(&proof)?;
+        Ok(())
+    })
+}
+
+/// Verify the key ownership proofs of every player and compute the aggregate key. The i-th public
+/// key, proof and player info belong to the same player.
+#[no_mangle]
+pub unsafe extern "C" fn mp_aggregate_key(
+    params: *const MpParameters,
+    public_keys: *const MpSlice,
+    proofs: *const MpSlice,
+    player_infos: *const MpSlice,
+    num_players: usize,
+    out: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out)?;
+        let mut key_infos = Vec::with_capacity(num_players);
+        let mut key_proofs: Vec<BnZKProofKeyOwnership> = Vec::with_capacity(num_players);
+        for ((pk, proof), info) in slices(public_keys, num_players)?
+            .iter()
+            .zip(slices(proofs, num_players)?)
+            .zip(slices(player_infos, num_players)?)
+        {
+            key_infos.push((decode::<BnPublicKey>(*pk)?, info.bytes()?.to_vec()));
+            key_proofs.push(decode(*proof)?);
+        }
+
+        let shared_key = BnCardProtocol::compute_aggregate_key(
+            borrow(params)?.0.parameters(),
+            &key_infos,
+            Some(&key_proofs),
+        )?;
+        *out = encode(&shared_key)?;
+        Ok(())
+    })
+}
+
+/// The open card at `index` of the deck.
+#[no_mangle]
+pub unsafe extern "C" fn mp_card(
+    params: *const MpParameters,
+    index: usize,
+    out_card: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out_card)?;
+        let num_cards = borrow(params)?.0.parameters().num_cards();
+        let card = get_card_elems(num_cards)
+            .get(index)
+            .copied()
+            .ok_or(MpStatus::ProtocolError)?;
+        *out_card = encode(&card)?;
+        Ok(())
+    })
+}
+
+/// Position of an open card in the deck.
+#[no_mangle]
+pub unsafe extern "C" fn mp_card_index(
+    params: *const MpParameters,
+    card: MpSlice,
+    out_index: *mut usize,
+) -> MpStatus {
+    guard(|| {
+        check_out(out_index)?;
+        let num_cards = borrow(params)?.0.parameters().num_cards();
+        let card = decode(card)?;
+        *out_index = get_card_elems(num_cards)
+            .iter()
+            .position(|open| *open == card)
+            .ok_or(MpStatus::ProtocolError)?;
+        Ok(())
+    })
+}
+
+/// Mask an open card under the aggregate key, as in the initial deck.
+#[no_mangle]
+pub unsafe extern "C" fn mp_mask(
+    params: *const MpParameters,
+    shared_key: MpSlice,
+    card: MpSlice,
+    out_masked_card: *mut MpBuffer,
+    out_proof: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out_masked_card)?;
+        check_out(out_proof)?;
+        let (masked_card, proof) = BnCardProtocol::mask(
+            &mut thread_rng(),
+            borrow(params)?.0.parameters(),
+            &decode(shared_key)?,
+            &decode(card)?,
+            &BnScalar::one(),
+        )?;
+        let masked_card = encode(&masked_card)?;
+        let proof = encode(&proof)?;
+        *out_masked_card = masked_card;
+        *out_proof = proof;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn mp_verify_mask(
+    params: *const MpParameters,
+    shared_key: MpSlice,
+    card: MpSlice,
+    masked_card: MpSlice,
+    proof: MpSlice,
+) -> MpStatus {
+    guard(|| {
+        let proof: BnZKProofMasking = decode(proof)?;
+        BnCardProtocol::verify_mask(
+            borrow(params)?.0.parameters(),
+            &decode(shared_key)?,
+            &decode(card)?,
+            &decode(masked_card)?,
+            &proof,
+        )
+        .map_err(|_| MpStatus::InvalidProof)
+    })
+}
+
+/// Mask every open card under the aggregate key, yielding the deck for the first shuffle.
+#[no_mangle]
+pub unsafe extern "C" fn mp_mask_deck(
+    params: *const MpParameters,
+    shared_key: MpSlice,
+    out_deck: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out_deck)?;
+        let params = borrow(params)?;
+        let prepared_key = PreparedKey::new(&decode(shared_key)?);
+        let rng = &mut thread_rng();
+        let deck = get_card_elems(params.0.parameters().num_cards())
+            .iter()
+            .map(|card| {
+                BnCardProtocol::mask_prepared(rng, &params.0, &prepared_key, card, &BnScalar::one())
+                    .map(|(masked_card, _)| masked_card)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        *out_deck = encode(&deck)?;
+        Ok(())
+    })
+}
+
+/// The masked card at `index` of a deck.
+#[no_mangle]
+pub unsafe extern "C" fn mp_deck_card(
+    deck: MpSlice,
+    index: usize,
+    out_masked_card: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out_masked_card)?;
+        let deck: Vec<BnMaskedCard> = decode(deck)?;
+        let masked_card = deck.get(index).ok_or(MpStatus::ProtocolError)?;
+        *out_masked_card = encode(masked_card)?;
+        Ok(())
+    })
+}
+
+/// Shuffle and remask a deck with a fresh permutation and masking factors.
+#[no_mangle]
+pub unsafe extern "C" fn mp_shuffle_and_remask(
+    params: *const MpParameters,
+    shared_key: MpSlice,
+    deck: MpSlice,
+    out_deck: *mut MpBuffer,
+    out_proof: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out_deck)?;
+        check_out(out_proof)?;
+        let params = borrow(params)?;
+        let deck: Vec<BnMaskedCard> = decode(deck)?;
+        if deck.len() != params.0.parameters().num_cards() {
+            return Err(MpStatus::ProtocolError);
+        }
+
+        let rng = &mut thread_rng();
+        let permutation = Permutation::new(rng, deck.len());
+        let masking_factors: Vec<BnScalar> = sample_vector(rng, deck.len());
+        let (shuffled_deck, proof) = BnCardProtocol::shuffle_and_remask_prepared(
+            rng,
+            &params.0,
+            &PreparedKey::new(&decode(shared_key)?),
+            &deck,
+            &masking_factors,
+            &permutation,
+        )?;
+        let shuffled_deck = encode(&shuffled_deck)?;
+        let proof = encode(&proof)?;
+        *out_deck = shuffled_deck;
+        *out_proof = proof;
+        Ok(())
+    })
+}
+
+/// Check that `shuffled_deck` is a correct shuffle of `deck`.
+#[no_mangle]
+pub unsafe extern "C" fn mp_verify_shuffle(
+    params: *const MpParameters,
+    shared_key: MpSlice,
+    deck: MpSlice,
+    shuffled_deck: MpSlice,
+    proof: MpSlice,
+) -> MpStatus {
+    guard(|| {
+        let deck: Vec<BnMaskedCard> = decode(deck)?;
+        let shuffled_deck: Vec<BnMaskedCard> = decode(shuffled_deck)?;
+        let proof: BnZKProofShuffle = decode(proof)?;
+        BnCardProtocol::verify_shuffle(
+            borrow(params)?.0.parameters(),
+            &decode(shared_key)?,
+            &deck,
+            &shuffled_deck,
+            &proof,
+        )
+        .map_err(|_| MpStatus::InvalidProof)
+    })
+}
+
+/// Compute the player's reveal token for a masked card.
+#[no_mangle]
+pub unsafe extern "C" fn mp_compute_reveal_token(
+    params: *const MpParameters,
+    keypair: *const MpKeypair,
+    masked_card: MpSlice,
+    out_token: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out_token)?;
+        let keypair = borrow(keypair)?;
+        let (token, proof) = BnCardProtocol::compute_reveal_token(
+            &mut thread_rng(),
+            borrow(params)?.0.parameters(),
+            &keypair.sk,
+            &keypair.pk,
+            &decode(masked_card)?,
+        )?;
+        *out_token = encode(&(token, proof, keypair.pk))?;
+        Ok(())
+    })
+}
+
+/// Unmask a card with the reveal tokens of every player, checking their proofs.
+#[no_mangle]
+pub unsafe extern "C" fn mp_unmask(
+    params: *const MpParameters,
+    tokens: *const MpSlice,
+    num_tokens: usize,
+    masked_card: MpSlice,
+    out_card: *mut MpBuffer,
+) -> MpStatus {
+    guard(|| {
+        check_out(out_card)?;
+        let decryption_key = slices(tokens, num_tokens)?
+            .iter()
+            .map(|token| decode::<(BnRevealToken, BnZKProofReveal, BnPublicKey)>(*token))
+            .collect::<Result<Vec<_>, _>>()?;
+        let card = BnCardProtocol::unmask(
+            borrow(params)?.0.parameters(),
+            &decryption_key,
+            &decode(masked_card)?,
+            false,
+        )?;
+        *out_card = encode(&card)?;
+        Ok(())
+    })
+}
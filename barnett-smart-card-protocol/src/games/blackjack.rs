@@ -0,0 +1,762 @@
+// Blackjack with the house as one more participant of the protocol.
+//
+// The house holds a key share and shuffles the shoe like every player, so nobody, the house
+// included, knows the order of the shoe. Player cards and the dealer's up card are dealt face-up:
+// every participant publishes a reveal share for them as soon as they are dealt. The hole card is
+// revealed once every player hand is finished, after which the house draws by the fixed dealer
+// rules. Settlement checks every recorded reveal again before computing the payouts, so anyone
+// holding the table can audit the outcome.
+//
+// There is no dealer peek: when the dealer has a natural, every hand but a player natural loses
+// its whole bet, doubles and splits included.
+
+use super::semantics::check_distinct;
+use super::{open_card, CardSemantics, FrenchCard, OpenedCard, Rank, RevealShare, StandardCard};
+use crate::discrete_log_cards::{
+    Card, CertifiedDeck, DealLog, Parameters, PublicKey, TranscriptHash,
+};
+use crate::error::CardProtocolError;
+
+use ark_ec::ProjectiveCurve;
+use ark_std::{vec, vec::Vec};
+use blake2::Blake2s;
+
+/// Largest bet a seat can place, so that a doubled bet and a 3:2 payout fit in an `i64`.
+pub const MAX_BET: u64 = i64::MAX as u64 / 3;
+
+/// The open cards of a shoe of one or more French decks. The card at index `i` stands for the
+/// French card of index `i % 52`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shoe<C: ProjectiveCurve> {
+    cards: Vec<Card<C>>,
+}
+
+impl<C: ProjectiveCurve> Shoe<C> {
+    /// Every card of the shoe must be a distinct point, even across decks.
+    pub fn new(cards: Vec<Card<C>>) -> Result<Self, CardProtocolError> {
+        let num_decks = cards.len() / FrenchCard::DECK_SIZE;
+        if num_decks == 0 || num_decks * FrenchCard::DECK_SIZE != cards.len() {
+            return Err(CardProtocolError::InvalidShoe(cards.len()));
+        }
+        check_distinct(&cards)?;
+
+        Ok(Self { cards })
+    }
+
+    pub fn cards(&self) -> &[Card<C>] {
+        &self.cards
+    }
+
+    pub fn num_decks(&self) -> usize {
+        self.cards.len() / FrenchCard::DECK_SIZE
+    }
+}
+
+impl<C: ProjectiveCurve> CardSemantics<C> for Shoe<C> {
+    type GameCard = FrenchCard;
+
+    fn points(&self) -> &[Card<C>] {
+        &self.cards
+    }
+
+    fn game_card(&self, index: usize) -> Option<FrenchCard> {
+        if index < self.cards.len() {
+            FrenchCard::from_index(index % FrenchCard::DECK_SIZE)
+        } else {
+            None
+        }
+    }
+
+    /// Index of the card in the first deck of the shoe.
+    fn index(&self, game_card: &FrenchCard) -> Option<usize> {
+        Some(game_card.index())
+    }
+}
+
+/// Best blackjack total of a hand of ranks, and whether an ace counts as 11 in it.
+pub fn hand_value(ranks: &[Rank]) -> (u32, bool) {
+    let hard: u32 = ranks
+        .iter()
+        .map(|rank| u32::from(rank.number()).min(10))
+        .sum();
+    if ranks.contains(&Rank::Ace) && hard + 10 <= 21 {
+        (hard + 10, true)
+    } else {
+        (hard, false)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Hit,
+    Stand,
+    /// Double the bet on a two-card hand and take exactly one more card.
+    Double,
+    /// Split a pair into two hands with the same bet, each dealt a second card.
+    Split,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rules {
+    /// Whether the dealer draws on a soft 17.
+    pub dealer_hits_soft_17: bool,
+    /// Number of hands a seat can split into.
+    pub max_hands_per_seat: usize,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            dealer_hits_soft_17: false,
+            max_hands_per_seat: 4,
+        }
+    }
+}
+
+/// A hand played from a seat. Splitting adds a hand for the same seat right after it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hand {
+    pub seat: usize,
+    /// Deck positions of the cards, in the order they were dealt.
+    pub positions: Vec<usize>,
+    pub bet: u64,
+    pub doubled: bool,
+    /// Whether the hand comes from a split, in which case 21 on two cards is not a natural.
+    pub split: bool,
+    pub finished: bool,
+}
+
+impl Hand {
+    fn new(seat: usize, bet: u64, split: bool) -> Self {
+        Self {
+            seat,
+            positions: Vec::new(),
+            bet,
+            doubled: false,
+            split,
+            finished: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Waiting on the hand at the given index of `hands()`.
+    Player(usize),
+    /// The house reveals its hole card and draws.
+    Dealer,
+    /// Every card has been revealed and the table can be settled.
+    Over,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HandOutcome {
+    pub seat: usize,
+    pub total: u32,
+    pub bet: u64,
+    /// Amount won by the player, negative if the bet is lost.
+    pub net: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settlement {
+    pub dealer_total: u32,
+    pub hands: Vec<HandOutcome>,
+}
+
+impl Settlement {
+    /// Amount won by the house, negative if it pays out more than it collects.
+    pub fn house_net(&self) -> i128 {
+        -self.hands.iter().map(|hand| hand.net as i128).sum::<i128>()
+    }
+}
+
+/// Public state of a blackjack table. Seats are participants `0` to `n - 1` and the house is
+/// participant `n`, in the order of the keys the table is created with. Reveal proofs are checked
+/// with transcript hash `D`.
+pub struct BlackjackTable<C: ProjectiveCurve, D: TranscriptHash = Blake2s> {
+    rules: Rules,
+    shoe: Shoe<C>,
+    keys: Vec<PublicKey<C>>,
+    log: DealLog<C, D>,
+    hands: Vec<Hand>,
+    dealer: Vec<usize>,
+    opened: Vec<Option<OpenedCard<C>>>,
+    phase: Phase,
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> BlackjackTable<C, D> {
+    /// Seat a player for each bet, with the house last in `keys`, and deal the first two cards of
+    /// every hand from the shuffled shoe. `deck` must be the shoe masked and shuffled by every
+    /// participant, under the sum of `keys`. Bets are at most `MAX_BET`.
+    pub fn new(
+        rules: Rules,
+        shoe: Shoe<C>,
+        keys: Vec<PublicKey<C>>,
+        deck: CertifiedDeck<C>,
+        bets: &[u64],
+    ) -> Result<Self, CardProtocolError> {
+        if deck.cards().len() != shoe.cards().len() {
+            return Err(CardProtocolError::InvalidShoe(deck.cards().len()));
+        }
+        if deck.num_of_players() != keys.len() || keys.len() != bets.len() + 1 {
+            return Err(CardProtocolError::ParticipantMismatch(
+                deck.num_of_players(),
+                bets.len() + 1,
+            ));
+        }
+        deck.check_keys(&keys)?;
+        if let Some(seat) = bets.iter().position(|bet| *bet > MAX_BET) {
+            return Err(CardProtocolError::BetTooLarge(seat));
+        }
+
+        let num_cards = deck.cards().len();
+        let mut table = Self {
+            rules,
+            shoe,
+            keys,
+            log: DealLog::new(deck),
+            hands: bets
+                .iter()
+                .enumerate()
+                .map(|(seat, bet)| Hand::new(seat, *bet, false))
+                .collect(),
+            dealer: Vec::new(),
+            opened: vec![None; num_cards],
+            phase: Phase::Player(0),
+        };
+
+        for _ in 0..2 {
+            for hand in 0..table.hands.len() {
+                table.deal_to_hand(hand)?;
+            }
+            table.deal_to_dealer()?;
+        }
+
+        Ok(table)
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn shoe(&self) -> &Shoe<C> {
+        &self.shoe
+    }
+
+    /// Participant index of the house.
+    pub fn house(&self) -> usize {
+        self.keys.len() - 1
+    }
+
+    pub fn log(&self) -> &DealLog<C, D> {
+        &self.log
+    }
+
+    pub fn hands(&self) -> &[Hand] {
+        &self.hands
+    }
+
+    /// Deck positions of the dealer's cards: the up card, the hole card, then the draws.
+    pub fn dealer_positions(&self) -> &[usize] {
+        &self.dealer
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn opened(&self, position: usize) -> Option<&OpenedCard<C>> {
+        self.opened.get(position).and_then(Option::as_ref)
+    }
+
+    /// Card at `position`, once it has been revealed.
+    pub fn card(&self, position: usize) -> Option<FrenchCard> {
+        self.opened(position)
+            .and_then(|opened| self.shoe.decode(&opened.card).ok())
+    }
+
+    /// Dealt positions every participant must now publish a reveal share for. The hole card is
+    /// only included once the players are done.
+    pub fn pending_reveals(&self) -> Vec<usize> {
+        let hole_card = self.dealer.get(1).copied();
+        self.hands
+            .iter()
+            .flat_map(|hand| hand.positions.iter())
+            .chain(self.dealer.iter())
+            .copied()
+            .filter(|position| self.opened[*position].is_none())
+            .filter(|position| {
+                Some(*position) != hole_card || !matches!(self.phase, Phase::Player(_))
+            })
+            .collect()
+    }
+
+    /// Open the card at `position` with the shares of every participant, in participant order,
+    /// and move the game on. Returns the card.
+    pub fn reveal(
+        &mut self,
+        pp: &Parameters<C>,
+        position: usize,
+        shares: Vec<RevealShare<C>>,
+    ) -> Result<FrenchCard, CardProtocolError> {
+        if !self.pending_reveals().contains(&position) {
+            return Err(CardProtocolError::NotAwaitingReveal(position));
+        }
+
+        let card = self.open(pp, position, &shares)?;
+        let game_card = self.shoe.decode(&card)?;
+        self.opened[position] = Some(OpenedCard {
+            position,
+            card,
+            shares,
+        });
+        self.advance()?;
+
+        Ok(game_card)
+    }
+
+    /// Play `action` for the hand whose turn it is, which must belong to `seat`.
+    pub fn act(&mut self, seat: usize, action: Action) -> Result<(), CardProtocolError> {
+        let index = match self.phase {
+            Phase::Player(index) => index,
+            _ => return Err(CardProtocolError::NotPlayersTurn(seat)),
+        };
+        if self.hands[index].seat != seat {
+            return Err(CardProtocolError::NotPlayersTurn(seat));
+        }
+        if let Some(position) = self.hands[index]
+            .positions
+            .iter()
+            .find(|position| self.opened[**position].is_none())
+        {
+            return Err(CardProtocolError::RevealPending(*position));
+        }
+
+        match action {
+            Action::Hit => {
+                self.deal_to_hand(index)?;
+            }
+            Action::Stand => {
+                self.hands[index].finished = true;
+            }
+            Action::Double => {
+                if self.hands[index].positions.len() != 2 {
+                    return Err(CardProtocolError::IllegalAction);
+                }
+                self.deal_to_hand(index)?;
+                let hand = &mut self.hands[index];
+                hand.bet *= 2;
+                hand.doubled = true;
+            }
+            Action::Split => {
+                let hand = &self.hands[index];
+                let seat_hands = self.hands.iter().filter(|hand| hand.seat == seat).count();
+                if hand.positions.len() != 2
+                    || self.rank(hand.positions[0]) != self.rank(hand.positions[1])
+                    || seat_hands >= self.rules.max_hands_per_seat
+                {
+                    return Err(CardProtocolError::IllegalAction);
+                }
+                if self.log.remaining() < 2 {
+                    return Err(CardProtocolError::DeckExhausted);
+                }
+
+                let mut second = Hand::new(seat, hand.bet, true);
+                second
+                    .positions
+                    .push(self.hands[index].positions.pop().unwrap());
+                self.hands[index].split = true;
+                self.hands.insert(index + 1, second);
+                self.deal_to_hand(index)?;
+                self.deal_to_hand(index + 1)?;
+            }
+        }
+
+        self.advance()
+    }
+
+    /// Check every reveal again and compute the payouts. Only possible once the game is over.
+    pub fn settle(&self, pp: &Parameters<C>) -> Result<Settlement, CardProtocolError> {
+        if self.phase != Phase::Over {
+            return Err(CardProtocolError::GameNotOver);
+        }
+
+        for opened in self.opened.iter().flatten() {
+            if self.open(pp, opened.position, &opened.shares)? != opened.card {
+                return Err(CardProtocolError::InvalidReveal(opened.position));
+            }
+        }
+
+        let (dealer_total, _) = self.value(&self.dealer)?;
+        let dealer_natural = self.dealer.len() == 2 && dealer_total == 21;
+
+        let hands = self
+            .hands
+            .iter()
+            .map(|hand| {
+                let (total, _) = self.value(&hand.positions)?;
+                let natural = !hand.split && hand.positions.len() == 2 && total == 21;
+                let bet = hand.bet as i64;
+                let net = if total > 21 {
+                    -bet
+                } else if natural && !dealer_natural {
+                    bet * 3 / 2
+                } else if dealer_natural {
+                    if natural {
+                        0
+                    } else {
+                        -bet
+                    }
+                } else if dealer_total > 21 || total > dealer_total {
+                    bet
+                } else if total == dealer_total {
+                    0
+                } else {
+                    -bet
+                };
+
+                Ok(HandOutcome {
+                    seat: hand.seat,
+                    total,
+                    bet: hand.bet,
+                    net,
+                })
+            })
+            .collect::<Result<Vec<_>, CardProtocolError>>()?;
+
+        Ok(Settlement {
+            dealer_total,
+            hands,
+        })
+    }
+
+    fn rank(&self, position: usize) -> Option<Rank> {
+        self.card(position).map(|card| card.rank)
+    }
+
+    fn open(
+        &self,
+        pp: &Parameters<C>,
+        position: usize,
+        shares: &[RevealShare<C>],
+    ) -> Result<Card<C>, CardProtocolError> {
+        let masked_card = self
+            .log
+            .card(position)
+            .ok_or(CardProtocolError::InvalidReveal(position))?;
+
+        open_card::<C, D>(pp, &self.keys, masked_card, shares)
+    }
+
+    /// Value of the revealed cards at `positions`.
+    fn value(&self, positions: &[usize]) -> Result<(u32, bool), CardProtocolError> {
+        let ranks = positions
+            .iter()
+            .map(|position| {
+                self.rank(*position)
+                    .ok_or(CardProtocolError::RevealPending(*position))
+            })
+            .collect::<Result<Vec<_>, CardProtocolError>>()?;
+
+        Ok(hand_value(&ranks))
+    }
+
+    fn deal_to_hand(&mut self, index: usize) -> Result<(), CardProtocolError> {
+        let position = self.log.deal(self.hands[index].seat)?;
+        self.hands[index].positions.push(position);
+
+        Ok(())
+    }
+
+    fn deal_to_dealer(&mut self) -> Result<(), CardProtocolError> {
+        let position = self.log.deal(self.house())?;
+        self.dealer.push(position);
+
+        Ok(())
+    }
+
+    /// Finish the hands that cannot take more cards and apply the dealer rules, until the table
+    /// waits on a reveal or on a player.
+    fn advance(&mut self) -> Result<(), CardProtocolError> {
+        loop {
+            match self.phase {
+                Phase::Player(index) if index == self.hands.len() => {
+                    self.phase = Phase::Dealer;
+                }
+                Phase::Player(index) => {
+                    let hand = &self.hands[index];
+                    if !hand.finished {
+                        let (total, _) = match self.value(&hand.positions) {
+                            Ok(value) => value,
+                            Err(_) => return Ok(()),
+                        };
+                        if total < 21 && !hand.doubled {
+                            return Ok(());
+                        }
+                        self.hands[index].finished = true;
+                    }
+                    self.phase = Phase::Player(index + 1);
+                }
+                Phase::Dealer => {
+                    let (total, soft) = match self.value(&self.dealer) {
+                        Ok(value) => value,
+                        Err(_) => return Ok(()),
+                    };
+                    let all_busted = self.hands.iter().all(
+                        |hand| matches!(self.value(&hand.positions), Ok((total, _)) if total > 21),
+                    );
+                    let draws =
+                        total < 17 || (total == 17 && soft && self.rules.dealer_hits_soft_17);
+                    if draws && !all_busted {
+                        return self.deal_to_dealer();
+                    }
+                    self.phase = Phase::Over;
+                }
+                Phase::Over => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Action, BlackjackTable, Phase, Rules, Shoe, MAX_BET};
+    use crate::discrete_log_cards::test_utils::setup_players;
+    use crate::discrete_log_cards::{self, ShuffleChain, ShuffleLink};
+    use crate::error::CardProtocolError;
+    use crate::games::{FrenchCard, Rank, Suit};
+    use crate::BarnettSmartProtocol;
+
+    use ark_ff::UniformRand;
+    use ark_std::rand::Rng;
+    use proof_essentials::utils::permutation::Permutation;
+    use proof_essentials::utils::rand::sample_vector;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+    type CardParameters = discrete_log_cards::Parameters<Curve>;
+    type PublicKey = discrete_log_cards::PublicKey<Curve>;
+    type SecretKey = discrete_log_cards::PlayerSecretKey<Curve>;
+
+    type Card = discrete_log_cards::Card<Curve>;
+
+    /// A single-deck table where the shoe cards at `top` are dealt first, in that order. Every
+    /// participant shuffles with the identity permutation so that the tests know the deal.
+    fn stacked_table<R: Rng>(
+        rng: &mut R,
+        rules: Rules,
+        bets: &[u64],
+        top: &[usize],
+    ) -> (
+        CardParameters,
+        Vec<(PublicKey, SecretKey)>,
+        BlackjackTable<Curve>,
+    ) {
+        let parameters = CardProtocol::setup(rng, 4, 13).unwrap();
+        let (players, aggregate_key) = setup_players(rng, &parameters, bets.len() + 1);
+        let shoe = Shoe::new((0..52).map(|_| Card::rand(rng)).collect()).unwrap();
+
+        let order = top
+            .iter()
+            .copied()
+            .chain((0..52).filter(|index| !top.contains(index)));
+        let deck = order
+            .map(|index| {
+                let alpha = Scalar::rand(rng);
+                CardProtocol::mask(
+                    rng,
+                    &parameters,
+                    &aggregate_key,
+                    &shoe.cards()[index],
+                    &alpha,
+                )
+                .unwrap()
+                .0
+            })
+            .collect::<Vec<_>>();
+
+        let mut chain = ShuffleChain::<Curve>::new(deck, (0..players.len()).collect()).unwrap();
+        for player in 0..players.len() {
+            let masking_factors: Vec<Scalar> = sample_vector(rng, 52);
+            let (shuffled_deck, proof) = CardProtocol::shuffle_and_remask(
+                rng,
+                &parameters,
+                &aggregate_key,
+                &chain.current_deck().to_vec(),
+                &masking_factors,
+                &Permutation::identity(52),
+            )
+            .unwrap();
+            let link = ShuffleLink {
+                player,
+                shuffled_deck,
+                proof,
+            };
+            chain.push(&parameters, &aggregate_key, link).unwrap();
+        }
+        let deck = chain.certify(&parameters, &aggregate_key).unwrap();
+
+        let keys: Vec<PublicKey> = players.iter().map(|(pk, _)| *pk).collect();
+
+        // The shoe was shuffled under the keys of the participants
+        let mut strangers = keys.clone();
+        strangers[0] = CardProtocol::player_keygen(rng, &parameters).unwrap().0;
+        assert_eq!(
+            BlackjackTable::<Curve>::new(rules, shoe.clone(), strangers, deck.clone(), bets).err(),
+            Some(CardProtocolError::AggregateKeyMismatch)
+        );
+
+        // Bets must leave room for doubling and a 3:2 payout
+        let mut huge_bets = bets.to_vec();
+        huge_bets[bets.len() - 1] = MAX_BET + 1;
+        assert_eq!(
+            BlackjackTable::<Curve>::new(
+                rules,
+                shoe.clone(),
+                keys.clone(),
+                deck.clone(),
+                &huge_bets
+            )
+            .err(),
+            Some(CardProtocolError::BetTooLarge(bets.len() - 1))
+        );
+
+        let table = BlackjackTable::<Curve>::new(rules, shoe, keys, deck, bets).unwrap();
+
+        (parameters, players, table)
+    }
+
+    /// Every participant publishes a reveal share for each pending position.
+    fn reveal_pending<R: Rng>(
+        rng: &mut R,
+        parameters: &CardParameters,
+        players: &[(PublicKey, SecretKey)],
+        table: &mut BlackjackTable<Curve>,
+    ) {
+        while let Some(position) = table.pending_reveals().first().copied() {
+            let masked_card = *table.log().card(position).unwrap();
+            let shares = players
+                .iter()
+                .map(|(pk, sk)| {
+                    CardProtocol::compute_reveal_token(rng, parameters, sk, pk, &masked_card)
+                        .unwrap()
+                })
+                .collect();
+            table.reveal(parameters, position, shares).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_split_double_and_dealer_draw() {
+        let rng = &mut thread_rng();
+
+        // Shoe index `i` is the French card of index `i`, of rank `i % 13 + 1`. Seat 0 gets a pair
+        // of eights, seat 1 a five and a six, the dealer a ten up and a six in the hole.
+        let top = [7, 4, 9, 20, 5, 18, 2, 22, 8, 12, 15];
+        let (parameters, players, mut table) =
+            stacked_table(rng, Rules::default(), &[10, 10], &top);
+        assert_eq!(table.house(), 2);
+        assert_eq!(table.pending_reveals(), vec![0, 3, 1, 4, 2]);
+
+        // Nobody acts before the cards are on the table
+        assert_eq!(
+            table.act(0, Action::Hit),
+            Err(CardProtocolError::RevealPending(0))
+        );
+
+        // A reveal needs a share from every participant
+        let masked_card = *table.log().card(0).unwrap();
+        let (pk, sk) = &players[0];
+        let share =
+            CardProtocol::compute_reveal_token(rng, &parameters, sk, pk, &masked_card).unwrap();
+        assert_eq!(
+            table.reveal(&parameters, 0, vec![share]),
+            Err(CardProtocolError::MissingRevealTokens(3, 1))
+        );
+        assert_eq!(
+            table.reveal(&parameters, 5, vec![share, share, share]),
+            Err(CardProtocolError::NotAwaitingReveal(5))
+        );
+
+        reveal_pending(rng, &parameters, &players, &mut table);
+        assert_eq!(table.phase(), Phase::Player(0));
+        assert_eq!(table.card(2), Some(FrenchCard::new(Rank::Ten, Suit::Clubs)));
+        assert_eq!(table.card(5), None);
+
+        assert_eq!(
+            table.act(1, Action::Stand),
+            Err(CardProtocolError::NotPlayersTurn(1))
+        );
+        assert_eq!(
+            table.settle(&parameters),
+            Err(CardProtocolError::GameNotOver)
+        );
+
+        // Seat 0 splits the eights and draws a three and a ten
+        table.act(0, Action::Split).unwrap();
+        assert_eq!(table.hands()[0].positions, vec![0, 6]);
+        assert_eq!(table.hands()[1].positions, vec![3, 7]);
+        reveal_pending(rng, &parameters, &players, &mut table);
+
+        // Doubles on 11 and gets a nine, then stands on 18
+        table.act(0, Action::Double).unwrap();
+        reveal_pending(rng, &parameters, &players, &mut table);
+        assert_eq!(table.phase(), Phase::Player(1));
+        table.act(0, Action::Stand).unwrap();
+
+        // Seat 1 cannot split a five and a six, doubles and gets a king
+        assert_eq!(
+            table.act(1, Action::Split),
+            Err(CardProtocolError::IllegalAction)
+        );
+        table.act(1, Action::Double).unwrap();
+        reveal_pending(rng, &parameters, &players, &mut table);
+
+        // The dealer turns a six over and draws a three to 19
+        assert_eq!(table.phase(), Phase::Over);
+        assert_eq!(table.dealer_positions(), &[2, 5, 10]);
+
+        let settlement = table.settle(&parameters).unwrap();
+        assert_eq!(settlement.dealer_total, 19);
+        let results = settlement
+            .hands
+            .iter()
+            .map(|hand| (hand.seat, hand.total, hand.bet, hand.net))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            vec![(0, 20, 20, 20), (0, 18, 10, -10), (1, 21, 20, 20)]
+        );
+        assert_eq!(settlement.house_net(), -30);
+    }
+
+    #[test]
+    fn test_natural_and_soft_17() {
+        let rng = &mut thread_rng();
+
+        // The seat gets an ace and a king, the dealer a six up and an ace in the hole
+        let top = [0, 5, 12, 13, 9];
+        for (dealer_hits_soft_17, dealer_cards) in [(false, 2), (true, 3)] {
+            let rules = Rules {
+                dealer_hits_soft_17,
+                ..Rules::default()
+            };
+            let (parameters, players, mut table) = stacked_table(rng, rules, &[10], &top);
+
+            // The natural finishes the hand, so the hole card is revealed right away
+            reveal_pending(rng, &parameters, &players, &mut table);
+            assert_eq!(table.phase(), Phase::Over);
+            assert_eq!(table.dealer_positions().len(), dealer_cards);
+
+            let settlement = table.settle(&parameters).unwrap();
+            assert_eq!(settlement.dealer_total, 17);
+            assert_eq!(settlement.hands[0].net, 15);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// Card games built on top of `DLCards`.
+//
+// A game keeps the public state of a table. Every participant feeds it the same messages, so they
+// all agree on whose turn it is and on which cards must be revealed next, and every message is
+// verified before it changes the state.
+
+use crate::discrete_log_cards::{
+    Card, DLCards, MaskedCard, Parameters, PublicKey, RevealToken, TranscriptHash,
+};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::ProjectiveCurve;
+use ark_std::vec::Vec;
+
+pub mod blackjack;
+mod semantics;
+pub mod tricks;
+
+pub use semantics::*;
+
+/// A participant's reveal token for a masked card, with its proof.
+pub type RevealShare<C> = (
+    RevealToken<C>,
+    <DLCards<C> as BarnettSmartProtocol>::ZKProofReveal,
+);
+
+/// A reveal share with the key of the participant it comes from, as taken by `unmask`.
+pub type KeyedRevealShare<C> = (
+    RevealToken<C>,
+    <DLCards<C> as BarnettSmartProtocol>::ZKProofReveal,
+    PublicKey<C>,
+);
+
+/// A card revealed at the table, with the reveal shares of every participant.
+#[derive(Clone)]
+pub struct OpenedCard<C: ProjectiveCurve> {
+    pub position: usize,
+    pub card: Card<C>,
+    pub shares: Vec<RevealShare<C>>,
+}
+
+/// Open `masked_card` with one reveal share per participant, in the order of `keys`. Every share
+/// is checked against the key of the participant it comes from, with transcript hash `D`.
+pub fn open_card<C: ProjectiveCurve, D: TranscriptHash>(
+    pp: &Parameters<C>,
+    keys: &[PublicKey<C>],
+    masked_card: &MaskedCard<C>,
+    shares: &[RevealShare<C>],
+) -> Result<Card<C>, CardProtocolError> {
+    if shares.len() != keys.len() {
+        return Err(CardProtocolError::MissingRevealTokens(
+            keys.len(),
+            shares.len(),
+        ));
+    }
+
+    let decryption_key = shares
+        .iter()
+        .zip(keys)
+        .map(|((token, proof), pk)| (*token, *proof, *pk))
+        .collect::<Vec<_>>();
+
+    DLCards::<C, D>::unmask(pp, &decryption_key, masked_card, false)
+}
@@ -0,0 +1,516 @@
+// Meaning of the open cards of a deck.
+//
+// The protocol only deals in curve points. A `CardSemantics` implementation fixes which point
+// stands for which game card, so opened cards can be decoded into domain values and game cards
+// encoded back into the points to mask. The standard decks map the `i`-th point to the card of
+// index `i` in a fixed order, see `StandardCard::index`.
+
+use super::KeyedRevealShare;
+use crate::discrete_log_cards::{Card, DLCards, MaskedCard, Parameters, TranscriptHash};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::ProjectiveCurve;
+use ark_ff::UniformRand;
+use ark_std::fmt::{self, Debug};
+use ark_std::marker::PhantomData;
+use ark_std::rand::Rng;
+use ark_std::vec::Vec;
+
+pub trait CardSemantics<C: ProjectiveCurve> {
+    type GameCard: Clone + Debug + PartialEq;
+
+    /// Open cards of the deck, in the order of their indices.
+    fn points(&self) -> &[Card<C>];
+
+    /// Game card at `index` in the deck.
+    fn game_card(&self, index: usize) -> Option<Self::GameCard>;
+
+    /// Index of the point standing for `game_card`.
+    fn index(&self, game_card: &Self::GameCard) -> Option<usize>;
+
+    fn decode(&self, card: &Card<C>) -> Result<Self::GameCard, CardProtocolError> {
+        self.points()
+            .iter()
+            .position(|point| point == card)
+            .and_then(|index| self.game_card(index))
+            .ok_or(CardProtocolError::UnknownCard)
+    }
+
+    fn encode(&self, game_card: &Self::GameCard) -> Result<Card<C>, CardProtocolError> {
+        self.index(game_card)
+            .and_then(|index| self.points().get(index))
+            .copied()
+            .ok_or(CardProtocolError::UnknownCard)
+    }
+
+    /// Unmask a card with the reveal tokens of every player and decode it. The reveal proofs are
+    /// checked with transcript hash `D`.
+    fn unmask<D: TranscriptHash>(
+        &self,
+        pp: &Parameters<C>,
+        decryption_key: &[KeyedRevealShare<C>],
+        masked_card: &MaskedCard<C>,
+    ) -> Result<Self::GameCard, CardProtocolError> {
+        let card = DLCards::<C, D>::unmask(pp, &decryption_key.to_vec(), masked_card, false)?;
+        self.decode(&card)
+    }
+}
+
+/// Fail with the positions of the first repeated point, which could not be decoded unambiguously.
+pub(crate) fn check_distinct<C: ProjectiveCurve>(
+    points: &[Card<C>],
+) -> Result<(), CardProtocolError> {
+    for (i, point) in points.iter().enumerate() {
+        if let Some(j) = points[..i].iter().position(|other| other == point) {
+            return Err(CardProtocolError::DuplicateCard(j, i));
+        }
+    }
+
+    Ok(())
+}
+
+/// A card of a standard deck, numbered from 0 to `DECK_SIZE - 1`.
+pub trait StandardCard: Clone + Debug + PartialEq + Sized {
+    const DECK_SIZE: usize;
+
+    fn from_index(index: usize) -> Option<Self>;
+
+    /// Index of the card. Only meaningful for cards of the deck, i.e. if `from_index` maps it
+    /// back to the card.
+    fn index(&self) -> usize;
+}
+
+/// A standard deck of `G` cards, with one point per card.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StandardDeck<C: ProjectiveCurve, G: StandardCard> {
+    points: Vec<Card<C>>,
+    _game_card: PhantomData<G>,
+}
+
+pub type FrenchDeck<C> = StandardDeck<C, FrenchCard>;
+pub type JokerDeck<C> = StandardDeck<C, JokerCard>;
+pub type SpanishDeck<C> = StandardDeck<C, SpanishCard>;
+pub type TarotDeck<C> = StandardDeck<C, TarotCard>;
+
+impl<C: ProjectiveCurve, G: StandardCard> StandardDeck<C, G> {
+    /// Use `points` as the open cards of the deck. Point `i` stands for the card of index `i`.
+    pub fn new(points: Vec<Card<C>>) -> Result<Self, CardProtocolError> {
+        if points.len() != G::DECK_SIZE {
+            return Err(CardProtocolError::DeckSizeMismatch(
+                points.len(),
+                G::DECK_SIZE,
+            ));
+        }
+        check_distinct(&points)?;
+
+        Ok(Self {
+            points,
+            _game_card: PhantomData,
+        })
+    }
+
+    /// A deck with random points.
+    pub fn rand<R: Rng>(rng: &mut R) -> Self {
+        Self {
+            points: (0..G::DECK_SIZE).map(|_| Card::rand(rng)).collect(),
+            _game_card: PhantomData,
+        }
+    }
+}
+
+impl<C: ProjectiveCurve, G: StandardCard> CardSemantics<C> for StandardDeck<C, G> {
+    type GameCard = G;
+
+    fn points(&self) -> &[Card<C>] {
+        &self.points
+    }
+
+    fn game_card(&self, index: usize) -> Option<G> {
+        G::from_index(index)
+    }
+
+    fn index(&self, game_card: &G) -> Option<usize> {
+        let index = game_card.index();
+        if G::from_index(index).as_ref() == Some(game_card) {
+            Some(index)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl Suit {
+    pub const ALL: [Self; 4] = [Self::Clubs, Self::Diamonds, Self::Hearts, Self::Spades];
+}
+
+impl fmt::Display for Suit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Clubs => "♣",
+            Self::Diamonds => "♦",
+            Self::Hearts => "♥",
+            Self::Spades => "♠",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rank {
+    Ace,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Queen,
+    King,
+}
+
+impl Rank {
+    pub const ALL: [Self; 13] = [
+        Self::Ace,
+        Self::Two,
+        Self::Three,
+        Self::Four,
+        Self::Five,
+        Self::Six,
+        Self::Seven,
+        Self::Eight,
+        Self::Nine,
+        Self::Ten,
+        Self::Jack,
+        Self::Queen,
+        Self::King,
+    ];
+
+    /// Number of the rank, from 1 for the ace to 13 for the king.
+    pub fn number(&self) -> u8 {
+        *self as u8 + 1
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ace => f.write_str("A"),
+            Self::Jack => f.write_str("J"),
+            Self::Queen => f.write_str("Q"),
+            Self::King => f.write_str("K"),
+            _ => write!(f, "{}", self.number()),
+        }
+    }
+}
+
+/// A card of the 52-card French deck, ordered by suit and then from ace to king.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FrenchCard {
+    pub rank: Rank,
+    pub suit: Suit,
+}
+
+impl FrenchCard {
+    pub fn new(rank: Rank, suit: Suit) -> Self {
+        Self { rank, suit }
+    }
+}
+
+impl StandardCard for FrenchCard {
+    const DECK_SIZE: usize = 52;
+
+    fn from_index(index: usize) -> Option<Self> {
+        let suit = *Suit::ALL.get(index / 13)?;
+        Some(Self::new(Rank::ALL[index % 13], suit))
+    }
+
+    fn index(&self) -> usize {
+        self.suit as usize * 13 + self.rank as usize
+    }
+}
+
+impl fmt::Display for FrenchCard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.rank, self.suit)
+    }
+}
+
+/// A card of the French deck with two jokers, which come after the 52 other cards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum JokerCard {
+    French(FrenchCard),
+    BlackJoker,
+    RedJoker,
+}
+
+impl StandardCard for JokerCard {
+    const DECK_SIZE: usize = 54;
+
+    fn from_index(index: usize) -> Option<Self> {
+        match index {
+            52 => Some(Self::BlackJoker),
+            53 => Some(Self::RedJoker),
+            _ => FrenchCard::from_index(index).map(Self::French),
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Self::French(card) => card.index(),
+            Self::BlackJoker => 52,
+            Self::RedJoker => 53,
+        }
+    }
+}
+
+/// Suits of the Spanish deck: oros, copas, espadas and bastos.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpanishSuit {
+    Coins,
+    Cups,
+    Swords,
+    Clubs,
+}
+
+impl SpanishSuit {
+    pub const ALL: [Self; 4] = [Self::Coins, Self::Cups, Self::Swords, Self::Clubs];
+}
+
+/// Ranks of the 40-card Spanish deck, which has no eights and nines: sota, caballo and rey follow
+/// the seven.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpanishRank {
+    One,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Jack,
+    Knight,
+    King,
+}
+
+impl SpanishRank {
+    pub const ALL: [Self; 10] = [
+        Self::One,
+        Self::Two,
+        Self::Three,
+        Self::Four,
+        Self::Five,
+        Self::Six,
+        Self::Seven,
+        Self::Jack,
+        Self::Knight,
+        Self::King,
+    ];
+}
+
+/// A card of the 40-card Spanish deck, ordered by suit and then by rank.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SpanishCard {
+    pub rank: SpanishRank,
+    pub suit: SpanishSuit,
+}
+
+impl StandardCard for SpanishCard {
+    const DECK_SIZE: usize = 40;
+
+    fn from_index(index: usize) -> Option<Self> {
+        let suit = *SpanishSuit::ALL.get(index / 10)?;
+        Some(Self {
+            rank: SpanishRank::ALL[index % 10],
+            suit,
+        })
+    }
+
+    fn index(&self) -> usize {
+        self.suit as usize * 10 + self.rank as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TarotRank {
+    One,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Knight,
+    Queen,
+    King,
+}
+
+impl TarotRank {
+    pub const ALL: [Self; 14] = [
+        Self::One,
+        Self::Two,
+        Self::Three,
+        Self::Four,
+        Self::Five,
+        Self::Six,
+        Self::Seven,
+        Self::Eight,
+        Self::Nine,
+        Self::Ten,
+        Self::Jack,
+        Self::Knight,
+        Self::Queen,
+        Self::King,
+    ];
+}
+
+/// A card of the 78-card tarot deck: the 56 suited cards, then the trumps from 1 to 21, then the
+/// excuse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TarotCard {
+    Suited {
+        rank: TarotRank,
+        suit: Suit,
+    },
+    /// A trump, numbered from 1 to 21.
+    Trump(u8),
+    Excuse,
+}
+
+impl StandardCard for TarotCard {
+    const DECK_SIZE: usize = 78;
+
+    fn from_index(index: usize) -> Option<Self> {
+        match index {
+            0..=55 => Some(Self::Suited {
+                rank: TarotRank::ALL[index % 14],
+                suit: Suit::ALL[index / 14],
+            }),
+            56..=76 => Some(Self::Trump((index - 55) as u8)),
+            77 => Some(Self::Excuse),
+            _ => None,
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Self::Suited { rank, suit } => *suit as usize * 14 + *rank as usize,
+            Self::Trump(number) => 55 + *number as usize,
+            Self::Excuse => 77,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        CardSemantics, FrenchCard, FrenchDeck, JokerCard, Rank, SpanishCard, StandardCard,
+        StandardDeck, Suit, TarotCard, TarotDeck,
+    };
+    use crate::discrete_log_cards::{self, Card};
+    use crate::error::CardProtocolError;
+    use crate::BarnettSmartProtocol;
+
+    use ark_ff::{One, UniformRand};
+    use blake2::Blake2s;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+
+    fn check_round_trip<G: StandardCard>() {
+        let rng = &mut thread_rng();
+        let deck = StandardDeck::<Curve, G>::rand(rng);
+
+        for index in 0..G::DECK_SIZE {
+            let game_card = deck.game_card(index).unwrap();
+            assert_eq!(game_card.index(), index);
+            let card = deck.encode(&game_card).unwrap();
+            assert_eq!(card, deck.points()[index]);
+            assert_eq!(deck.decode(&card), Ok(game_card));
+        }
+        assert_eq!(deck.game_card(G::DECK_SIZE), None);
+    }
+
+    #[test]
+    fn test_standard_decks() {
+        check_round_trip::<FrenchCard>();
+        check_round_trip::<JokerCard>();
+        check_round_trip::<SpanishCard>();
+        check_round_trip::<TarotCard>();
+
+        assert_eq!(
+            FrenchCard::from_index(51),
+            Some(FrenchCard::new(Rank::King, Suit::Spades))
+        );
+        assert_eq!(FrenchCard::new(Rank::Ten, Suit::Hearts).to_string(), "10♥");
+        assert_eq!(JokerCard::from_index(53), Some(JokerCard::RedJoker));
+        assert_eq!(TarotCard::from_index(56), Some(TarotCard::Trump(1)));
+        assert_eq!(TarotCard::from_index(76), Some(TarotCard::Trump(21)));
+
+        // Trumps out of 1..=21 are not cards of the deck, even though their index is in range
+        let rng = &mut thread_rng();
+        let tarot = TarotDeck::<Curve>::rand(rng);
+        for trump in [0, 22] {
+            assert_eq!(
+                tarot.encode(&TarotCard::Trump(trump)),
+                Err(CardProtocolError::UnknownCard)
+            );
+        }
+
+        // A repeated point would decode to two different cards
+        let mut points = FrenchDeck::<Curve>::rand(rng).points().to_vec();
+        points[40] = points[7];
+        assert_eq!(
+            FrenchDeck::<Curve>::new(points),
+            Err(CardProtocolError::DuplicateCard(7, 40))
+        );
+    }
+
+    #[test]
+    fn test_decode_unmasked_card() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 4, 13).unwrap();
+        let (pk, sk) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+        let deck = FrenchDeck::<Curve>::rand(rng);
+
+        let queen = FrenchCard::new(Rank::Queen, Suit::Diamonds);
+        let card = deck.encode(&queen).unwrap();
+        let (masked_card, _) =
+            CardProtocol::mask(rng, &parameters, &pk, &card, &Scalar::one()).unwrap();
+        let (token, proof) =
+            CardProtocol::compute_reveal_token(rng, &parameters, &sk, &pk, &masked_card).unwrap();
+        assert_eq!(
+            deck.unmask::<Blake2s>(&parameters, &[(token, proof, pk)], &masked_card),
+            Ok(queen)
+        );
+
+        // A point that is not in the deck cannot be decoded
+        let stranger = Card::rand(rng);
+        assert_eq!(deck.decode(&stranger), Err(CardProtocolError::UnknownCard));
+
+        assert_eq!(
+            FrenchDeck::<Curve>::new(deck.points()[..40].to_vec()).err(),
+            Some(CardProtocolError::DeckSizeMismatch(40, 52))
+        );
+    }
+}
@@ -0,0 +1,706 @@
+// Trick-taking games where players must follow the led suit.
+//
+// Hands are dealt face-down. For every dealt card, each participant other than the holder
+// publishes a verified reveal share, which lets the holder peek at the card with their own token
+// while it stays hidden from everyone else. A play then only needs the holder's share to open the
+// card face-up, and the reveal proofs tie the opened card to the position dealt to that player.
+//
+// Nobody can tell whether a player follows suit while their hand is hidden, so the rule is checked
+// once every card has been played: a player who did not follow the led suit must not have played a
+// card of that suit in a later trick. The audit reports the first renege with both opened cards.
+
+use super::semantics::check_distinct;
+use super::{
+    open_card, CardSemantics, FrenchCard, FrenchDeck, OpenedCard, Rank, RevealShare, Suit,
+};
+use crate::discrete_log_cards::{
+    Card, CardState, CertifiedDeck, DLCards, DealLog, Parameters, PublicKey, TranscriptHash,
+};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ec::ProjectiveCurve;
+use ark_std::{vec, vec::Vec};
+use blake2::Blake2s;
+
+/// The open cards of a deck split into suits of equal size. The card at index `i` is of suit
+/// `i / suit_size` and has rank `i % suit_size`, higher ranks winning tricks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SuitedDeck<C: ProjectiveCurve> {
+    cards: Vec<Card<C>>,
+    suit_size: usize,
+}
+
+impl<C: ProjectiveCurve> SuitedDeck<C> {
+    /// Every card of the deck must be a distinct point.
+    pub fn new(cards: Vec<Card<C>>, suit_size: usize) -> Result<Self, CardProtocolError> {
+        if suit_size == 0 || cards.is_empty() || cards.len() / suit_size * suit_size != cards.len()
+        {
+            return Err(CardProtocolError::UnevenSuits(cards.len(), suit_size));
+        }
+        check_distinct(&cards)?;
+
+        Ok(Self { cards, suit_size })
+    }
+
+    pub fn cards(&self) -> &[Card<C>] {
+        &self.cards
+    }
+
+    pub fn num_suits(&self) -> usize {
+        self.cards.len() / self.suit_size
+    }
+
+    /// The cards of a French deck, with the ace above the king in every suit.
+    pub fn from_french(deck: &FrenchDeck<C>) -> Self {
+        let ace_high = Rank::ALL[1..].iter().chain(&Rank::ALL[..1]);
+        let cards = Suit::ALL
+            .iter()
+            .flat_map(|suit| {
+                ace_high
+                    .clone()
+                    .map(move |rank| FrenchCard::new(*rank, *suit))
+            })
+            .map(|card| deck.encode(&card).unwrap())
+            .collect();
+
+        Self {
+            cards,
+            suit_size: 13,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SuitedCard {
+    pub suit: usize,
+    pub rank: usize,
+}
+
+impl<C: ProjectiveCurve> CardSemantics<C> for SuitedDeck<C> {
+    type GameCard = SuitedCard;
+
+    fn points(&self) -> &[Card<C>] {
+        &self.cards
+    }
+
+    fn game_card(&self, index: usize) -> Option<SuitedCard> {
+        if index < self.cards.len() {
+            Some(SuitedCard {
+                suit: index / self.suit_size,
+                rank: index % self.suit_size,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn index(&self, game_card: &SuitedCard) -> Option<usize> {
+        if game_card.rank < self.suit_size && game_card.suit < self.num_suits() {
+            Some(game_card.suit * self.suit_size + game_card.rank)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rules {
+    /// Suit beating every other suit, if any.
+    pub trump: Option<usize>,
+    /// Player leading the first trick.
+    pub first_leader: usize,
+}
+
+/// A card played face-up, with its suit and rank.
+#[derive(Clone)]
+pub struct Play<C: ProjectiveCurve> {
+    pub player: usize,
+    pub suit: usize,
+    pub rank: usize,
+    pub card: OpenedCard<C>,
+}
+
+#[derive(Clone)]
+pub struct Trick<C: ProjectiveCurve> {
+    pub leader: usize,
+    pub plays: Vec<Play<C>>,
+    /// Player taking the trick, once everybody has played to it.
+    pub winner: Option<usize>,
+}
+
+impl<C: ProjectiveCurve> Trick<C> {
+    /// Suit of the first card of the trick.
+    pub fn led_suit(&self) -> Option<usize> {
+        self.plays.first().map(|play| play.suit)
+    }
+}
+
+/// A player who did not follow suit while holding a card of the led suit. `withheld` is that card,
+/// played face-up in trick `withheld_trick`.
+#[derive(Clone)]
+pub struct Renege<C: ProjectiveCurve> {
+    pub trick: usize,
+    pub player: usize,
+    pub led_suit: usize,
+    pub played: OpenedCard<C>,
+    pub withheld: OpenedCard<C>,
+    pub withheld_trick: usize,
+}
+
+/// Public state of one hand of a trick-taking game. Every participant is a player, in the order of
+/// the keys the table is created with. Reveal proofs are checked with transcript hash `D`.
+pub struct TrickTable<C: ProjectiveCurve, D: TranscriptHash = Blake2s> {
+    rules: Rules,
+    deck: SuitedDeck<C>,
+    keys: Vec<PublicKey<C>>,
+    log: DealLog<C, D>,
+    /// Peek shares by position, one slot per participant. The holder's own slot stays empty.
+    peek_shares: Vec<Vec<Option<RevealShare<C>>>>,
+    played: Vec<bool>,
+    tricks: Vec<Trick<C>>,
+    hand_size: usize,
+}
+
+impl<C: ProjectiveCurve, D: TranscriptHash> TrickTable<C, D> {
+    /// Deal `hand_size` cards to every player, one at a time, from the deck shuffled by all of
+    /// them under the sum of `keys`.
+    pub fn new(
+        rules: Rules,
+        deck: SuitedDeck<C>,
+        keys: Vec<PublicKey<C>>,
+        shuffled_deck: CertifiedDeck<C>,
+        hand_size: usize,
+    ) -> Result<Self, CardProtocolError> {
+        if shuffled_deck.num_of_players() != keys.len() {
+            return Err(CardProtocolError::ParticipantMismatch(
+                shuffled_deck.num_of_players(),
+                keys.len(),
+            ));
+        }
+        shuffled_deck.check_keys(&keys)?;
+        if shuffled_deck.cards().len() != deck.cards().len() {
+            return Err(CardProtocolError::DeckSizeMismatch(
+                shuffled_deck.cards().len(),
+                deck.cards().len(),
+            ));
+        }
+        if rules.first_leader >= keys.len() {
+            return Err(CardProtocolError::UnknownPlayer(rules.first_leader));
+        }
+
+        let num_cards = deck.cards().len();
+        let mut log = DealLog::new(shuffled_deck);
+        for _ in 0..hand_size {
+            for player in 0..keys.len() {
+                log.deal(player)?;
+            }
+        }
+
+        Ok(Self {
+            rules,
+            deck,
+            peek_shares: vec![vec![None; keys.len()]; num_cards],
+            keys,
+            log,
+            played: vec![false; num_cards],
+            tricks: vec![Trick {
+                leader: rules.first_leader,
+                plays: Vec::new(),
+                winner: None,
+            }],
+            hand_size,
+        })
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn deck(&self) -> &SuitedDeck<C> {
+        &self.deck
+    }
+
+    pub fn log(&self) -> &DealLog<C, D> {
+        &self.log
+    }
+
+    pub fn hand_size(&self) -> usize {
+        self.hand_size
+    }
+
+    pub fn tricks(&self) -> &[Trick<C>] {
+        &self.tricks
+    }
+
+    /// Positions of the cards `player` has not played yet.
+    pub fn hand(&self, player: usize) -> Vec<usize> {
+        self.log
+            .hand(player)
+            .into_iter()
+            .filter(|position| !self.played[*position])
+            .collect()
+    }
+
+    /// Peek shares still missing, as (participant, position) pairs.
+    pub fn missing_peek_shares(&self) -> Vec<(usize, usize)> {
+        (0..self.keys.len())
+            .flat_map(|player| self.log.hand(player))
+            .flat_map(|position| {
+                let holder = self.holder(position);
+                self.peek_shares[position]
+                    .iter()
+                    .enumerate()
+                    .filter(move |(participant, share)| {
+                        Some(*participant) != holder && share.is_none()
+                    })
+                    .map(move |(participant, _)| (participant, position))
+            })
+            .collect()
+    }
+
+    /// Record the reveal share of `participant` for the card dealt at `position`, after checking
+    /// its proof. The holder of the card never publishes a share before playing it.
+    pub fn add_peek_share(
+        &mut self,
+        pp: &Parameters<C>,
+        participant: usize,
+        position: usize,
+        share: RevealShare<C>,
+    ) -> Result<(), CardProtocolError> {
+        let holder = self
+            .holder(position)
+            .ok_or(CardProtocolError::NotAwaitingReveal(position))?;
+        let pk = self
+            .keys
+            .get(participant)
+            .ok_or(CardProtocolError::UnknownPlayer(participant))?;
+        if participant == holder || self.peek_shares[position][participant].is_some() {
+            return Err(CardProtocolError::IllegalAction);
+        }
+
+        let masked_card = self.log.card(position).unwrap();
+        DLCards::<C, D>::verify_reveal(pp, pk, &share.0, masked_card, &share.1)?;
+        self.peek_shares[position][participant] = Some(share);
+
+        Ok(())
+    }
+
+    /// Open a card of the holder's hand with their own reveal share, which they keep to
+    /// themselves.
+    pub fn peek(
+        &self,
+        pp: &Parameters<C>,
+        position: usize,
+        own_share: RevealShare<C>,
+    ) -> Result<Card<C>, CardProtocolError> {
+        let shares = self.shares(position, own_share)?;
+        open_card::<C, D>(pp, &self.keys, self.log.card(position).unwrap(), &shares)
+    }
+
+    /// Player expected to play next, or `None` once every card has been played.
+    pub fn turn(&self) -> Option<usize> {
+        let trick = self.tricks.last().unwrap();
+        match trick.winner {
+            Some(_) => None,
+            None => Some((trick.leader + trick.plays.len()) % self.keys.len()),
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.turn().is_none()
+    }
+
+    /// Play the card at `position` face-up by publishing the holder's reveal share for it.
+    /// Following suit is not checked here, see `audit`.
+    pub fn play(
+        &mut self,
+        pp: &Parameters<C>,
+        player: usize,
+        position: usize,
+        own_share: RevealShare<C>,
+    ) -> Result<Card<C>, CardProtocolError> {
+        if self.turn() != Some(player) {
+            return Err(CardProtocolError::NotPlayersTurn(player));
+        }
+        self.log.check_held(player, position)?;
+        if self.played[position] {
+            return Err(CardProtocolError::NotInHand(position, player));
+        }
+
+        let shares = self.shares(position, own_share)?;
+        let card = open_card::<C, D>(pp, &self.keys, self.log.card(position).unwrap(), &shares)?;
+        let SuitedCard { suit, rank } = self.deck.decode(&card)?;
+
+        self.played[position] = true;
+        let num_of_players = self.keys.len();
+        let trick = self.tricks.last_mut().unwrap();
+        trick.plays.push(Play {
+            player,
+            suit,
+            rank,
+            card: OpenedCard {
+                position,
+                card,
+                shares,
+            },
+        });
+
+        if trick.plays.len() == num_of_players {
+            let winner = trick_winner(trick, self.rules.trump);
+            trick.winner = Some(winner);
+            if self.tricks.len() < self.hand_size {
+                self.tricks.push(Trick {
+                    leader: winner,
+                    plays: Vec::new(),
+                    winner: None,
+                });
+            }
+        }
+
+        Ok(card)
+    }
+
+    /// Check every play again and the follow-suit rule against the hands, which are all open by
+    /// the end of the game. Returns the first renege, if any.
+    pub fn audit(&self, pp: &Parameters<C>) -> Result<Option<Renege<C>>, CardProtocolError> {
+        if !self.is_over() {
+            return Err(CardProtocolError::GameNotOver);
+        }
+
+        for play in self.tricks.iter().flat_map(|trick| trick.plays.iter()) {
+            let opened = &play.card;
+            let masked_card = self.log.card(opened.position).unwrap();
+            if open_card::<C, D>(pp, &self.keys, masked_card, &opened.shares)? != opened.card {
+                return Err(CardProtocolError::InvalidReveal(opened.position));
+            }
+        }
+
+        for (index, trick) in self.tricks.iter().enumerate() {
+            let led_suit = match trick.led_suit() {
+                Some(suit) => suit,
+                None => continue,
+            };
+            for play in trick.plays.iter().skip(1) {
+                if play.suit == led_suit {
+                    continue;
+                }
+
+                let withheld = self
+                    .tricks
+                    .iter()
+                    .enumerate()
+                    .skip(index + 1)
+                    .flat_map(|(later, trick)| trick.plays.iter().map(move |p| (later, p)))
+                    .find(|(_, later)| later.player == play.player && later.suit == led_suit);
+                if let Some((withheld_trick, withheld)) = withheld {
+                    return Ok(Some(Renege {
+                        trick: index,
+                        player: play.player,
+                        led_suit,
+                        played: play.card.clone(),
+                        withheld: withheld.card.clone(),
+                        withheld_trick,
+                    }));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn holder(&self, position: usize) -> Option<usize> {
+        match self.log.state(position)? {
+            CardState::Held(player) => Some(player),
+            _ => None,
+        }
+    }
+
+    /// Reveal shares of every participant for `position`, with the holder's in its slot.
+    fn shares(
+        &self,
+        position: usize,
+        own_share: RevealShare<C>,
+    ) -> Result<Vec<RevealShare<C>>, CardProtocolError> {
+        let holder = self
+            .holder(position)
+            .ok_or(CardProtocolError::NotAwaitingReveal(position))?;
+
+        self.peek_shares[position]
+            .iter()
+            .enumerate()
+            .map(|(participant, share)| match share {
+                _ if participant == holder => Ok(own_share),
+                Some(share) => Ok(*share),
+                None => Err(CardProtocolError::RevealPending(position)),
+            })
+            .collect()
+    }
+}
+
+/// Highest trump in the trick, or else highest card of the led suit.
+fn trick_winner<C: ProjectiveCurve>(trick: &Trick<C>, trump: Option<usize>) -> usize {
+    let led_suit = trick.plays[0].suit;
+    let winning_suit = match trump {
+        Some(trump) if trick.plays.iter().any(|play| play.suit == trump) => trump,
+        _ => led_suit,
+    };
+
+    trick
+        .plays
+        .iter()
+        .filter(|play| play.suit == winning_suit)
+        .max_by_key(|play| play.rank)
+        .unwrap()
+        .player
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Rules, SuitedCard, SuitedDeck, TrickTable};
+    use crate::discrete_log_cards::test_utils::setup_players;
+    use crate::discrete_log_cards::{self, ShuffleChain, ShuffleLink};
+    use crate::error::CardProtocolError;
+    use crate::games::{CardSemantics, FrenchCard, FrenchDeck, Rank, RevealShare, Suit};
+    use crate::BarnettSmartProtocol;
+
+    use ark_ff::UniformRand;
+    use ark_std::rand::Rng;
+    use proof_essentials::utils::permutation::Permutation;
+    use proof_essentials::utils::rand::sample_vector;
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type Scalar = starknet_curve::Fr;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+    type CardParameters = discrete_log_cards::Parameters<Curve>;
+    type PublicKey = discrete_log_cards::PublicKey<Curve>;
+    type SecretKey = discrete_log_cards::PlayerSecretKey<Curve>;
+
+    type Card = discrete_log_cards::Card<Curve>;
+
+    /// Three players with three cards each from a deck of four suits of three cards. The deck
+    /// cards at `top` are dealt first, in that order, and every player shuffles with the identity
+    /// permutation so that the tests know the deal.
+    fn stacked_table<R: Rng>(
+        rng: &mut R,
+        rules: Rules,
+        top: &[usize],
+    ) -> (
+        CardParameters,
+        Vec<(PublicKey, SecretKey)>,
+        TrickTable<Curve>,
+    ) {
+        let parameters = CardProtocol::setup(rng, 3, 4).unwrap();
+        let (players, aggregate_key) = setup_players(rng, &parameters, 3);
+        let deck = SuitedDeck::new((0..12).map(|_| Card::rand(rng)).collect(), 3).unwrap();
+
+        let order = top
+            .iter()
+            .copied()
+            .chain((0..12).filter(|index| !top.contains(index)));
+        let masked_deck = order
+            .map(|index| {
+                let alpha = Scalar::rand(rng);
+                CardProtocol::mask(
+                    rng,
+                    &parameters,
+                    &aggregate_key,
+                    &deck.cards()[index],
+                    &alpha,
+                )
+                .unwrap()
+                .0
+            })
+            .collect::<Vec<_>>();
+
+        let mut chain = ShuffleChain::<Curve>::new(masked_deck, (0..3).collect()).unwrap();
+        for player in 0..3 {
+            let masking_factors: Vec<Scalar> = sample_vector(rng, 12);
+            let (shuffled_deck, proof) = CardProtocol::shuffle_and_remask(
+                rng,
+                &parameters,
+                &aggregate_key,
+                &chain.current_deck().to_vec(),
+                &masking_factors,
+                &Permutation::identity(12),
+            )
+            .unwrap();
+            let link = ShuffleLink {
+                player,
+                shuffled_deck,
+                proof,
+            };
+            chain.push(&parameters, &aggregate_key, link).unwrap();
+        }
+        let shuffled_deck = chain.certify(&parameters, &aggregate_key).unwrap();
+
+        let keys: Vec<PublicKey> = players.iter().map(|(pk, _)| *pk).collect();
+
+        // The deck was shuffled under the keys of the players
+        let mut strangers = keys.clone();
+        strangers[0] = CardProtocol::player_keygen(rng, &parameters).unwrap().0;
+        assert_eq!(
+            TrickTable::<Curve>::new(rules, deck.clone(), strangers, shuffled_deck.clone(), 3)
+                .err(),
+            Some(CardProtocolError::AggregateKeyMismatch)
+        );
+
+        let table = TrickTable::<Curve>::new(rules, deck, keys, shuffled_deck, 3).unwrap();
+
+        (parameters, players, table)
+    }
+
+    fn share<R: Rng>(
+        rng: &mut R,
+        parameters: &CardParameters,
+        (pk, sk): &(PublicKey, SecretKey),
+        table: &TrickTable<Curve>,
+        position: usize,
+    ) -> RevealShare<Curve> {
+        let masked_card = table.log().card(position).unwrap();
+        CardProtocol::compute_reveal_token(rng, parameters, sk, pk, masked_card).unwrap()
+    }
+
+    fn publish_peek_shares<R: Rng>(
+        rng: &mut R,
+        parameters: &CardParameters,
+        players: &[(PublicKey, SecretKey)],
+        table: &mut TrickTable<Curve>,
+    ) {
+        for (participant, position) in table.missing_peek_shares() {
+            let share = share(rng, parameters, &players[participant], table, position);
+            table
+                .add_peek_share(parameters, participant, position, share)
+                .unwrap();
+        }
+    }
+
+    fn play_all<R: Rng>(
+        rng: &mut R,
+        parameters: &CardParameters,
+        players: &[(PublicKey, SecretKey)],
+        table: &mut TrickTable<Curve>,
+        positions: &[usize],
+    ) {
+        for position in positions {
+            let player = table.turn().unwrap();
+            let share = share(rng, parameters, &players[player], table, *position);
+            table.play(parameters, player, *position, share).unwrap();
+        }
+    }
+
+    // Deck index `i` is of suit `i / 3` and rank `i % 3`. Player 0 holds positions 0, 3 and 6
+    // (0, 4, 8), player 1 positions 1, 4 and 7 (1, 5, 9), player 2 positions 2, 5 and 8 (2, 3, 10).
+    const TOP: [usize; 9] = [0, 1, 2, 4, 5, 3, 8, 9, 10];
+
+    #[test]
+    fn test_french_deck_is_ace_high() {
+        let rng = &mut thread_rng();
+        let french = FrenchDeck::<Curve>::rand(rng);
+        let deck = SuitedDeck::from_french(&french);
+        assert_eq!(deck.num_suits(), 4);
+
+        let decode = |rank, suit| {
+            let card = french.encode(&FrenchCard::new(rank, suit)).unwrap();
+            deck.decode(&card).unwrap()
+        };
+        assert_eq!(
+            decode(Rank::Ace, Suit::Spades),
+            SuitedCard { suit: 3, rank: 12 }
+        );
+        assert_eq!(
+            decode(Rank::Two, Suit::Clubs),
+            SuitedCard { suit: 0, rank: 0 }
+        );
+        assert_eq!(
+            decode(Rank::King, Suit::Hearts),
+            SuitedCard { suit: 2, rank: 11 }
+        );
+    }
+
+    #[test]
+    fn test_honest_hand() {
+        let rng = &mut thread_rng();
+        let (parameters, players, mut table) = stacked_table(rng, Rules::default(), &TOP);
+        assert_eq!(table.hand(1), vec![1, 4, 7]);
+
+        // The holder's share is only used to peek, never published
+        let own_share = share(rng, &parameters, &players[0], &table, 3);
+        assert_eq!(
+            table.add_peek_share(&parameters, 0, 3, own_share),
+            Err(CardProtocolError::IllegalAction)
+        );
+        assert_eq!(
+            table.play(&parameters, 0, 3, own_share),
+            Err(CardProtocolError::RevealPending(3))
+        );
+
+        publish_peek_shares(rng, &parameters, &players, &mut table);
+        assert!(table.missing_peek_shares().is_empty());
+        assert_eq!(
+            table.peek(&parameters, 3, own_share),
+            Ok(table.deck().cards()[4])
+        );
+
+        // Players only play their own cards, in turn
+        let share_1 = share(rng, &parameters, &players[1], &table, 1);
+        assert_eq!(
+            table.play(&parameters, 1, 1, share_1),
+            Err(CardProtocolError::NotPlayersTurn(1))
+        );
+        let share_0 = share(rng, &parameters, &players[0], &table, 1);
+        assert_eq!(
+            table.play(&parameters, 0, 1, share_0),
+            Err(CardProtocolError::NotInHand(1, 0))
+        );
+
+        play_all(rng, &parameters, &players, &mut table, &[3, 4, 5]);
+        assert_eq!(table.tricks()[0].winner, Some(1));
+        assert_eq!(table.turn(), Some(1));
+        assert_eq!(
+            table.audit(&parameters).err(),
+            Some(CardProtocolError::GameNotOver)
+        );
+
+        play_all(rng, &parameters, &players, &mut table, &[1, 2, 0, 8, 6, 7]);
+        let winners = table
+            .tricks()
+            .iter()
+            .map(|trick| trick.winner)
+            .collect::<Vec<_>>();
+        assert_eq!(winners, vec![Some(1), Some(2), Some(2)]);
+        assert!(table.is_over());
+        assert!(table.audit(&parameters).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_renege_is_reported() {
+        let rng = &mut thread_rng();
+        let rules = Rules {
+            trump: Some(3),
+            first_leader: 0,
+        };
+        let (parameters, players, mut table) = stacked_table(rng, rules, &TOP);
+        publish_peek_shares(rng, &parameters, &players, &mut table);
+
+        // Player 1 trumps the first trick while holding a card of the led suit, which they play in
+        // the last trick
+        play_all(rng, &parameters, &players, &mut table, &[3, 7, 5]);
+        assert_eq!(table.tricks()[0].winner, Some(1));
+        play_all(rng, &parameters, &players, &mut table, &[1, 2, 0, 8, 6, 4]);
+
+        let renege = table.audit(&parameters).unwrap().unwrap();
+        assert_eq!(renege.trick, 0);
+        assert_eq!(renege.player, 1);
+        assert_eq!(renege.led_suit, 1);
+        assert_eq!(renege.played.position, 7);
+        assert_eq!(renege.played.card, table.deck().cards()[9]);
+        assert_eq!(renege.withheld.position, 4);
+        assert_eq!(renege.withheld.card, table.deck().cards()[5]);
+        assert_eq!(renege.withheld_trick, 2);
+    }
+}
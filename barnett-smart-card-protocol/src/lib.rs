@@ -1,17 +1,29 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
 use crate::error::CardProtocolError;
 
 use ark_ff::{Field, ToBytes};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::hash::Hash;
+use ark_std::ops::{Add, Mul};
 use ark_std::rand::Rng;
+use ark_std::vec::Vec;
 use proof_essentials::error::CryptoError;
 use proof_essentials::homomorphic_encryption::HomomorphicEncryptionScheme;
 use proof_essentials::utils::permutation::Permutation;
 use proof_essentials::vector_commitment::HomomorphicCommitmentScheme;
-use std::hash::Hash;
-use std::ops::{Add, Mul};
 
 pub mod discrete_log_cards;
 pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod games;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+pub mod session;
+#[cfg(feature = "std")]
+pub mod transport;
 
 pub trait Mask<Scalar: Field, Enc: HomomorphicEncryptionScheme<Scalar>> {
     fn mask(
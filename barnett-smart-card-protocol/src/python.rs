@@ -0,0 +1,695 @@
+// Python bindings for simulations and for re-verifying recorded games. The curve is fixed to
+// Edwards on BN254, as in the wasm and C bindings.
+//
+// Parameters, keypairs and transcripts are Python objects. Every other value is `bytes` holding
+// its canonical encoding, so it can be stored, hashed and compared from Python; a deck is a list
+// of masked cards, and a reveal token the encoding of the token, its proof and the public key of
+// the player who computed it. Malformed bytes raise `ValueError`, protocol failures
+// `ProtocolError`, and invalid proofs its subclass `InvalidProofError`. The `verify_*` functions
+// return a bool instead.
+//
+// Build the module with `maturin build --release` and import it as `mental_poker`.
+
+use crate::discrete_log_cards::{
+    get_card_elems, BnCard, BnCardProtocol, BnMaskedCard, BnPlayerSecretKey, BnPublicKey,
+    BnRevealToken, BnScalar, BnZKProofKeyOwnership, BnZKProofMasking, BnZKProofRemasking,
+    BnZKProofReveal, BnZKProofShuffle, EncryptedSecretKey, FaceDownCard, KeyRegistration,
+    MaskedDeck, PreparedKey, PreparedParameters, PublishedReveal, ShuffleLink, Transcript,
+    TranscriptMessage,
+};
+use crate::error::CardProtocolError;
+use crate::BarnettSmartProtocol;
+
+use ark_ed_on_bn254::EdwardsProjective;
+use ark_ff::{One, UniformRand};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use proof_essentials::utils::permutation::Permutation;
+use proof_essentials::utils::rand::sample_vector;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rand::thread_rng;
+
+type Bytes<'py> = Bound<'py, PyBytes>;
+
+create_exception!(mental_poker, ProtocolError, PyException);
+create_exception!(mental_poker, InvalidProofError, ProtocolError);
+
+impl From<CardProtocolError> for PyErr {
+    fn from(err: CardProtocolError) -> Self {
+        match err {
+            CardProtocolError::ProofVerificationError(_) => {
+                InvalidProofError::new_err(err.to_string())
+            }
+            _ => ProtocolError::new_err(err.to_string()),
+        }
+    }
+}
+
+fn decode<T: CanonicalDeserialize>(bytes: &[u8]) -> PyResult<T> {
+    T::deserialize(bytes).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+fn decode_all<T: CanonicalDeserialize>(items: Vec<Bytes>) -> PyResult<Vec<T>> {
+    items.iter().map(|item| decode(item.as_bytes())).collect()
+}
+
+fn encode<'py, T: CanonicalSerialize>(py: Python<'py>, value: &T) -> PyResult<Bytes<'py>> {
+    let mut bytes = Vec::new();
+    value
+        .serialize(&mut bytes)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(PyBytes::new_bound(py, &bytes))
+}
+
+fn encode_all<'py, T: CanonicalSerialize>(
+    py: Python<'py>,
+    values: &[T],
+) -> PyResult<Vec<Bytes<'py>>> {
+    values.iter().map(|value| encode(py, value)).collect()
+}
+
+/// Protocol parameters, with the window table of the generator.
+#[pyclass(name = "Parameters", module = "mental_poker")]
+pub struct PyParameters {
+    inner: PreparedParameters<EdwardsProjective>,
+}
+
+#[pymethods]
+impl PyParameters {
+    /// Generate parameters for a deck of `m * n` cards.
+    #[staticmethod]
+    fn setup(m: usize, n: usize) -> PyResult<Self> {
+        let parameters = BnCardProtocol::setup(&mut thread_rng(), m, n)?;
+        Ok(Self {
+            inner: PreparedParameters::new(&parameters),
+        })
+    }
+
+    #[getter]
+    fn num_cards(&self) -> usize {
+        self.inner.parameters().num_cards()
+    }
+
+    fn to_bytes<'py>(&self, py: Python<'py>) -> PyResult<Bytes<'py>> {
+        encode(py, self.inner.parameters())
+    }
+
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        Ok(Self {
+            inner: PreparedParameters::new(&decode(bytes)?),
+        })
+    }
+}
+
+/// The keypair of a player.
+#[pyclass(name = "Keypair", module = "mental_poker")]
+pub struct PyKeypair {
+    pk: BnPublicKey,
+    sk: BnPlayerSecretKey,
+}
+
+#[pymethods]
+impl PyKeypair {
+    #[staticmethod]
+    fn generate(params: &PyParameters) -> PyResult<Self> {
+        let (pk, sk) = BnCardProtocol::player_keygen(&mut thread_rng(), params.inner.parameters())?;
+        Ok(Self { pk, sk })
+    }
+
+    #[getter]
+    fn public_key<'py>(&self, py: Python<'py>) -> PyResult<Bytes<'py>> {
+        encode(py, &self.pk)
+    }
+
+    /// Prove ownership of the public key, binding the proof to the player's public info.
+    fn prove_key_ownership<'py>(
+        &self,
+        py: Python<'py>,
+        params: &PyParameters,
+        player_info: &[u8],
+    ) -> PyResult<Bytes<'py>> {
+        let proof = BnCardProtocol::prove_key_ownership(
+            &mut thread_rng(),
+            params.inner.parameters(),
+            &self.pk,
+            &self.sk,
+            player_info,
+        )
+        .map_err(CardProtocolError::from)?;
+        encode(py, &proof)
+    }
+
+    /// The player's reveal token for a masked card.
+    fn reveal_token<'py>(
+        &self,
+        py: Python<'py>,
+        params: &PyParameters,
+        masked_card: &[u8],
+    ) -> PyResult<Bytes<'py>> {
+        let (token, proof) = BnCardProtocol::compute_reveal_token(
+            &mut thread_rng(),
+            params.inner.parameters(),
+            &self.sk,
+            &self.pk,
+            &decode(masked_card)?,
+        )?;
+        encode(py, &(token, proof, self.pk))
+    }
+
+    /// Encode the keypair as the public key followed by the secret key encrypted under `password`.
+    fn to_bytes<'py>(&self, py: Python<'py>, password: &str) -> PyResult<Bytes<'py>> {
+        let mut bytes = Vec::new();
+        self.pk
+            .serialize(&mut bytes)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let keystore = self.sk.encrypt(&mut thread_rng(), password.as_bytes())?;
+        bytes.extend_from_slice(&keystore.to_bytes());
+        Ok(PyBytes::new_bound(py, &bytes))
+    }
+
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8], password: &str) -> PyResult<Self> {
+        let mut reader = bytes;
+        let pk = BnPublicKey::deserialize(&mut reader)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let sk = EncryptedSecretKey::from_bytes(reader)?.decrypt(password.as_bytes())?;
+        Ok(Self { pk, sk })
+    }
+}
+
+/// Verify the key ownership proof of every player and compute the aggregate key. `players` holds
+/// a `(public_key, player_info, proof)` tuple per player.
+#[pyfunction]
+fn aggregate_key<'py>(
+    py: Python<'py>,
+    params: &PyParameters,
+    players: Vec<(Bytes, Bytes, Bytes)>,
+) -> PyResult<Bytes<'py>> {
+    let mut key_infos = Vec::with_capacity(players.len());
+    let mut key_proofs: Vec<BnZKProofKeyOwnership> = Vec::with_capacity(players.len());
+    for (pk, info, proof) in players {
+        key_infos.push((
+            decode::<BnPublicKey>(pk.as_bytes())?,
+            info.as_bytes().to_vec(),
+        ));
+        key_proofs.push(decode(proof.as_bytes())?);
+    }
+
+    let shared_key = BnCardProtocol::compute_aggregate_key(
+        params.inner.parameters(),
+        &key_infos,
+        Some(&key_proofs),
+    )?;
+    encode(py, &shared_key)
+}
+
+/// The open cards of the deck, in order.
+#[pyfunction]
+fn cards<'py>(py: Python<'py>, params: &PyParameters) -> PyResult<Vec<Bytes<'py>>> {
+    encode_all(py, &get_card_elems(params.num_cards()))
+}
+
+/// Position of an open card in the deck, or `None` if it is not a card of the deck.
+#[pyfunction]
+fn card_index(params: &PyParameters, card: &[u8]) -> PyResult<Option<usize>> {
+    let card: BnCard = decode(card)?;
+    Ok(get_card_elems(params.num_cards())
+        .iter()
+        .position(|open| *open == card))
+}
+
+/// Mask an open card under the aggregate key, as in the initial deck. Returns the masked card
+/// and the masking proof.
+#[pyfunction]
+fn mask<'py>(
+    py: Python<'py>,
+    params: &PyParameters,
+    shared_key: &[u8],
+    card: &[u8],
+) -> PyResult<(Bytes<'py>, Bytes<'py>)> {
+    let (masked_card, proof) = BnCardProtocol::mask(
+        &mut thread_rng(),
+        params.inner.parameters(),
+        &decode(shared_key)?,
+        &decode(card)?,
+        &BnScalar::one(),
+    )?;
+    Ok((encode(py, &masked_card)?, encode(py, &proof)?))
+}
+
+#[pyfunction]
+fn verify_mask(
+    params: &PyParameters,
+    shared_key: &[u8],
+    card: &[u8],
+    masked_card: &[u8],
+    proof: &[u8],
+) -> PyResult<bool> {
+    let proof: BnZKProofMasking = decode(proof)?;
+    Ok(BnCardProtocol::verify_mask(
+        params.inner.parameters(),
+        &decode(shared_key)?,
+        &decode(card)?,
+        &decode(masked_card)?,
+        &proof,
+    )
+    .is_ok())
+}
+
+/// Mask every open card under the aggregate key. Returns the deck for the first shuffle and the
+/// masking proofs.
+#[pyfunction]
+fn mask_deck<'py>(
+    py: Python<'py>,
+    params: &PyParameters,
+    shared_key: &[u8],
+) -> PyResult<(Vec<Bytes<'py>>, Vec<Bytes<'py>>)> {
+    let prepared_key = PreparedKey::new(&decode(shared_key)?);
+    let rng = &mut thread_rng();
+    let (deck, proofs): (Vec<_>, Vec<_>) = get_card_elems(params.num_cards())
+        .iter()
+        .map(|card| {
+            BnCardProtocol::mask_prepared(rng, &params.inner, &prepared_key, card, &BnScalar::one())
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
+    Ok((encode_all(py, &deck)?, encode_all(py, &proofs)?))
+}
+
+/// Remask a masked card with a fresh masking factor. Returns the remasked card and the proof.
+#[pyfunction]
+fn remask<'py>(
+    py: Python<'py>,
+    params: &PyParameters,
+    shared_key: &[u8],
+    masked_card: &[u8],
+) -> PyResult<(Bytes<'py>, Bytes<'py>)> {
+    let rng = &mut thread_rng();
+    let alpha = BnScalar::rand(rng);
+    let (remasked, proof) = BnCardProtocol::remask_prepared(
+        rng,
+        &params.inner,
+        &PreparedKey::new(&decode(shared_key)?),
+        &decode(masked_card)?,
+        &alpha,
+    )?;
+    Ok((encode(py, &remasked)?, encode(py, &proof)?))
+}
+
+#[pyfunction]
+fn verify_remask(
+    params: &PyParameters,
+    shared_key: &[u8],
+    masked_card: &[u8],
+    remasked_card: &[u8],
+    proof: &[u8],
+) -> PyResult<bool> {
+    let proof: BnZKProofRemasking = decode(proof)?;
+    Ok(BnCardProtocol::verify_remask(
+        params.inner.parameters(),
+        &decode(shared_key)?,
+        &decode(masked_card)?,
+        &decode(remasked_card)?,
+        &proof,
+    )
+    .is_ok())
+}
+
+/// Shuffle and remask a deck with a fresh permutation and masking factors. Returns the shuffled
+/// deck and the shuffle proof.
+#[pyfunction]
+fn shuffle_and_remask<'py>(
+    py: Python<'py>,
+    params: &PyParameters,
+    shared_key: &[u8],
+    deck: Vec<Bytes>,
+) -> PyResult<(Vec<Bytes<'py>>, Bytes<'py>)> {
+    let deck: Vec<BnMaskedCard> = decode_all(deck)?;
+    if deck.len() != params.num_cards() {
+        return Err(PyValueError::new_err(format!(
+            "expected a deck of {} cards, got {}",
+            params.num_cards(),
+            deck.len()
+        )));
+    }
+
+    let rng = &mut thread_rng();
+    let permutation = Permutation::new(rng, deck.len());
+    let masking_factors: Vec<BnScalar> = sample_vector(rng, deck.len());
+    let (shuffled_deck, proof) = BnCardProtocol::shuffle_and_remask_prepared(
+        rng,
+        &params.inner,
+        &PreparedKey::new(&decode(shared_key)?),
+        &deck,
+        &masking_factors,
+        &permutation,
+    )?;
+    Ok((encode_all(py, &shuffled_deck)?, encode(py, &proof)?))
+}
+
+/// Check that `shuffled_deck` is a correct shuffle of `deck`.
+#[pyfunction]
+fn verify_shuffle(
+    params: &PyParameters,
+    shared_key: &[u8],
+    deck: Vec<Bytes>,
+    shuffled_deck: Vec<Bytes>,
+    proof: &[u8],
+) -> PyResult<bool> {
+    let deck: Vec<BnMaskedCard> = decode_all(deck)?;
+    let shuffled_deck: Vec<BnMaskedCard> = decode_all(shuffled_deck)?;
+    let proof: BnZKProofShuffle = decode(proof)?;
+    Ok(BnCardProtocol::verify_shuffle(
+        params.inner.parameters(),
+        &decode(shared_key)?,
+        &deck,
+        &shuffled_deck,
+        &proof,
+    )
+    .is_ok())
+}
+
+/// Unmask a card with the reveal tokens of every player, checking their proofs.
+#[pyfunction]
+fn unmask<'py>(
+    py: Python<'py>,
+    params: &PyParameters,
+    tokens: Vec<Bytes>,
+    masked_card: &[u8],
+) -> PyResult<Bytes<'py>> {
+    let decryption_key: Vec<(BnRevealToken, BnZKProofReveal, BnPublicKey)> = decode_all(tokens)?;
+    let card = BnCardProtocol::unmask(
+        params.inner.parameters(),
+        &decryption_key,
+        &decode(masked_card)?,
+        false,
+    )?;
+    encode(py, &card)
+}
+
+/// Hash-chained log of the public messages of a game. Record a simulated game with the `add_*`
+/// methods, or load a recorded one with `from_bytes`.
+#[pyclass(name = "Transcript", module = "mental_poker")]
+pub struct PyTranscript {
+    inner: Transcript<EdwardsProjective>,
+}
+
+impl PyTranscript {
+    fn append<'py>(
+        &mut self,
+        py: Python<'py>,
+        message: TranscriptMessage<EdwardsProjective>,
+    ) -> PyResult<Bytes<'py>> {
+        Ok(PyBytes::new_bound(py, self.inner.append(message)?))
+    }
+}
+
+#[pymethods]
+impl PyTranscript {
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: Transcript::new(),
+        }
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Hash of the latest entry, which commits to the whole transcript.
+    #[getter]
+    fn head<'py>(&self, py: Python<'py>) -> Bytes<'py> {
+        PyBytes::new_bound(py, self.inner.head())
+    }
+
+    fn to_bytes<'py>(&self, py: Python<'py>) -> PyResult<Bytes<'py>> {
+        encode(py, &self.inner)
+    }
+
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        Ok(Self {
+            inner: decode(bytes)?,
+        })
+    }
+
+    /// The `add_*` methods append a message and return the hash of the new entry.
+    fn add_parameters<'py>(
+        &mut self,
+        py: Python<'py>,
+        params: &PyParameters,
+    ) -> PyResult<Bytes<'py>> {
+        let parameters = params.inner.parameters().clone();
+        self.append(py, TranscriptMessage::Parameters(parameters))
+    }
+
+    fn add_key_registration<'py>(
+        &mut self,
+        py: Python<'py>,
+        player: usize,
+        public_key: &[u8],
+        player_info: &[u8],
+        proof: &[u8],
+    ) -> PyResult<Bytes<'py>> {
+        let registration = KeyRegistration {
+            player,
+            public_key: decode(public_key)?,
+            info: player_info.to_vec(),
+            proof: decode(proof)?,
+        };
+        self.append(py, TranscriptMessage::KeyRegistration(registration))
+    }
+
+    fn add_masked_deck<'py>(
+        &mut self,
+        py: Python<'py>,
+        cards: Vec<Bytes>,
+        masked_cards: Vec<Bytes>,
+        proofs: Vec<Bytes>,
+    ) -> PyResult<Bytes<'py>> {
+        let masked_deck = MaskedDeck {
+            cards: decode_all(cards)?,
+            masked_cards: decode_all(masked_cards)?,
+            proofs: decode_all(proofs)?,
+        };
+        self.append(py, TranscriptMessage::MaskedDeck(masked_deck))
+    }
+
+    fn add_shuffle<'py>(
+        &mut self,
+        py: Python<'py>,
+        player: usize,
+        shuffled_deck: Vec<Bytes>,
+        proof: &[u8],
+    ) -> PyResult<Bytes<'py>> {
+        let link = ShuffleLink {
+            player,
+            shuffled_deck: decode_all(shuffled_deck)?,
+            proof: decode(proof)?,
+        };
+        self.append(py, TranscriptMessage::Shuffle(link))
+    }
+
+    fn add_remask<'py>(
+        &mut self,
+        py: Python<'py>,
+        player: usize,
+        position: usize,
+        masked_card: &[u8],
+        proof: &[u8],
+    ) -> PyResult<Bytes<'py>> {
+        let remask = FaceDownCard {
+            player,
+            position,
+            masked_card: decode(masked_card)?,
+            proof: decode(proof)?,
+        };
+        self.append(py, TranscriptMessage::Remask(remask))
+    }
+
+    /// Record a reveal token as returned by `Keypair.reveal_token`.
+    fn add_reveal<'py>(
+        &mut self,
+        py: Python<'py>,
+        player: usize,
+        position: usize,
+        token: &[u8],
+    ) -> PyResult<Bytes<'py>> {
+        let (token, proof, _): (BnRevealToken, BnZKProofReveal, BnPublicKey) = decode(token)?;
+        let reveal = PublishedReveal {
+            player,
+            position,
+            token,
+            proof,
+        };
+        self.append(py, TranscriptMessage::Reveal(reveal))
+    }
+
+    /// Whether every entry extends the one before it.
+    fn verify_chain(&self) -> bool {
+        self.inner.verify_chain().is_ok()
+    }
+
+    /// Verify the hash chain and every proof, in order, and reconstruct the public outcome of
+    /// the game. Raises `ProtocolError` naming the first offending entry.
+    fn replay_verify(&self, py: Python) -> PyResult<PyReplayedGame> {
+        let game = BnCardProtocol::replay_verify(&self.inner)?;
+        let revealed = game
+            .revealed
+            .iter()
+            .map(|card| card.and_then(|card| game.cards.iter().position(|open| *open == card)))
+            .collect();
+
+        Ok(PyReplayedGame {
+            parameters: encode(py, &game.parameters)?.into(),
+            player_keys: encode_all(py, &game.player_keys)?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            aggregate_key: encode(py, &game.aggregate_key)?.into(),
+            deck: encode_all(py, &game.deck)?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            revealed,
+        })
+    }
+}
+
+/// Public outcome of a game, reconstructed from its transcript.
+#[pyclass(name = "ReplayedGame", module = "mental_poker")]
+pub struct PyReplayedGame {
+    #[pyo3(get)]
+    parameters: Py<PyBytes>,
+    #[pyo3(get)]
+    player_keys: Vec<Py<PyBytes>>,
+    #[pyo3(get)]
+    aggregate_key: Py<PyBytes>,
+    /// Deck at the end of the game.
+    #[pyo3(get)]
+    deck: Vec<Py<PyBytes>>,
+    /// For each position of the final deck, the index of the revealed card in the initial deck,
+    /// or `None` if not every player published a reveal token for it.
+    #[pyo3(get)]
+    revealed: Vec<Option<usize>>,
+}
+
+#[pymodule]
+fn mental_poker(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("ProtocolError", py.get_type_bound::<ProtocolError>())?;
+    m.add(
+        "InvalidProofError",
+        py.get_type_bound::<InvalidProofError>(),
+    )?;
+    m.add_class::<PyParameters>()?;
+    m.add_class::<PyKeypair>()?;
+    m.add_class::<PyTranscript>()?;
+    m.add_class::<PyReplayedGame>()?;
+    m.add_function(wrap_pyfunction!(aggregate_key, m)?)?;
+    m.add_function(wrap_pyfunction!(cards, m)?)?;
+    m.add_function(wrap_pyfunction!(card_index, m)?)?;
+    m.add_function(wrap_pyfunction!(mask, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_mask, m)?)?;
+    m.add_function(wrap_pyfunction!(mask_deck, m)?)?;
+    m.add_function(wrap_pyfunction!(remask, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_remask, m)?)?;
+    m.add_function(wrap_pyfunction!(shuffle_and_remask, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_shuffle, m)?)?;
+    m.add_function(wrap_pyfunction!(unmask, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use pyo3::prelude::*;
+    use pyo3::types::{PyDict, PyModule};
+
+    // A full game scripted from Python, recorded in a transcript and replayed
+    const GAME: &str = r#"
+params = mp.Parameters.setup(2, 2)
+assert mp.Parameters.from_bytes(params.to_bytes()).num_cards == 4
+
+names = [b"Andrija", b"Kobi", b"Nico"]
+players = [mp.Keypair.generate(params) for _ in names]
+proofs = [p.prove_key_ownership(params, name) for p, name in zip(players, names)]
+
+transcript = mp.Transcript()
+transcript.add_parameters(params)
+for i, (p, name, proof) in enumerate(zip(players, names, proofs)):
+    transcript.add_key_registration(i, p.public_key, name, proof)
+
+try:
+    mp.aggregate_key(params, [(players[0].public_key, b"Mallory", proofs[0])])
+    raise AssertionError("proof bound to another name accepted")
+except mp.InvalidProofError:
+    pass
+shared_key = mp.aggregate_key(
+    params, [(p.public_key, name, proof) for p, name, proof in zip(players, names, proofs)]
+)
+
+cards = mp.cards(params)
+deck, mask_proofs = mp.mask_deck(params, shared_key)
+assert all(mp.verify_mask(params, shared_key, c, m, pr) for c, m, pr in zip(cards, deck, mask_proofs))
+transcript.add_masked_deck(cards, deck, mask_proofs)
+
+for i in range(len(players)):
+    shuffled, proof = mp.shuffle_and_remask(params, shared_key, deck)
+    assert mp.verify_shuffle(params, shared_key, deck, shuffled, proof)
+    assert not mp.verify_shuffle(params, shared_key, shuffled, shuffled, proof)
+    transcript.add_shuffle(i, shuffled, proof)
+    deck = shuffled
+
+remasked, proof = mp.remask(params, shared_key, deck[1])
+assert mp.verify_remask(params, shared_key, deck[1], remasked, proof)
+transcript.add_remask(0, 1, remasked, proof)
+deck[1] = remasked
+
+tokens = [p.reveal_token(params, deck[1]) for p in players]
+for i, token in enumerate(tokens):
+    transcript.add_reveal(i, 1, token)
+index = mp.card_index(params, mp.unmask(params, tokens, deck[1]))
+assert index is not None
+assert mp.card_index(params, mp.unmask(params, tokens[1:], deck[1])) is None
+
+game = mp.Transcript.from_bytes(transcript.to_bytes()).replay_verify()
+assert game.revealed == [None, index, None, None]
+assert game.player_keys == [p.public_key for p in players]
+assert game.aggregate_key == shared_key
+assert game.deck == deck
+
+exported = players[0].to_bytes("hunter2")
+assert mp.Keypair.from_bytes(exported, "hunter2").public_key == players[0].public_key
+try:
+    mp.Keypair.from_bytes(exported, "wrong")
+    raise AssertionError("wrong password accepted")
+except mp.ProtocolError:
+    pass
+try:
+    mp.card_index(params, b"\x01\x02\x03")
+    raise AssertionError("malformed card accepted")
+except ValueError:
+    pass
+"#;
+
+    #[test]
+    fn test_python_game() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "mental_poker").unwrap();
+            super::mental_poker(&module).unwrap();
+            let globals = PyDict::new_bound(py);
+            globals.set_item("mp", module).unwrap();
+
+            if let Err(err) = py.run_bound(GAME, Some(&globals), None) {
+                err.print(py);
+                panic!("Python game failed");
+            }
+        });
+    }
+}
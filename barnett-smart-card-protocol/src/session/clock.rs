@@ -0,0 +1,67 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+
+/// Source of time for deadlines. Times are durations since an arbitrary, clock-specific origin.
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+/// Wall clock, measuring time since its creation. `Instant` panics on wasm32-unknown-unknown, so
+/// there the caller drives a `MockClock` instead, e.g. from `Date.now()`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// Clock driven by the caller, for tests and for platforms without `Instant`. Clones share the same
+/// time.
+#[derive(Clone, Debug, Default)]
+pub struct MockClock {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+
+    pub fn set(&self, now: Duration) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
@@ -0,0 +1,447 @@
+// Liveness tracking for a table: which player is expected to act at which step, and by when.
+//
+// The engine arms a deadline whenever it starts waiting on a player and clears it when the
+// player's message arrives. Polling reports every missed deadline as a `Timeout`, which records
+// when the wait started, when it was due, when it was found expired and when the player was last
+// heard from. A `TimeoutPolicy` then decides what the table does about it.
+//
+// The times are local to the table and prove nothing to anyone else. A timeout also carries what
+// other players can check: the signed header of the last message received from the player, the
+// signed request they were waited on to answer, and their echo of that request, which shows that
+// they received it.
+
+use crate::discrete_log_cards::{Parameters, PublicKey};
+use crate::error::CardProtocolError;
+use crate::transport::{Echo, SignedHeader};
+
+use ark_ec::ProjectiveCurve;
+use std::time::Duration;
+
+mod clock;
+
+pub use clock::*;
+
+/// A step of the protocol a player can be waited on for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Step {
+    KeyOwnership,
+    Masking,
+    Shuffle,
+    /// Reveal token for the card at the given deck position.
+    Reveal(usize),
+    Discard,
+    Play,
+}
+
+/// A pending wait on `player` to complete `step`, in answer to `request` if it is a signed
+/// message. `acknowledgement` is the player's echo of the request, once received.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deadline<C: ProjectiveCurve> {
+    pub player: usize,
+    pub step: Step,
+    pub started_at: Duration,
+    pub due: Duration,
+    pub request: Option<SignedHeader<C>>,
+    pub acknowledgement: Option<Echo<C>>,
+}
+
+/// A missed deadline. `last_seen` is the last time anything was heard from the player, if ever,
+/// and `last_message` the header of the last signed message received from them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Timeout<C: ProjectiveCurve> {
+    pub player: usize,
+    pub step: Step,
+    pub deadline: Deadline<C>,
+    pub observed_at: Duration,
+    pub last_seen: Option<Duration>,
+    pub last_message: Option<SignedHeader<C>>,
+}
+
+impl<C: ProjectiveCurve> Timeout<C> {
+    /// Check the signed evidence of the timeout against the keys of the players: the last message
+    /// must be signed by the player, the request by its sender, and the acknowledgement must be
+    /// the player's echo of the request.
+    pub fn verify(
+        &self,
+        pp: &Parameters<C>,
+        player_keys: &[PublicKey<C>],
+    ) -> Result<(), CardProtocolError> {
+        let key = |player: usize| {
+            player_keys
+                .get(player)
+                .ok_or(CardProtocolError::UnknownPlayer(player))
+        };
+
+        if let Some(header) = &self.last_message {
+            if header.sender != self.player {
+                return Err(CardProtocolError::InvalidTimeoutEvidence(self.player));
+            }
+            header.verify(pp, key(self.player)?)?;
+        }
+
+        if let Some(request) = &self.deadline.request {
+            request.verify(pp, key(request.sender)?)?;
+        }
+
+        if let Some(echo) = &self.deadline.acknowledgement {
+            if echo.echoer != self.player || self.deadline.request.as_ref() != Some(&echo.header) {
+                return Err(CardProtocolError::InvalidTimeoutEvidence(self.player));
+            }
+            echo.verify(pp, key(self.player)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What the table does about a timeout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeoutAction {
+    /// Give the player more time; the deadline is re-armed for the same step.
+    Extend(Duration),
+    /// Carry on without the player's contribution to this step, e.g. skip their shuffle.
+    Skip,
+    /// Stand in for the player, e.g. by recovering their reveal tokens from escrow.
+    Recover,
+    /// Abandon the hand.
+    Abort,
+}
+
+/// Hook deciding how to react to a timeout.
+pub trait TimeoutPolicy<C: ProjectiveCurve> {
+    fn on_timeout(&mut self, timeout: &Timeout<C>) -> TimeoutAction;
+}
+
+/// Policy that abandons the hand on any timeout.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AbortOnTimeout;
+
+impl<C: ProjectiveCurve> TimeoutPolicy<C> for AbortOnTimeout {
+    fn on_timeout(&mut self, _timeout: &Timeout<C>) -> TimeoutAction {
+        TimeoutAction::Abort
+    }
+}
+
+impl<C: ProjectiveCurve, F: FnMut(&Timeout<C>) -> TimeoutAction> TimeoutPolicy<C> for F {
+    fn on_timeout(&mut self, timeout: &Timeout<C>) -> TimeoutAction {
+        self(timeout)
+    }
+}
+
+/// Pending deadlines of a table, driven by a `Clock`.
+pub struct LivenessTracker<C: ProjectiveCurve, K: Clock> {
+    clock: K,
+    deadlines: Vec<Deadline<C>>,
+    last_seen: Vec<Option<Duration>>,
+    last_message: Vec<Option<SignedHeader<C>>>,
+}
+
+impl<C: ProjectiveCurve, K: Clock> LivenessTracker<C, K> {
+    pub fn new(clock: K, num_of_players: usize) -> Self {
+        Self {
+            clock,
+            deadlines: Vec::new(),
+            last_seen: vec![None; num_of_players],
+            last_message: vec![None; num_of_players],
+        }
+    }
+
+    pub fn clock(&self) -> &K {
+        &self.clock
+    }
+
+    pub fn deadlines(&self) -> &[Deadline<C>] {
+        &self.deadlines
+    }
+
+    pub fn last_seen(&self, player: usize) -> Option<Duration> {
+        self.last_seen.get(player).copied().flatten()
+    }
+
+    /// Header of the last signed message received from `player`, if any.
+    pub fn last_message(&self, player: usize) -> Option<&SignedHeader<C>> {
+        self.last_message.get(player).and_then(Option::as_ref)
+    }
+
+    /// Start waiting on `player` to complete `step` within `timeout`. Re-arms an existing
+    /// deadline for the same player and step.
+    pub fn expect(&mut self, player: usize, step: Step, timeout: Duration) -> Deadline<C> {
+        self.arm(player, step, timeout, None)
+    }
+
+    /// Start waiting on `player` to answer the signed `request` by completing `step` within
+    /// `timeout`, like `expect`.
+    pub fn expect_reply(
+        &mut self,
+        player: usize,
+        step: Step,
+        timeout: Duration,
+        request: SignedHeader<C>,
+    ) -> Deadline<C> {
+        self.arm(player, step, timeout, Some(request))
+    }
+
+    fn arm(
+        &mut self,
+        player: usize,
+        step: Step,
+        timeout: Duration,
+        request: Option<SignedHeader<C>>,
+    ) -> Deadline<C> {
+        let now = self.clock.now();
+        let deadline = Deadline {
+            player,
+            step,
+            started_at: now,
+            due: now + timeout,
+            request,
+            acknowledgement: None,
+        };
+
+        self.deadlines
+            .retain(|pending| pending.player != player || pending.step != step);
+        self.deadlines.push(deadline.clone());
+
+        deadline
+    }
+
+    /// Note that a message was received from `player`.
+    pub fn heard_from(&mut self, player: usize) {
+        let now = self.clock.now();
+        if let Some(last_seen) = self.last_seen.get_mut(player) {
+            *last_seen = Some(now);
+        }
+    }
+
+    /// Note that a signed message was received from its sender. The header must have been
+    /// verified.
+    pub fn received(&mut self, header: SignedHeader<C>) {
+        let sender = header.sender;
+        self.heard_from(sender);
+        if let Some(last_message) = self.last_message.get_mut(sender) {
+            *last_message = Some(header);
+        }
+    }
+
+    /// Note an echo, which must have been verified. An echo by a player of the request they are
+    /// waited on for is kept as the acknowledgement of that deadline.
+    pub fn echoed(&mut self, echo: Echo<C>) {
+        self.heard_from(echo.echoer);
+        for deadline in self.deadlines.iter_mut() {
+            if deadline.player == echo.echoer && deadline.request.as_ref() == Some(&echo.header) {
+                deadline.acknowledgement = Some(echo.clone());
+            }
+        }
+    }
+
+    /// Clear the deadline of `player` for `step`, if any. Returns the cleared deadline, or `None`
+    /// if nothing was pending.
+    pub fn complete(&mut self, player: usize, step: Step) -> Option<Deadline<C>> {
+        self.heard_from(player);
+
+        let index = self
+            .deadlines
+            .iter()
+            .position(|pending| pending.player == player && pending.step == step)?;
+
+        Some(self.deadlines.remove(index))
+    }
+
+    /// Remove and return every deadline that has passed.
+    pub fn poll(&mut self) -> Vec<Timeout<C>> {
+        let now = self.clock.now();
+
+        let (expired, pending): (Vec<_>, Vec<_>) = self
+            .deadlines
+            .drain(..)
+            .partition(|deadline| deadline.due <= now);
+        self.deadlines = pending;
+
+        expired
+            .into_iter()
+            .map(|deadline| Timeout {
+                player: deadline.player,
+                step: deadline.step,
+                observed_at: now,
+                last_seen: self.last_seen(deadline.player),
+                last_message: self.last_message(deadline.player).cloned(),
+                deadline,
+            })
+            .collect()
+    }
+
+    /// Poll and ask `policy` what to do about each timeout. Deadlines the policy extends are
+    /// re-armed, keeping their request and acknowledgement; all other decisions are returned for
+    /// the engine to carry out.
+    pub fn poll_with<P: TimeoutPolicy<C>>(
+        &mut self,
+        policy: &mut P,
+    ) -> Vec<(Timeout<C>, TimeoutAction)> {
+        let mut decisions = Vec::new();
+
+        for timeout in self.poll() {
+            match policy.on_timeout(&timeout) {
+                TimeoutAction::Extend(by) => {
+                    let now = self.clock.now();
+                    self.deadlines.push(Deadline {
+                        started_at: now,
+                        due: now + by,
+                        ..timeout.deadline
+                    });
+                }
+                action => decisions.push((timeout, action)),
+            }
+        }
+
+        decisions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AbortOnTimeout, LivenessTracker, MockClock, Step, Timeout, TimeoutAction};
+    use crate::discrete_log_cards::DLCards;
+    use crate::error::CardProtocolError;
+    use crate::transport::{Echo, MessageKind, SignedHeader, Slot};
+    use crate::BarnettSmartProtocol;
+
+    use rand::thread_rng;
+    use std::time::Duration;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+    type CardProtocol = DLCards<Curve>;
+
+    #[test]
+    fn test_deadlines() {
+        let clock = MockClock::new();
+        let mut tracker = LivenessTracker::<Curve, _>::new(clock.clone(), 3);
+
+        tracker.expect(0, Step::Shuffle, Duration::from_secs(30));
+        tracker.expect(1, Step::Reveal(4), Duration::from_secs(10));
+
+        clock.advance(Duration::from_secs(5));
+        tracker.heard_from(1);
+        assert!(tracker.poll().is_empty());
+
+        // Player 0 shuffles in time, player 1 never sends their token
+        clock.advance(Duration::from_secs(5));
+        assert!(tracker.complete(0, Step::Shuffle).is_some());
+        assert_eq!(
+            tracker.poll(),
+            vec![Timeout {
+                player: 1,
+                step: Step::Reveal(4),
+                deadline: super::Deadline {
+                    player: 1,
+                    step: Step::Reveal(4),
+                    started_at: Duration::from_secs(0),
+                    due: Duration::from_secs(10),
+                    request: None,
+                    acknowledgement: None,
+                },
+                observed_at: Duration::from_secs(10),
+                last_seen: Some(Duration::from_secs(5)),
+                last_message: None,
+            }]
+        );
+        assert!(tracker.deadlines().is_empty());
+    }
+
+    #[test]
+    fn test_policy() {
+        let clock = MockClock::new();
+        let mut tracker = LivenessTracker::<Curve, _>::new(clock.clone(), 3);
+
+        tracker.expect(0, Step::Shuffle, Duration::from_secs(30));
+        tracker.expect(2, Step::Reveal(0), Duration::from_secs(30));
+
+        // Give shufflers one extension, then skip them; recover missing reveal tokens
+        let mut extended = false;
+        let mut policy = |timeout: &Timeout<Curve>| match timeout.step {
+            Step::Shuffle if !extended => {
+                extended = true;
+                TimeoutAction::Extend(Duration::from_secs(30))
+            }
+            Step::Shuffle => TimeoutAction::Skip,
+            Step::Reveal(_) => TimeoutAction::Recover,
+            _ => TimeoutAction::Abort,
+        };
+
+        clock.advance(Duration::from_secs(30));
+        let decisions = tracker.poll_with(&mut policy);
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].0.player, 2);
+        assert_eq!(decisions[0].1, TimeoutAction::Recover);
+        assert_eq!(tracker.deadlines()[0].due, Duration::from_secs(60));
+
+        clock.advance(Duration::from_secs(30));
+        let decisions = tracker.poll_with(&mut policy);
+        assert_eq!(decisions[0].1, TimeoutAction::Skip);
+
+        tracker.expect(1, Step::Play, Duration::from_secs(1));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            tracker.poll_with(&mut AbortOnTimeout)[0].1,
+            TimeoutAction::Abort
+        );
+    }
+
+    #[test]
+    fn test_timeout_evidence() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 2, 2).unwrap();
+        let players = (0..3)
+            .map(|_| CardProtocol::player_keygen(rng, &parameters).unwrap())
+            .collect::<Vec<_>>();
+        let keys = players.iter().map(|(pk, _)| *pk).collect::<Vec<_>>();
+        let header = |rng: &mut _, sender: usize, kind, payload: &[u8]| {
+            let slot = Slot {
+                session: 0,
+                sender,
+                kind,
+                sequence: 0,
+            };
+            let (pk, sk) = &players[sender];
+            SignedHeader::sign(rng, &parameters, slot, sk, pk, payload).unwrap()
+        };
+
+        let clock = MockClock::new();
+        let mut tracker = LivenessTracker::new(clock.clone(), 3);
+
+        // Player 1 registered, then received the shuffle of player 0 but never shuffled in turn
+        let registration = header(rng, 1, MessageKind::KeyRegistration, b"key");
+        tracker.received(registration.clone());
+        let request = header(rng, 0, MessageKind::Shuffle, b"deck");
+        tracker.received(request.clone());
+        tracker.expect_reply(1, Step::Shuffle, Duration::from_secs(10), request.clone());
+
+        let (pk, sk) = &players[1];
+        let echo = Echo::sign(rng, &parameters, 1, sk, pk, request.clone()).unwrap();
+        tracker.echoed(echo.clone());
+
+        clock.advance(Duration::from_secs(10));
+        let mut timeouts = tracker.poll();
+        assert_eq!(timeouts.len(), 1);
+        let timeout = timeouts.pop().unwrap();
+        assert_eq!(timeout.last_message, Some(registration));
+        assert_eq!(timeout.deadline.request, Some(request.clone()));
+        assert_eq!(timeout.deadline.acknowledgement, Some(echo));
+        assert!(timeout.verify(&parameters, &keys).is_ok());
+
+        // Anyone else's echo does not show that player 1 received the request
+        let mut forged = timeout.clone();
+        let (pk, sk) = &players[2];
+        forged.deadline.acknowledgement =
+            Some(Echo::sign(rng, &parameters, 2, sk, pk, request).unwrap());
+        assert_eq!(
+            forged.verify(&parameters, &keys),
+            Err(CardProtocolError::InvalidTimeoutEvidence(1))
+        );
+
+        // The evidence is bound to the keys of the players
+        let mut other_keys = keys.clone();
+        other_keys.swap(0, 1);
+        assert!(timeout.verify(&parameters, &other_keys).is_err());
+    }
+}
@@ -0,0 +1,631 @@
+// Echo broadcast: a message is only delivered once every other player has confirmed, with a signed
+// echo, that they received the same message from its sender.
+//
+// The sender signs a header binding the message to a slot (session, sender, kind, sequence number)
+// and to the hash of the payload. The session identifies the table, so a header signed for one
+// table cannot be replayed at another one with the same players. Every receiver checks the
+// payload against the header and echoes the signed header to the whole table. Since echoes carry
+// the sender's signature, two headers for the same slot with different hashes are transferable
+// evidence that the sender equivocated, even if one of them was already delivered.
+
+use super::{Envelope, Transport};
+use crate::discrete_log_cards::{DLCards, Parameters, PlayerSecretKey, PublicKey, Signature};
+use crate::error::CardProtocolError;
+
+use ark_ec::ProjectiveCurve;
+use ark_ff::to_bytes;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::io::{Read, Write};
+use ark_std::rand::Rng;
+use blake2::{Blake2s, Digest};
+use proof_essentials::error::CryptoError;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+const HEADER_TAG: &[u8] = b"Broadcast Header";
+const ECHO_TAG: &[u8] = b"Broadcast Echo";
+
+/// Protocol messages that go through echo broadcast.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MessageKind {
+    KeyRegistration,
+    Shuffle,
+    RevealToken,
+}
+
+impl MessageKind {
+    fn tag(self) -> u8 {
+        match self {
+            MessageKind::KeyRegistration => 0,
+            MessageKind::Shuffle => 1,
+            MessageKind::RevealToken => 2,
+        }
+    }
+}
+
+impl CanonicalSerialize for MessageKind {
+    fn serialize<W: Write>(&self, writer: W) -> Result<(), SerializationError> {
+        self.tag().serialize(writer)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.tag().serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for MessageKind {
+    fn deserialize<R: Read>(reader: R) -> Result<Self, SerializationError> {
+        match u8::deserialize(reader)? {
+            0 => Ok(MessageKind::KeyRegistration),
+            1 => Ok(MessageKind::Shuffle),
+            2 => Ok(MessageKind::RevealToken),
+            _ => Err(SerializationError::InvalidData),
+        }
+    }
+}
+
+/// A sender may broadcast at most one message per kind and sequence number in a session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Slot {
+    pub session: u64,
+    pub sender: usize,
+    pub kind: MessageKind,
+    pub sequence: u64,
+}
+
+/// The sender's signed commitment to the payload of a slot.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SignedHeader<C: ProjectiveCurve> {
+    pub session: u64,
+    pub sender: usize,
+    pub kind: MessageKind,
+    pub sequence: u64,
+    pub digest: Vec<u8>,
+    pub signature: Signature<C>,
+}
+
+impl<C: ProjectiveCurve> SignedHeader<C> {
+    pub fn sign<R: Rng>(
+        rng: &mut R,
+        pp: &Parameters<C>,
+        slot: Slot,
+        sk: &PlayerSecretKey<C>,
+        pk: &PublicKey<C>,
+        payload: &[u8],
+    ) -> Result<Self, CryptoError> {
+        let digest = Blake2s::digest(payload).to_vec();
+        let message = header_bytes(slot, &digest)?;
+
+        Ok(Self {
+            session: slot.session,
+            sender: slot.sender,
+            kind: slot.kind,
+            sequence: slot.sequence,
+            digest,
+            signature: DLCards::sign(rng, pp, sk, pk, &message)?,
+        })
+    }
+
+    pub fn slot(&self) -> Slot {
+        Slot {
+            session: self.session,
+            sender: self.sender,
+            kind: self.kind,
+            sequence: self.sequence,
+        }
+    }
+
+    pub fn verify(&self, pp: &Parameters<C>, sender_key: &PublicKey<C>) -> Result<(), CryptoError> {
+        let message = header_bytes(self.slot(), &self.digest)?;
+        DLCards::verify_signature(pp, sender_key, &message, &self.signature)
+    }
+}
+
+fn header_bytes(slot: Slot, digest: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    Ok(to_bytes![
+        HEADER_TAG,
+        slot.session,
+        slot.sender as u64,
+        slot.kind.tag(),
+        slot.sequence,
+        digest
+    ]?)
+}
+
+/// Confirmation by `echoer` that they received the message described by `header`.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Echo<C: ProjectiveCurve> {
+    pub echoer: usize,
+    pub header: SignedHeader<C>,
+    pub signature: Signature<C>,
+}
+
+impl<C: ProjectiveCurve> Echo<C> {
+    pub fn sign<R: Rng>(
+        rng: &mut R,
+        pp: &Parameters<C>,
+        echoer: usize,
+        sk: &PlayerSecretKey<C>,
+        pk: &PublicKey<C>,
+        header: SignedHeader<C>,
+    ) -> Result<Self, CryptoError> {
+        let message = echo_bytes(echoer, &header)?;
+
+        Ok(Self {
+            echoer,
+            signature: DLCards::sign(rng, pp, sk, pk, &message)?,
+            header,
+        })
+    }
+
+    /// Check the echoer's signature. The header is checked separately, against its sender's key.
+    pub fn verify(&self, pp: &Parameters<C>, echoer_key: &PublicKey<C>) -> Result<(), CryptoError> {
+        let message = echo_bytes(self.echoer, &self.header)?;
+        DLCards::verify_signature(pp, echoer_key, &message, &self.signature)
+    }
+}
+
+fn echo_bytes<C: ProjectiveCurve>(
+    echoer: usize,
+    header: &SignedHeader<C>,
+) -> Result<Vec<u8>, CryptoError> {
+    let header = header_bytes(header.slot(), &header.digest)?;
+    Ok(to_bytes![ECHO_TAG, echoer as u64, header]?)
+}
+
+/// Two validly signed headers from the same sender for the same slot, committing to different
+/// payloads. Anyone holding the sender's public key can check it.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct EquivocationEvidence<C: ProjectiveCurve> {
+    pub first: SignedHeader<C>,
+    pub second: SignedHeader<C>,
+}
+
+impl<C: ProjectiveCurve> EquivocationEvidence<C> {
+    pub fn sender(&self) -> usize {
+        self.first.sender
+    }
+
+    pub fn verify(&self, pp: &Parameters<C>, sender_key: &PublicKey<C>) -> Result<(), CryptoError> {
+        if self.first.slot() != self.second.slot() || self.first.digest == self.second.digest {
+            return Err(CryptoError::ProofVerificationError(String::from(
+                "Equivocation",
+            )));
+        }
+
+        self.first.verify(pp, sender_key)?;
+        self.second.verify(pp, sender_key)
+    }
+}
+
+/// What goes over the wire in echo broadcast.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EchoMessage<C: ProjectiveCurve> {
+    Broadcast {
+        header: SignedHeader<C>,
+        payload: Vec<u8>,
+    },
+    Echo(Echo<C>),
+}
+
+impl<C: ProjectiveCurve> CanonicalSerialize for EchoMessage<C> {
+    fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        match self {
+            EchoMessage::Broadcast { header, payload } => {
+                0u8.serialize(&mut writer)?;
+                header.serialize(&mut writer)?;
+                payload.serialize(&mut writer)
+            }
+            EchoMessage::Echo(echo) => {
+                1u8.serialize(&mut writer)?;
+                echo.serialize(&mut writer)
+            }
+        }
+    }
+
+    fn serialized_size(&self) -> usize {
+        1 + match self {
+            EchoMessage::Broadcast { header, payload } => {
+                header.serialized_size() + payload.serialized_size()
+            }
+            EchoMessage::Echo(echo) => echo.serialized_size(),
+        }
+    }
+}
+
+impl<C: ProjectiveCurve> CanonicalDeserialize for EchoMessage<C> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        match u8::deserialize(&mut reader)? {
+            0 => Ok(EchoMessage::Broadcast {
+                header: SignedHeader::deserialize(&mut reader)?,
+                payload: Vec::deserialize(&mut reader)?,
+            }),
+            1 => Ok(EchoMessage::Echo(Echo::deserialize(&mut reader)?)),
+            _ => Err(SerializationError::InvalidData),
+        }
+    }
+}
+
+/// Outcome of echo broadcast.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EchoEvent<C: ProjectiveCurve> {
+    /// Every other player confirmed receiving this payload for the slot.
+    Delivered { slot: Slot, payload: Vec<u8> },
+    /// The sender of the slot equivocated. Nothing is delivered for the slot.
+    Equivocation(EquivocationEvidence<C>),
+}
+
+struct SlotState<C: ProjectiveCurve> {
+    header: SignedHeader<C>,
+    payload: Option<Vec<u8>>,
+    echoes: BTreeSet<usize>,
+    echoed: bool,
+    equivocated: bool,
+    done: bool,
+}
+
+/// Echo broadcast of protocol messages over a `Transport`.
+pub struct EchoBroadcast<C: ProjectiveCurve, T: Transport> {
+    transport: T,
+    session: u64,
+    pp: Parameters<C>,
+    player_keys: Vec<PublicKey<C>>,
+    sk: PlayerSecretKey<C>,
+    slots: HashMap<Slot, SlotState<C>>,
+    events: VecDeque<EchoEvent<C>>,
+}
+
+impl<C: ProjectiveCurve, T: Transport> EchoBroadcast<C, T> {
+    /// `player_keys` holds the public key of every player of the table, in transport order.
+    /// `session` identifies the table; headers signed for any other session are rejected.
+    pub fn new(
+        transport: T,
+        session: u64,
+        pp: Parameters<C>,
+        player_keys: Vec<PublicKey<C>>,
+        sk: PlayerSecretKey<C>,
+    ) -> Result<Self, CardProtocolError> {
+        if player_keys.len() != transport.num_of_players() {
+            return Err(CardProtocolError::UnknownPlayer(player_keys.len()));
+        }
+
+        Ok(Self {
+            transport,
+            session,
+            pp,
+            player_keys,
+            sk,
+            slots: HashMap::new(),
+            events: VecDeque::new(),
+        })
+    }
+
+    pub fn transport(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Sign `payload` for the given slot of the local player and broadcast it.
+    pub fn broadcast<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        kind: MessageKind,
+        sequence: u64,
+        payload: Vec<u8>,
+    ) -> Result<(), CardProtocolError> {
+        let player = self.transport.player();
+        let slot = Slot {
+            session: self.session,
+            sender: player,
+            kind,
+            sequence,
+        };
+        let header = SignedHeader::sign(
+            rng,
+            &self.pp,
+            slot,
+            &self.sk,
+            &self.player_keys[player],
+            &payload,
+        )?;
+
+        self.transport.broadcast_message(&EchoMessage::Broadcast {
+            header: header.clone(),
+            payload: payload.clone(),
+        })?;
+        self.record(header, Some(payload));
+
+        Ok(())
+    }
+
+    /// Wait for the next delivered message or equivocation.
+    pub fn next_event<R: Rng>(&mut self, rng: &mut R) -> Result<EchoEvent<C>, CardProtocolError> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+
+            let envelope = self.transport.recv()?;
+            self.process(rng, &envelope)?;
+        }
+    }
+
+    /// Handle a message received from the transport. Resulting events are queued for
+    /// `next_event`.
+    pub fn process<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        envelope: &Envelope,
+    ) -> Result<(), CardProtocolError> {
+        match envelope.decode::<EchoMessage<C>>()? {
+            EchoMessage::Broadcast { header, payload } => {
+                if header.sender != envelope.from {
+                    return Err(CardProtocolError::UnknownPlayer(header.sender));
+                }
+                self.check_header(&header)?;
+                if Blake2s::digest(&payload).as_slice() != header.digest.as_slice() {
+                    return Err(echo_error().into());
+                }
+
+                let slot = header.slot();
+                self.record(header, Some(payload));
+                self.echo(rng, slot)?;
+            }
+            EchoMessage::Echo(echo) => {
+                if echo.echoer != envelope.from {
+                    return Err(CardProtocolError::UnknownPlayer(echo.echoer));
+                }
+                self.check_header(&echo.header)?;
+                echo.verify(&self.pp, &self.player_keys[echo.echoer])?;
+
+                let slot = echo.header.slot();
+                if self.record(echo.header, None) {
+                    if let Some(state) = self.slots.get_mut(&slot) {
+                        state.echoes.insert(echo.echoer);
+                    }
+                }
+            }
+        }
+
+        self.check_delivery();
+
+        Ok(())
+    }
+
+    fn check_header(&self, header: &SignedHeader<C>) -> Result<(), CardProtocolError> {
+        if header.session != self.session {
+            return Err(CardProtocolError::WrongSession(header.session));
+        }
+        let sender_key = self
+            .player_keys
+            .get(header.sender)
+            .ok_or(CardProtocolError::UnknownPlayer(header.sender))?;
+        header.verify(&self.pp, sender_key)?;
+
+        Ok(())
+    }
+
+    /// Record a verified header and, if known, its payload. Returns whether the header agrees
+    /// with what was recorded for the slot so far.
+    fn record(&mut self, header: SignedHeader<C>, payload: Option<Vec<u8>>) -> bool {
+        let state = self
+            .slots
+            .entry(header.slot())
+            .or_insert_with(|| SlotState {
+                header: header.clone(),
+                payload: None,
+                echoes: BTreeSet::new(),
+                echoed: false,
+                equivocated: false,
+                done: false,
+            });
+
+        // A conflicting header is evidence even if the slot was already delivered, but it is only
+        // reported once per slot.
+        if state.header.digest != header.digest {
+            if !state.equivocated {
+                state.equivocated = true;
+                state.done = true;
+                self.events
+                    .push_back(EchoEvent::Equivocation(EquivocationEvidence {
+                        first: state.header.clone(),
+                        second: header,
+                    }));
+            }
+            return false;
+        }
+
+        if payload.is_some() {
+            state.payload = payload;
+        }
+        true
+    }
+
+    fn echo<R: Rng>(&mut self, rng: &mut R, slot: Slot) -> Result<(), CardProtocolError> {
+        let player = self.transport.player();
+        let state = match self.slots.get_mut(&slot) {
+            Some(state) if !state.echoed && !state.done => state,
+            _ => return Ok(()),
+        };
+
+        let echo = Echo::sign(
+            rng,
+            &self.pp,
+            player,
+            &self.sk,
+            &self.player_keys[player],
+            state.header.clone(),
+        )?;
+        state.echoed = true;
+        state.echoes.insert(player);
+
+        self.transport.broadcast_message(&EchoMessage::Echo(echo))
+    }
+
+    fn check_delivery(&mut self) {
+        let num_of_players = self.player_keys.len();
+
+        for (slot, state) in self.slots.iter_mut() {
+            let confirmed = (0..num_of_players)
+                .filter(|player| *player != slot.sender)
+                .all(|player| state.echoes.contains(&player));
+
+            if !state.done && confirmed {
+                if let Some(payload) = &state.payload {
+                    state.done = true;
+                    self.events.push_back(EchoEvent::Delivered {
+                        slot: *slot,
+                        payload: payload.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn echo_error() -> CryptoError {
+    CryptoError::ProofVerificationError(String::from("Echo"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EchoBroadcast, EchoEvent, EchoMessage, MessageKind, SignedHeader, Slot};
+    use crate::discrete_log_cards;
+    use crate::error::CardProtocolError;
+    use crate::transport::{MemoryTransport, Transport};
+    use crate::BarnettSmartProtocol;
+
+    use rand::thread_rng;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+
+    #[test]
+    fn test_echo_broadcast() {
+        let rng = &mut thread_rng();
+        let session = 7;
+        let parameters = CardProtocol::setup(rng, 2, 4).unwrap();
+        let players = (0..3)
+            .map(|_| CardProtocol::player_keygen(rng, &parameters).unwrap())
+            .collect::<Vec<_>>();
+        let player_keys = players.iter().map(|(pk, _)| *pk).collect::<Vec<_>>();
+
+        let mut tables = MemoryTransport::network(3)
+            .into_iter()
+            .zip(players.iter())
+            .map(|(transport, (_, sk))| {
+                EchoBroadcast::new(
+                    transport,
+                    session,
+                    parameters.clone(),
+                    player_keys.clone(),
+                    sk.clone(),
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        // Honest broadcast from player 0
+        tables[0]
+            .broadcast(rng, MessageKind::Shuffle, 0, b"deck".to_vec())
+            .unwrap();
+        let slot = Slot {
+            session,
+            sender: 0,
+            kind: MessageKind::Shuffle,
+            sequence: 0,
+        };
+
+        // Players 1 and 2 echo on receipt, then everyone collects the echoes
+        for player in [1, 2] {
+            let envelope = tables[player].transport().recv().unwrap();
+            tables[player].process(rng, &envelope).unwrap();
+        }
+        for table in tables.iter_mut() {
+            assert_eq!(
+                table.next_event(rng).unwrap(),
+                EchoEvent::Delivered {
+                    slot,
+                    payload: b"deck".to_vec()
+                }
+            );
+        }
+
+        // Player 0 signs and sends a shuffle for a slot to a single player
+        let send = |table: &mut EchoBroadcast<Curve, MemoryTransport>,
+                    rng: &mut _,
+                    to,
+                    slot,
+                    payload: &[u8]| {
+            let header = SignedHeader::sign(
+                rng,
+                &parameters,
+                slot,
+                &players[0].1,
+                &player_keys[0],
+                payload,
+            )
+            .unwrap();
+            table
+                .transport()
+                .send_message(
+                    to,
+                    &EchoMessage::Broadcast {
+                        header,
+                        payload: payload.to_vec(),
+                    },
+                )
+                .unwrap();
+        };
+
+        // Player 0 sends different shuffles to players 1 and 2
+        let delivered = slot;
+        let slot = Slot {
+            sequence: 1,
+            ..slot
+        };
+        send(&mut tables[0], rng, 1, slot, b"deck a");
+        send(&mut tables[0], rng, 2, slot, b"deck b");
+
+        for player in [1, 2] {
+            let envelope = tables[player].transport().recv().unwrap();
+            tables[player].process(rng, &envelope).unwrap();
+        }
+        for player in [1, 2] {
+            match tables[player].next_event(rng).unwrap() {
+                EchoEvent::Equivocation(evidence) => {
+                    assert_eq!(evidence.sender(), 0);
+                    assert_eq!(evidence.first.slot(), slot);
+                    assert_eq!(evidence.verify(&parameters, &player_keys[0]), Ok(()));
+                    assert!(evidence.verify(&parameters, &player_keys[1]).is_err());
+                }
+                event => panic!("expected equivocation, got {:?}", event),
+            }
+        }
+
+        // Player 0 sends player 1 another shuffle for the slot already delivered, and player 2
+        // one signed for another session
+        send(&mut tables[0], rng, 1, delivered, b"deck c");
+        let other_session = Slot {
+            session: session + 1,
+            ..slot
+        };
+        send(&mut tables[0], rng, 2, other_session, b"deck d");
+
+        // Player 1 still reports the equivocation on the delivered slot
+        match tables[1].next_event(rng).unwrap() {
+            EchoEvent::Equivocation(evidence) => {
+                assert_eq!(evidence.first.slot(), delivered);
+                assert_eq!(evidence.verify(&parameters, &player_keys[0]), Ok(()));
+            }
+            event => panic!("expected equivocation, got {:?}", event),
+        }
+
+        // Player 2 rejects the header of the other session
+        assert_eq!(
+            tables[2].next_event(rng).err(),
+            Some(CardProtocolError::WrongSession(session + 1))
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use super::{check_recipient, Delivery, Envelope, Transport, MAX_MESSAGE_LEN};
+use crate::error::CardProtocolError;
+
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+/// In-process transport over channels, for tests and single-process games.
+pub struct MemoryTransport {
+    player: usize,
+    peers: Vec<Sender<Envelope>>,
+    inbox: Receiver<Envelope>,
+}
+
+impl MemoryTransport {
+    /// Create connected transports for a table of `num_of_players`, one per player in order.
+    pub fn network(num_of_players: usize) -> Vec<Self> {
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..num_of_players).map(|_| channel()).unzip();
+
+        receivers
+            .into_iter()
+            .enumerate()
+            .map(|(player, inbox)| Self {
+                player,
+                peers: senders.clone(),
+                inbox,
+            })
+            .collect()
+    }
+
+    fn deliver(
+        &self,
+        to: usize,
+        delivery: Delivery,
+        payload: &[u8],
+    ) -> Result<(), CardProtocolError> {
+        if payload.len() > MAX_MESSAGE_LEN {
+            return Err(CardProtocolError::IoError(String::from("message too long")));
+        }
+
+        self.peers[to]
+            .send(Envelope {
+                from: self.player,
+                delivery,
+                payload: payload.to_vec(),
+            })
+            .map_err(|_| CardProtocolError::Disconnected(to))
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn player(&self) -> usize {
+        self.player
+    }
+
+    fn num_of_players(&self) -> usize {
+        self.peers.len()
+    }
+
+    fn send(&mut self, to: usize, payload: &[u8]) -> Result<(), CardProtocolError> {
+        check_recipient(self.player, self.num_of_players(), to)?;
+        self.deliver(to, Delivery::Direct, payload)
+    }
+
+    fn broadcast(&mut self, payload: &[u8]) -> Result<(), CardProtocolError> {
+        for to in (0..self.num_of_players()).filter(|to| *to != self.player) {
+            self.deliver(to, Delivery::Broadcast, payload)?;
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<Envelope, CardProtocolError> {
+        // The transport holds a sender to its own inbox, so the channel never disconnects
+        self.inbox
+            .recv()
+            .map_err(|_| CardProtocolError::Disconnected(self.player))
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<Envelope>, CardProtocolError> {
+        match self.inbox.recv_timeout(timeout) {
+            Ok(envelope) => Ok(Some(envelope)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(CardProtocolError::Disconnected(self.player))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MemoryTransport;
+    use crate::discrete_log_cards;
+    use crate::error::CardProtocolError;
+    use crate::transport::{Delivery, Transport};
+    use crate::BarnettSmartProtocol;
+
+    use rand::thread_rng;
+    use std::time::Duration;
+
+    // Choose elliptic curve setting
+    type Curve = starknet_curve::Projective;
+
+    // Instantiate concrete type for our card protocol
+    type CardProtocol<'a> = discrete_log_cards::DLCards<Curve>;
+    type PublicKey = discrete_log_cards::PublicKey<Curve>;
+
+    #[test]
+    fn test_memory_transport() {
+        let rng = &mut thread_rng();
+        let parameters = CardProtocol::setup(rng, 2, 4).unwrap();
+        let (pk, _) = CardProtocol::player_keygen(rng, &parameters).unwrap();
+
+        let mut transports = MemoryTransport::network(3);
+        transports[0].broadcast_message(&pk).unwrap();
+        transports[2].send(1, b"hello").unwrap();
+
+        let envelope = transports[1].recv().unwrap();
+        assert_eq!((envelope.from, envelope.delivery), (0, Delivery::Broadcast));
+        assert_eq!(envelope.decode::<PublicKey>().unwrap(), pk);
+
+        let envelope = transports[1].recv().unwrap();
+        assert_eq!((envelope.from, envelope.delivery), (2, Delivery::Direct));
+        assert_eq!(envelope.payload, b"hello");
+
+        assert_eq!(transports[2].recv().unwrap().from, 0);
+        assert_eq!(
+            transports[0].recv_timeout(Duration::from_millis(10)),
+            Ok(None)
+        );
+        assert_eq!(
+            transports[0].send(0, b"self"),
+            Err(CardProtocolError::UnknownPlayer(0))
+        );
+    }
+}
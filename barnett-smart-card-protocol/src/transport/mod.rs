@@ -0,0 +1,100 @@
+// Delivery of protocol messages between the players of a table.
+//
+// Messages are opaque byte strings, normally the canonical serialization of a protocol value.
+// Every transport numbers players from 0 and stamps each message with its sender, so the receiving
+// side knows whose key to check proofs against.
+
+use crate::error::CardProtocolError;
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::time::Duration;
+
+mod echo;
+mod memory;
+mod tcp;
+
+pub use echo::*;
+pub use memory::*;
+pub use tcp::*;
+
+/// Largest message a transport accepts, to bound allocations on untrusted input.
+pub const MAX_MESSAGE_LEN: usize = 64 << 20;
+
+/// Whether a message was broadcast to the table or sent to one player only.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Delivery {
+    Broadcast,
+    Direct,
+}
+
+/// A received message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Envelope {
+    pub from: usize,
+    pub delivery: Delivery,
+    pub payload: Vec<u8>,
+}
+
+impl Envelope {
+    /// Deserialize the payload as a protocol value.
+    pub fn decode<T: CanonicalDeserialize>(&self) -> Result<T, CardProtocolError> {
+        T::deserialize(self.payload.as_slice())
+            .map_err(|e| CardProtocolError::IoError(e.to_string()))
+    }
+}
+
+/// Broadcast and point-to-point delivery of messages between the players of a table.
+pub trait Transport {
+    /// Index of the local player.
+    fn player(&self) -> usize;
+
+    fn num_of_players(&self) -> usize;
+
+    fn send(&mut self, to: usize, payload: &[u8]) -> Result<(), CardProtocolError>;
+
+    /// Send `payload` to every other player.
+    fn broadcast(&mut self, payload: &[u8]) -> Result<(), CardProtocolError>;
+
+    /// Block until the next message arrives.
+    fn recv(&mut self) -> Result<Envelope, CardProtocolError>;
+
+    /// Wait at most `timeout` for the next message.
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<Envelope>, CardProtocolError>;
+
+    /// Serialize `message` and send it to `to`.
+    fn send_message<T: CanonicalSerialize>(
+        &mut self,
+        to: usize,
+        message: &T,
+    ) -> Result<(), CardProtocolError> {
+        self.send(to, &encode(message)?)
+    }
+
+    /// Serialize `message` and broadcast it.
+    fn broadcast_message<T: CanonicalSerialize>(
+        &mut self,
+        message: &T,
+    ) -> Result<(), CardProtocolError> {
+        self.broadcast(&encode(message)?)
+    }
+}
+
+fn encode<T: CanonicalSerialize>(message: &T) -> Result<Vec<u8>, CardProtocolError> {
+    let mut payload = Vec::new();
+    message
+        .serialize(&mut payload)
+        .map_err(|e| CardProtocolError::IoError(e.to_string()))?;
+
+    Ok(payload)
+}
+
+fn check_recipient(
+    player: usize,
+    num_of_players: usize,
+    to: usize,
+) -> Result<(), CardProtocolError> {
+    if to >= num_of_players || to == player {
+        return Err(CardProtocolError::UnknownPlayer(to));
+    }
+    Ok(())
+}
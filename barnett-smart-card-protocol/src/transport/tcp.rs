@@ -0,0 +1,304 @@
+// A full mesh of TCP connections between the players, e.g. one process per player on localhost.
+//
+// Player i connects to every player with a lower index and accepts connections from every player
+// with a higher one; the connecting side announces its index first. Each message is framed as
+// `delivery || len || payload`, with `len` a little-endian u32.
+
+use super::{check_recipient, Delivery, Envelope, Transport, MAX_MESSAGE_LEN};
+use crate::error::CardProtocolError;
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(20);
+/// How long an accepted connection has to announce its index.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Transport over TCP connections to every other player.
+pub struct TcpTransport {
+    player: usize,
+    peers: Vec<Option<TcpStream>>,
+    inbox: Receiver<Result<Envelope, CardProtocolError>>,
+}
+
+impl TcpTransport {
+    /// Join the table of players listening at `addrs`, listening on `addrs[player]` ourselves.
+    /// Gives up if the mesh is not complete within `timeout`.
+    pub fn connect(
+        player: usize,
+        addrs: &[SocketAddr],
+        timeout: Duration,
+    ) -> Result<Self, CardProtocolError> {
+        let addr = addrs
+            .get(player)
+            .ok_or(CardProtocolError::UnknownPlayer(player))?;
+        let listener = TcpListener::bind(addr)?;
+
+        Self::with_listener(player, listener, addrs, timeout)
+    }
+
+    /// Like `connect`, with an already bound listener. `addrs[player]` is ignored.
+    pub fn with_listener(
+        player: usize,
+        listener: TcpListener,
+        addrs: &[SocketAddr],
+        timeout: Duration,
+    ) -> Result<Self, CardProtocolError> {
+        let num_of_players = addrs.len();
+        if player >= num_of_players {
+            return Err(CardProtocolError::UnknownPlayer(player));
+        }
+        let deadline = Instant::now() + timeout;
+
+        let mut peers: Vec<Option<TcpStream>> = (0..num_of_players).map(|_| None).collect();
+
+        for (peer, addr) in addrs.iter().enumerate().take(player) {
+            let mut stream = loop {
+                match TcpStream::connect(addr) {
+                    Ok(stream) => break stream,
+                    Err(e) if Instant::now() >= deadline => return Err(e.into()),
+                    Err(_) => thread::sleep(RETRY_INTERVAL),
+                }
+            };
+            stream.write_all(&(player as u32).to_le_bytes())?;
+            peers[peer] = Some(stream);
+        }
+
+        listener.set_nonblocking(true)?;
+        let mut missing = num_of_players - player - 1;
+        while missing > 0 {
+            let mut stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() != std::io::ErrorKind::WouldBlock => return Err(e.into()),
+                Err(e) if Instant::now() >= deadline => return Err(e.into()),
+                Err(_) => {
+                    thread::sleep(RETRY_INTERVAL);
+                    continue;
+                }
+            };
+            let timeout = deadline
+                .saturating_duration_since(Instant::now())
+                .min(HANDSHAKE_TIMEOUT);
+
+            // Anyone can connect to the listener, so a connection that does not announce the
+            // index of a missing player is dropped instead of failing the whole table.
+            match read_index(&mut stream, timeout) {
+                Ok(peer) if peer > player && peer < num_of_players && peers[peer].is_none() => {
+                    stream.set_read_timeout(None)?;
+                    peers[peer] = Some(stream);
+                    missing -= 1;
+                }
+                _ => continue,
+            }
+        }
+
+        let (sender, inbox) = channel();
+        for (peer, stream) in peers.iter().enumerate() {
+            if let Some(stream) = stream {
+                let stream = stream.try_clone()?;
+                let sender = sender.clone();
+                thread::spawn(move || read_frames(peer, stream, sender));
+            }
+        }
+
+        Ok(Self {
+            player,
+            peers,
+            inbox,
+        })
+    }
+
+    fn deliver(
+        &mut self,
+        to: usize,
+        delivery: Delivery,
+        payload: &[u8],
+    ) -> Result<(), CardProtocolError> {
+        if payload.len() > MAX_MESSAGE_LEN {
+            return Err(CardProtocolError::IoError(String::from("message too long")));
+        }
+
+        let stream = self.peers[to]
+            .as_mut()
+            .ok_or(CardProtocolError::UnknownPlayer(to))?;
+
+        let mut frame = Vec::with_capacity(5 + payload.len());
+        frame.push(match delivery {
+            Delivery::Broadcast => 0,
+            Delivery::Direct => 1,
+        });
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(payload);
+
+        stream
+            .write_all(&frame)
+            .map_err(|_| CardProtocolError::Disconnected(to))
+    }
+}
+
+/// Read the index a connecting player announces, waiting at most `timeout`.
+fn read_index(stream: &mut TcpStream, timeout: Duration) -> std::io::Result<usize> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(timeout))?;
+
+    let mut index = [0u8; 4];
+    stream.read_exact(&mut index)?;
+    Ok(u32::from_le_bytes(index) as usize)
+}
+
+fn read_frames(
+    peer: usize,
+    mut stream: TcpStream,
+    sender: Sender<Result<Envelope, CardProtocolError>>,
+) {
+    loop {
+        let envelope = read_frame(peer, &mut stream);
+        let failed = envelope.is_err();
+        if sender.send(envelope).is_err() || failed {
+            return;
+        }
+    }
+}
+
+fn read_frame(peer: usize, stream: &mut TcpStream) -> Result<Envelope, CardProtocolError> {
+    let mut header = [0u8; 5];
+    stream
+        .read_exact(&mut header)
+        .map_err(|_| CardProtocolError::Disconnected(peer))?;
+
+    let delivery = match header[0] {
+        0 => Delivery::Broadcast,
+        1 => Delivery::Direct,
+        _ => return Err(CardProtocolError::IoError(String::from("malformed frame"))),
+    };
+    let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    if len > MAX_MESSAGE_LEN {
+        return Err(CardProtocolError::IoError(String::from("message too long")));
+    }
+
+    let mut payload = vec![0u8; len];
+    stream
+        .read_exact(&mut payload)
+        .map_err(|_| CardProtocolError::Disconnected(peer))?;
+
+    Ok(Envelope {
+        from: peer,
+        delivery,
+        payload,
+    })
+}
+
+impl Transport for TcpTransport {
+    fn player(&self) -> usize {
+        self.player
+    }
+
+    fn num_of_players(&self) -> usize {
+        self.peers.len()
+    }
+
+    fn send(&mut self, to: usize, payload: &[u8]) -> Result<(), CardProtocolError> {
+        check_recipient(self.player, self.num_of_players(), to)?;
+        self.deliver(to, Delivery::Direct, payload)
+    }
+
+    fn broadcast(&mut self, payload: &[u8]) -> Result<(), CardProtocolError> {
+        let player = self.player;
+        for to in (0..self.num_of_players()).filter(|to| *to != player) {
+            self.deliver(to, Delivery::Broadcast, payload)?;
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<Envelope, CardProtocolError> {
+        match self.inbox.recv() {
+            Ok(envelope) => envelope,
+            Err(_) => Err(CardProtocolError::Disconnected(self.player)),
+        }
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<Envelope>, CardProtocolError> {
+        match self.inbox.recv_timeout(timeout) {
+            Ok(envelope) => envelope.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(CardProtocolError::Disconnected(self.player))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TcpTransport;
+    use crate::transport::{Delivery, Transport};
+
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_tcp_transport() {
+        let listeners = (0..3)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect::<Vec<_>>();
+        let addrs = listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap())
+            .collect::<Vec<_>>();
+
+        // Connections that do not announce a missing player are dropped
+        let mut stray = TcpStream::connect(addrs[0]).unwrap();
+        stray.write_all(&7u32.to_le_bytes()).unwrap();
+        let _silent = TcpStream::connect(addrs[0]).unwrap();
+
+        let handles = listeners
+            .into_iter()
+            .enumerate()
+            .map(|(player, listener)| {
+                let addrs = addrs.clone();
+                thread::spawn(move || {
+                    let mut transport = TcpTransport::with_listener(
+                        player,
+                        listener,
+                        &addrs,
+                        Duration::from_secs(5),
+                    )
+                    .unwrap();
+
+                    transport.broadcast(&[player as u8]).unwrap();
+                    transport.send((player + 1) % 3, b"next").unwrap();
+
+                    let mut received = (0..3)
+                        .map(|_| {
+                            let envelope = transport.recv().unwrap();
+                            (envelope.from, envelope.delivery, envelope.payload)
+                        })
+                        .collect::<Vec<_>>();
+                    received.sort();
+                    received
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (player, handle) in handles.into_iter().enumerate() {
+            let previous = (player + 2) % 3;
+            let mut expected = (0..3)
+                .filter(|from| *from != player)
+                .map(|from| (from, Delivery::Broadcast, vec![from as u8]))
+                .chain(std::iter::once((
+                    previous,
+                    Delivery::Direct,
+                    b"next".to_vec(),
+                )))
+                .collect::<Vec<_>>();
+            expected.sort();
+
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    }
+}
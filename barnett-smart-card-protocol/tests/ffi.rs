@@ -0,0 +1,53 @@
+// Builds the C harness in tests/ffi against the cdylib and the header in include/, then runs it.
+#![cfg(unix)]
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const LIB_NAME: &str = "barnett_smart_card_protocol";
+
+/// The cdylib built next to this test binary, in `target/<profile>/deps` or its parent.
+fn cdylib() -> PathBuf {
+    let file_name = format!(
+        "{}{}{}",
+        env::consts::DLL_PREFIX,
+        LIB_NAME,
+        env::consts::DLL_SUFFIX
+    );
+    let exe = env::current_exe().unwrap();
+    exe.ancestors()
+        .skip(1)
+        .take(2)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| panic!("{} not found next to {}", file_name, exe.display()))
+}
+
+#[test]
+fn test_c_harness() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let lib = cdylib();
+    let lib_dir = lib.parent().unwrap();
+    let harness = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ffi_harness");
+
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(manifest_dir.join("include"))
+        .arg(manifest_dir.join("tests/ffi/harness.c"))
+        .arg(&lib)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-o")
+        .arg(&harness)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "failed to compile the C harness");
+
+    let output = Command::new(&harness).output().unwrap();
+    assert!(
+        output.status.success(),
+        "C harness failed:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
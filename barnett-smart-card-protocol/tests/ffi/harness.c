@@ -0,0 +1,163 @@
+/* Plays a full round through the C ABI: key setup, masking, a shuffle by every player, and the
+ * reveal of a card. Exits with a non-zero status on the first failed check. */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "mental_poker.h"
+
+#define NUM_PLAYERS 3
+
+#define CHECK(expr, expected)                                                                      \
+  do {                                                                                             \
+    MpStatus status_ = (expr);                                                                     \
+    if (status_ != (expected)) {                                                                   \
+      fprintf(stderr, "%s:%d: %s returned %d, expected %d\n", __FILE__, __LINE__, #expr,          \
+              (int)status_, (int)(expected));                                                      \
+      exit(1);                                                                                     \
+    }                                                                                              \
+  } while (0)
+
+#define OK(expr) CHECK(expr, MP_STATUS_OK)
+
+static MpSlice slice(const MpBuffer *buffer) {
+  MpSlice s = {buffer->data, buffer->len};
+  return s;
+}
+
+static MpSlice str(const char *s) {
+  MpSlice out = {(const uint8_t *)s, strlen(s)};
+  return out;
+}
+
+int main(void) {
+  static const char *names[NUM_PLAYERS] = {"Andrija", "Kobi", "Nico"};
+  MpParameters *params = NULL;
+  MpKeypair *players[NUM_PLAYERS];
+  MpBuffer pks[NUM_PLAYERS], key_proofs[NUM_PLAYERS], tokens[NUM_PLAYERS];
+  MpSlice pk_slices[NUM_PLAYERS], proof_slices[NUM_PLAYERS], info_slices[NUM_PLAYERS];
+  MpSlice token_slices[NUM_PLAYERS];
+  MpBuffer shared_key, deck, card, masked_card, open_card;
+  size_t num_cards, index, i;
+
+  OK(mp_parameters_setup(2, 2, &params));
+  OK(mp_parameters_num_cards(params, &num_cards));
+  if (num_cards != 4) {
+    fprintf(stderr, "expected 4 cards, got %zu\n", num_cards);
+    return 1;
+  }
+
+  /* Parameters survive an encoding round trip */
+  {
+    MpBuffer bytes;
+    MpParameters *decoded = NULL;
+    OK(mp_parameters_to_bytes(params, &bytes));
+    OK(mp_parameters_from_bytes(slice(&bytes), &decoded));
+    mp_parameters_free(decoded);
+    mp_buffer_free(&bytes);
+  }
+
+  for (i = 0; i < NUM_PLAYERS; i++) {
+    OK(mp_keygen(params, &players[i]));
+    OK(mp_keypair_public_key(players[i], &pks[i]));
+    OK(mp_prove_key_ownership(params, players[i], str(names[i]), &key_proofs[i]));
+    pk_slices[i] = slice(&pks[i]);
+    proof_slices[i] = slice(&key_proofs[i]);
+    info_slices[i] = str(names[i]);
+  }
+
+  /* A proof bound to another player's info is rejected */
+  info_slices[0] = str("Mallory");
+  CHECK(mp_aggregate_key(params, pk_slices, proof_slices, info_slices, NUM_PLAYERS, &shared_key),
+        MP_STATUS_INVALID_PROOF);
+  info_slices[0] = str(names[0]);
+  OK(mp_aggregate_key(params, pk_slices, proof_slices, info_slices, NUM_PLAYERS, &shared_key));
+
+  /* Masking a single card */
+  {
+    MpBuffer proof;
+    OK(mp_card(params, 1, &card));
+    OK(mp_mask(params, slice(&shared_key), slice(&card), &masked_card, &proof));
+    OK(mp_verify_mask(params, slice(&shared_key), slice(&card), slice(&masked_card),
+                      slice(&proof)));
+    mp_buffer_free(&proof);
+    mp_buffer_free(&masked_card);
+    mp_buffer_free(&card);
+  }
+
+  OK(mp_mask_deck(params, slice(&shared_key), &deck));
+
+  for (i = 0; i < NUM_PLAYERS; i++) {
+    MpBuffer shuffled, proof;
+    OK(mp_shuffle_and_remask(params, slice(&shared_key), slice(&deck), &shuffled, &proof));
+    OK(mp_verify_shuffle(params, slice(&shared_key), slice(&deck), slice(&shuffled),
+                         slice(&proof)));
+    /* The proof does not hold for the deck against itself */
+    CHECK(mp_verify_shuffle(params, slice(&shared_key), slice(&shuffled), slice(&shuffled),
+                            slice(&proof)),
+          MP_STATUS_INVALID_PROOF);
+    mp_buffer_free(&proof);
+    mp_buffer_free(&deck);
+    deck = shuffled;
+  }
+
+  /* Every player reveals the top card */
+  OK(mp_deck_card(slice(&deck), 0, &masked_card));
+  for (i = 0; i < NUM_PLAYERS; i++) {
+    OK(mp_compute_reveal_token(params, players[i], slice(&masked_card), &tokens[i]));
+    token_slices[i] = slice(&tokens[i]);
+  }
+  /* Without every token the card stays masked */
+  OK(mp_unmask(params, token_slices, NUM_PLAYERS - 1, slice(&masked_card), &open_card));
+  CHECK(mp_card_index(params, slice(&open_card), &index), MP_STATUS_PROTOCOL_ERROR);
+  mp_buffer_free(&open_card);
+  OK(mp_unmask(params, token_slices, NUM_PLAYERS, slice(&masked_card), &open_card));
+  OK(mp_card_index(params, slice(&open_card), &index));
+  if (index >= num_cards) {
+    fprintf(stderr, "card index %zu out of range\n", index);
+    return 1;
+  }
+  printf("revealed card %zu\n", index);
+
+  /* Keypair export and import */
+  {
+    MpBuffer exported, pk;
+    MpKeypair *imported = NULL;
+    OK(mp_keypair_export(players[0], str("hunter2"), &exported));
+    CHECK(mp_keypair_import(slice(&exported), str("wrong"), &imported), MP_STATUS_PROTOCOL_ERROR);
+    OK(mp_keypair_import(slice(&exported), str("hunter2"), &imported));
+    OK(mp_keypair_public_key(imported, &pk));
+    if (pk.len != pks[0].len || memcmp(pk.data, pks[0].data, pk.len) != 0) {
+      fprintf(stderr, "imported keypair has another public key\n");
+      return 1;
+    }
+    mp_buffer_free(&pk);
+    mp_keypair_free(imported);
+    mp_buffer_free(&exported);
+  }
+
+  /* Malformed input and null pointers */
+  {
+    static const uint8_t garbage[3] = {1, 2, 3};
+    MpSlice bad = {garbage, sizeof(garbage)};
+    MpBuffer unused;
+    CHECK(mp_deck_card(bad, 0, &unused), MP_STATUS_INVALID_ENCODING);
+    CHECK(mp_keygen(NULL, &players[0]), MP_STATUS_NULL_POINTER);
+    CHECK(mp_mask_deck(params, slice(&shared_key), NULL), MP_STATUS_NULL_POINTER);
+  }
+
+  mp_buffer_free(&open_card);
+  mp_buffer_free(&masked_card);
+  mp_buffer_free(&deck);
+  mp_buffer_free(&shared_key);
+  for (i = 0; i < NUM_PLAYERS; i++) {
+    mp_buffer_free(&tokens[i]);
+    mp_buffer_free(&key_proofs[i]);
+    mp_buffer_free(&pks[i]);
+    mp_keypair_free(players[i]);
+  }
+  mp_parameters_free(params);
+
+  return 0;
+}